@@ -0,0 +1,188 @@
+use chrono::{DateTime, Utc};
+use rust_decimal::Decimal;
+use rusty_money::{iso::Currency, Money};
+use serenity::model::prelude::{ChannelId, GuildId, MessageId, UserId};
+
+/// Discord allows 25 buttons per message, one row of five is kept free for bill actions.
+pub const MAX_PAYERS: usize = 20;
+
+/// The longest a bill's title can be, which keeps its message within discord's limit along
+/// with everyone's shares.
+pub const MAX_TITLE_LENGTH: u16 = 100;
+
+/// The longest a bill's payment method can be, for the same reason as `MAX_TITLE_LENGTH`.
+pub const MAX_METHOD_LENGTH: u16 = 300;
+
+/// A bill posted to a guild channel, along with the share each payer owes.
+#[derive(Clone, Debug)]
+pub struct Bill {
+    pub id: i64,
+    pub guild_id: GuildId,
+    pub channel_id: ChannelId,
+    pub message_id: Option<MessageId>,
+    pub creator_id: UserId,
+    pub creator_name: String,
+    pub title: String,
+    pub amount: Decimal,
+    pub currency: &'static Currency,
+    pub method: String,
+    pub created_at: DateTime<Utc>,
+    /// When payers are expected to have paid by, shares still owed after it are overdue.
+    pub due_at: Option<DateTime<Utc>>,
+    /// The rate into the guild's currency when the bill was created, if it's in another currency.
+    pub rate: Option<BillRate>,
+    /// Set once the creator voids the bill, after which nobody owes anything on it.
+    pub cancellation: Option<Cancellation>,
+    pub shares: Vec<BillShare>,
+    /// What was bought, for bills split by item instead of by portion. Empty otherwise.
+    pub items: Vec<BillItem>,
+}
+
+#[derive(Clone, Debug)]
+pub struct Cancellation {
+    pub cancelled_by: UserId,
+    pub reason: String,
+    pub cancelled_at: DateTime<Utc>,
+}
+
+/// How many units of `currency` one unit of a bill's currency was worth.
+#[derive(Clone, Copy, Debug)]
+pub struct BillRate {
+    pub currency: &'static Currency,
+    pub rate: Decimal,
+}
+
+/// A line item on an itemized bill, split evenly between the payers it's assigned to.
+#[derive(Clone, Debug)]
+pub struct BillItem {
+    pub name: String,
+    pub amount: Decimal,
+    pub payers: Vec<UserId>,
+}
+
+/// The portion of a bill owed by a single payer.
+#[derive(Clone, Debug)]
+pub struct BillShare {
+    pub user_id: UserId,
+    /// The payer's display name when the bill was created.
+    pub name: String,
+    pub amount: Decimal,
+    pub status: ShareStatus,
+    pub claimed_at: Option<DateTime<Utc>>,
+    pub settled_at: Option<DateTime<Utc>>,
+}
+
+/// A share starts out owed, is claimed once the payer says they sent the money,
+/// and only settles once the bill creator confirms they received it.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum ShareStatus {
+    Owed,
+    Claimed,
+    Settled,
+}
+
+impl ShareStatus {
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            ShareStatus::Owed => "owed",
+            ShareStatus::Claimed => "claimed",
+            ShareStatus::Settled => "settled",
+        }
+    }
+
+    pub fn parse(s: &str) -> anyhow::Result<ShareStatus> {
+        match s {
+            "owed" => Ok(ShareStatus::Owed),
+            "claimed" => Ok(ShareStatus::Claimed),
+            "settled" => Ok(ShareStatus::Settled),
+            _ => Err(anyhow::anyhow!("unknown share status: {}", s)),
+        }
+    }
+}
+
+/// Where a bill stands as a whole.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum BillStatus {
+    Open,
+    Settled,
+    Cancelled,
+}
+
+impl BillStatus {
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            BillStatus::Open => "open",
+            BillStatus::Settled => "settled",
+            BillStatus::Cancelled => "cancelled",
+        }
+    }
+
+    pub fn parse(s: &str) -> anyhow::Result<BillStatus> {
+        match s {
+            "open" => Ok(BillStatus::Open),
+            "settled" => Ok(BillStatus::Settled),
+            "cancelled" => Ok(BillStatus::Cancelled),
+            _ => Err(anyhow::anyhow!("unknown bill status: {}", s)),
+        }
+    }
+}
+
+impl Bill {
+    pub fn total(&self) -> Money<'static, Currency> {
+        Money::from_decimal(self.amount, self.currency)
+    }
+
+    pub fn share(&self, user_id: UserId) -> Option<&BillShare> {
+        self.shares.iter().find(|share| share.user_id == user_id)
+    }
+
+    /// Shares owed by someone other than the creator, who can't owe themselves.
+    pub fn payer_shares(&self) -> impl Iterator<Item = &BillShare> {
+        self.shares
+            .iter()
+            .filter(|share| share.user_id != self.creator_id)
+    }
+
+    /// What the creator gets back in total, which excludes their own share if they have one.
+    pub fn owed_to_creator(&self) -> Money<'static, Currency> {
+        Money::from_decimal(
+            self.payer_shares().map(|share| share.amount).sum(),
+            self.currency,
+        )
+    }
+
+    pub fn is_cancelled(&self) -> bool {
+        self.cancellation.is_some()
+    }
+
+    pub fn status(&self) -> BillStatus {
+        if self.is_cancelled() {
+            BillStatus::Cancelled
+        } else if self.is_settled() {
+            BillStatus::Settled
+        } else {
+            BillStatus::Open
+        }
+    }
+
+    pub fn is_settled(&self) -> bool {
+        self.shares
+            .iter()
+            .all(|share| share.status == ShareStatus::Settled)
+    }
+
+    /// Whether the bill's due date has passed and the share still hasn't been sent. Shares
+    /// awaiting confirmation aren't overdue, the payer already did their part.
+    pub fn is_overdue(&self, share: &BillShare, now: DateTime<Utc>) -> bool {
+        !self.is_cancelled()
+            && share.user_id != self.creator_id
+            && share.status == ShareStatus::Owed
+            && self.due_at.is_some_and(|due_at| due_at < now)
+    }
+
+    pub fn overdue_shares(&self, now: DateTime<Utc>) -> impl Iterator<Item = &BillShare> {
+        self.shares
+            .iter()
+            .filter(move |share| self.is_overdue(share, now))
+    }
+}
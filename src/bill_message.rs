@@ -0,0 +1,439 @@
+use chrono::Utc;
+use log::{error, info, warn};
+use rust_decimal::Decimal;
+use rusty_money::Money;
+use serenity::{
+    builder::CreateComponents,
+    model::prelude::component::ButtonStyle,
+    prelude::{Context, Mentionable},
+};
+
+use crate::bill::{Bill, BillShare, ShareStatus};
+use crate::items;
+use crate::rates;
+use crate::store::BillStore;
+
+pub const MARK_PAID_ID: &str = "bill_paid";
+pub const EDIT_ID: &str = "bill_edit";
+pub const CANCEL_ID: &str = "bill_cancel";
+pub const NUDGE_ID: &str = "bill_nudge";
+
+/// Discord rejects messages with more characters than this.
+pub const MAX_CONTENT_LENGTH: usize = 2000;
+
+/// The shortest an item breakdown gets cut down to before it's left out altogether.
+const MIN_BREAKDOWN_LENGTH: usize = 20;
+
+/// Renders the text of a bill's channel message from its current state. Itemized bills with
+/// many payers can outgrow a message, in which case what each payer had is shortened or left
+/// out, since `/bill show` lists the items in full. Whatever still doesn't fit is cut off.
+pub fn content(bill: &Bill) -> String {
+    let full = render(bill, Some(usize::MAX));
+    if full.chars().count() <= MAX_CONTENT_LENGTH {
+        return full;
+    }
+    if bill.items.is_empty() {
+        return truncate(&full, MAX_CONTENT_LENGTH);
+    }
+
+    let without = render(bill, None);
+    let spare = MAX_CONTENT_LENGTH.saturating_sub(without.chars().count());
+    // every breakdown takes its own line, which starts with `\n└ ` and is struck out with `~~`
+    // on cancelled bills
+    let per_payer = (spare / bill.shares.len()).saturating_sub(7);
+    if per_payer >= MIN_BREAKDOWN_LENGTH {
+        let shortened = render(bill, Some(per_payer));
+        if shortened.chars().count() <= MAX_CONTENT_LENGTH {
+            return shortened;
+        }
+    }
+
+    truncate(&without, MAX_CONTENT_LENGTH)
+}
+
+/// Renders the message with each payer's item breakdown cut to `breakdown_length` characters,
+/// or without breakdowns and a pointer to `/bill show` if it's `None`.
+fn render(bill: &Bill, breakdown_length: Option<usize>) -> String {
+    let header = if bill.is_cancelled() {
+        "**🚫 BILL CANCELLED 🚫**"
+    } else if bill.is_settled() {
+        "**✅ BILL SETTLED ✅**"
+    } else {
+        "**🚨 AYO NEW BILL AVAILABLE 🚨**"
+    };
+
+    let mut amounts = String::from("");
+    for share in &bill.shares {
+        amounts.push_str(&format!(
+            "\n{} pays {}",
+            share.user_id.mention(),
+            Money::from_decimal(share.amount, bill.currency)
+        ));
+        if share.user_id == bill.creator_id {
+            amounts.push_str(" (their own share)");
+        }
+        if let (false, Some(length)) = (bill.items.is_empty(), breakdown_length) {
+            amounts.push_str(&format!(
+                "\n└ {}",
+                truncate(&item_breakdown(bill, share), length)
+            ));
+        }
+    }
+    if !bill.items.is_empty() && breakdown_length.is_none() {
+        amounts.push_str(&format!(
+            "\nSee `/bill show bill:{}` for what everyone had",
+            bill.id
+        ));
+    }
+
+    let claimed = create_payer_mention_string(
+        bill.payer_shares()
+            .filter(|s| s.status == ShareStatus::Claimed),
+    );
+    let claimed = if claimed.is_empty() {
+        claimed
+    } else {
+        format!("\nAwaiting confirmation: {}", claimed)
+    };
+
+    // bills in another currency than their guild's show what they're worth in it
+    let converted = bill
+        .rate
+        .and_then(|rate| rates::convert(bill.total(), rate.currency, rate.rate).ok())
+        .map(|converted| format!(" (≈ {} {})", converted, converted.currency().iso_alpha_code))
+        .unwrap_or_default();
+
+    let due = bill
+        .due_at
+        .map(|due_at| {
+            format!(
+                "\nDue: <t:{}:D> (<t:{}:R>)",
+                due_at.timestamp(),
+                due_at.timestamp()
+            )
+        })
+        .unwrap_or_default();
+
+    // the message is only redrawn when something happens to the bill, so this is as of then
+    let still_owes = if bill.overdue_shares(Utc::now()).next().is_some() {
+        "⏰ Overdue"
+    } else {
+        "Still owes"
+    };
+
+    let details = format!(
+        "Title: {} (bill #{})\nTotal amount: {}{}\nBill created by: {}\nOwed to {}: {}\nPayment method: {}{}\nPayers:{}\n\nPaid: {}{}\n{}: {}",
+        bill.title,
+        bill.id,
+        bill.total(),
+        converted,
+        bill.creator_name,
+        bill.creator_name,
+        bill.owed_to_creator(),
+        bill.method,
+        due,
+        amounts,
+        or_nobody(create_payer_mention_string(
+            bill.payer_shares().filter(|s| s.status == ShareStatus::Settled)
+        )),
+        claimed,
+        still_owes,
+        or_nobody(create_payer_mention_string(
+            bill.payer_shares().filter(|s| s.status == ShareStatus::Owed)
+        )),
+    );
+
+    match &bill.cancellation {
+        Some(cancellation) => format!(
+            "{}\n >>> {}\n\nCancelled by {} <t:{}:R>: {}",
+            header,
+            strikethrough(&details),
+            cancellation.cancelled_by.mention(),
+            cancellation.cancelled_at.timestamp(),
+            if cancellation.reason.is_empty() {
+                "no reason given"
+            } else {
+                &cancellation.reason
+            }
+        ),
+        None => format!("{}\n >>> {}\n\n *Thanks lads ❤️*", header, details),
+    }
+}
+
+/// Lists what a payer had on an itemized bill, with their part of anything the total has on top
+/// of the items, like tax and tip.
+fn item_breakdown(bill: &Bill, share: &BillShare) -> String {
+    let breakdown = items::breakdown(&bill.items, share.user_id, bill.currency);
+
+    let mut parts: Vec<String> = breakdown
+        .iter()
+        .map(|(item, amount)| {
+            let amount = Money::from_decimal(*amount, bill.currency);
+            match item.payers.len() {
+                1 => format!("{} {}", item.name, amount),
+                n => format!("{} {} (split {} ways)", item.name, amount, n),
+            }
+        })
+        .collect();
+
+    let extra = share.amount - breakdown.iter().map(|(_, amount)| amount).sum::<Decimal>();
+    if extra > Decimal::ZERO {
+        parts.push(format!(
+            "{} tax and tip",
+            Money::from_decimal(extra, bill.currency)
+        ));
+    }
+
+    parts.join(", ")
+}
+
+/// Cuts `text` down to at most `max` characters, ending it with an ellipsis if anything was cut.
+pub fn truncate(text: &str, max: usize) -> String {
+    if text.chars().count() <= max {
+        return text.to_owned();
+    }
+
+    let mut truncated: String = text.chars().take(max.saturating_sub(1)).collect();
+    truncated.push('…');
+    truncated
+}
+
+/// Strikes out every line on its own, since discord doesn't carry strikethrough across lines.
+fn strikethrough(text: &str) -> String {
+    text.lines()
+        .map(|line| {
+            if line.trim().is_empty() {
+                line.to_owned()
+            } else {
+                format!("~~{}~~", line)
+            }
+        })
+        .collect::<Vec<_>>()
+        .join("\n")
+}
+
+/// Adds a button per payer. Payers press their own button once they've sent the money,
+/// the bill creator can press any button to settle or reopen that payer's share. The last
+/// row holds the buttons for the bill as a whole. Cancelled bills have no buttons at all.
+pub fn components<'a>(bill: &Bill, cmp: &'a mut CreateComponents) -> &'a mut CreateComponents {
+    if bill.is_cancelled() {
+        return cmp;
+    }
+
+    let shares: Vec<&BillShare> = bill.payer_shares().collect();
+    for row_shares in shares.chunks(5) {
+        cmp.create_action_row(|row| {
+            for share in row_shares {
+                row.create_button(|btn| {
+                    let (style, label) = match share.status {
+                        ShareStatus::Owed => (
+                            ButtonStyle::Secondary,
+                            format!("{} - I sent it", share.name),
+                        ),
+                        ShareStatus::Claimed => {
+                            (ButtonStyle::Primary, format!("{} sent it", share.name))
+                        }
+                        ShareStatus::Settled => {
+                            (ButtonStyle::Success, format!("{} paid", share.name))
+                        }
+                    };
+
+                    btn.custom_id(format!("{}:{}:{}", MARK_PAID_ID, bill.id, share.user_id))
+                        .style(style)
+                        .label(label)
+                });
+            }
+            row
+        });
+    }
+
+    cmp.create_action_row(|row| {
+        row.create_button(|btn| {
+            btn.custom_id(format!("{}:{}", EDIT_ID, bill.id))
+                .style(ButtonStyle::Secondary)
+                .label("Edit")
+        })
+        .create_button(|btn| {
+            btn.custom_id(format!("{}:{}", CANCEL_ID, bill.id))
+                .style(ButtonStyle::Danger)
+                .label("Cancel")
+        })
+        .create_button(|btn| {
+            btn.custom_id(format!("{}:{}", NUDGE_ID, bill.id))
+                .style(ButtonStyle::Secondary)
+                .label("Nudge")
+                .disabled(bill.is_settled())
+        })
+    })
+}
+
+/// Posts a saved bill to its channel and records the message it was posted in. A bill that
+/// can't be posted is deleted again.
+pub async fn send(ctx: &Context, store: &dyn BillStore, bill: &mut Bill) -> anyhow::Result<()> {
+    let message = match bill
+        .channel_id
+        .send_message(&ctx.http, |msg| {
+            msg.content(content(bill))
+                .components(|cmp| components(bill, cmp))
+        })
+        .await
+    {
+        Ok(message) => message,
+        Err(why) => {
+            discard(store, bill.id);
+            return Err(why.into());
+        }
+    };
+    store.set_message_id(bill.id, message.id)?;
+    bill.message_id = Some(message.id);
+
+    Ok(())
+}
+
+/// Deletes a saved bill whose message failed to post. Nobody can see or settle it, so it
+/// shouldn't count towards balances, settle plans or reminders either.
+pub fn discard(store: &dyn BillStore, bill_id: i64) {
+    match store.delete_bill(bill_id) {
+        Ok(()) => info!("deleted bill {} since its message failed to post", bill_id),
+        Err(why) => error!("failed to delete unposted bill {}: {:?}", bill_id, why),
+    }
+}
+
+/// Edits the bill's channel message to match its current state.
+pub async fn update(ctx: &Context, bill: &Bill) -> anyhow::Result<()> {
+    let message_id = match bill.message_id {
+        Some(id) => id,
+        None => {
+            warn!("bill {} has no message to update", bill.id);
+            return Ok(());
+        }
+    };
+
+    bill.channel_id
+        .edit_message(&ctx.http, message_id, |msg| {
+            msg.content(content(bill))
+                .components(|cmp| components(bill, cmp))
+        })
+        .await?;
+
+    Ok(())
+}
+
+#[allow(clippy::single_char_add_str)]
+pub fn create_payer_mention_string<'a>(payers: impl IntoIterator<Item = &'a BillShare>) -> String {
+    let mut s = String::from("");
+
+    for payer in payers {
+        s.push_str(payer.user_id.mention().to_string().as_str());
+        s.push_str(" ");
+    }
+
+    s.trim_end().to_owned()
+}
+
+fn or_nobody(mentions: String) -> String {
+    if mentions.is_empty() {
+        return String::from("nobody");
+    }
+
+    mentions
+}
+
+#[cfg(test)]
+mod tests {
+    use rusty_money::iso;
+    use serenity::model::prelude::{ChannelId, GuildId, UserId};
+
+    use super::*;
+    use crate::bill::{BillItem, Cancellation};
+
+    /// An itemized bill with the most payers a bill can have, each on a few long named items.
+    fn big_bill() -> Bill {
+        let payers: Vec<UserId> = (0..20)
+            .map(|i| UserId(100_000_000_000_000_000 + i))
+            .collect();
+        let items: Vec<BillItem> = (0..60)
+            .map(|i| BillItem {
+                name: format!("Extra large house special number {}", i),
+                amount: Decimal::new(1999, 2),
+                payers: vec![payers[i % 20], payers[(i + 1) % 20]],
+            })
+            .collect();
+        let total = Decimal::new(1999 * 60, 2);
+
+        Bill {
+            id: 12345,
+            guild_id: GuildId(1),
+            channel_id: ChannelId(1),
+            message_id: None,
+            creator_id: payers[0],
+            creator_name: String::from("Jacob"),
+            title: String::from("Team dinner"),
+            amount: total,
+            currency: iso::CAD,
+            method: String::from("etransfer"),
+            created_at: Utc::now(),
+            due_at: None,
+            rate: None,
+            cancellation: None,
+            shares: payers
+                .iter()
+                .map(|user_id| BillShare {
+                    user_id: *user_id,
+                    name: user_id.to_string(),
+                    amount: total / Decimal::from(20),
+                    status: ShareStatus::Owed,
+                    claimed_at: None,
+                    settled_at: None,
+                })
+                .collect(),
+            items,
+        }
+    }
+
+    #[test]
+    fn small_bills_list_everything_each_payer_had() {
+        let mut bill = big_bill();
+        bill.items.truncate(2);
+
+        let content = content(&bill);
+        assert!(content.contains("Extra large house special number 1 "));
+        assert!(!content.contains('…'));
+    }
+
+    #[test]
+    fn long_itemized_bills_fit_in_a_message() {
+        let mut bill = big_bill();
+        assert!(render(&bill, Some(usize::MAX)).chars().count() > MAX_CONTENT_LENGTH);
+        assert!(content(&bill).chars().count() <= MAX_CONTENT_LENGTH);
+
+        bill.cancellation = Some(Cancellation {
+            cancelled_by: bill.creator_id,
+            reason: String::from("wrong restaurant"),
+            cancelled_at: Utc::now(),
+        });
+        assert!(content(&bill).chars().count() <= MAX_CONTENT_LENGTH);
+    }
+
+    #[test]
+    fn breakdowns_are_dropped_when_they_cannot_be_shortened_enough() {
+        let mut bill = big_bill();
+        bill.title = "a".repeat(1000);
+
+        let content = content(&bill);
+        assert!(content.chars().count() <= MAX_CONTENT_LENGTH);
+        assert!(content.contains("/bill show bill:12345"));
+    }
+
+    #[test]
+    fn bills_without_items_are_cut_to_fit_too() {
+        let mut bill = big_bill();
+        bill.items.clear();
+        bill.title = "a".repeat(1000);
+        bill.method = "b".repeat(1000);
+
+        let content = content(&bill);
+        assert_eq!(content.chars().count(), MAX_CONTENT_LENGTH);
+        assert!(content.ends_with('…'));
+    }
+}
@@ -0,0 +1,195 @@
+use chrono::{DateTime, Utc};
+use rusty_money::Money;
+use serenity::builder::CreateApplicationCommandOption;
+use serenity::model::prelude::command::CommandOptionType;
+use serenity::model::prelude::interaction::application_command::{
+    ApplicationCommandInteraction, CommandDataOptionValue,
+};
+use serenity::model::prelude::interaction::InteractionResponseType::ChannelMessageWithSource;
+use serenity::model::prelude::UserId;
+use serenity::prelude::{Context, Mentionable};
+
+use crate::ledger::{self, Debt};
+use crate::rates::{self, RateProvider};
+use crate::store::BillStore;
+
+pub fn register(opt: &mut CreateApplicationCommandOption) -> &mut CreateApplicationCommandOption {
+    opt.kind(CommandOptionType::SubCommand)
+        .name("balance")
+        .description("Show how much you owe and are owed across all unsettled bills")
+        .create_sub_option(|opt| {
+            opt.kind(CommandOptionType::User)
+                .name("user")
+                .description("Only show the balance between you and this person")
+        })
+}
+
+pub async fn respond(
+    ctx: &Context,
+    cmd: &ApplicationCommandInteraction,
+    store: &dyn BillStore,
+    rates: &dyn RateProvider,
+) -> anyhow::Result<()> {
+    let content = match cmd.guild_id {
+        Some(guild_id) => {
+            let other = cmd
+                .data
+                .options
+                .first()
+                .and_then(|sub| sub.options.iter().find(|opt| opt.name == "user"))
+                .and_then(|opt| match &opt.resolved {
+                    Some(CommandDataOptionValue::User(user, _)) => Some(user.id),
+                    _ => None,
+                });
+
+            let bills = store.open_bills(guild_id)?;
+            let currency = store.guild_settings(guild_id)?.currency;
+            let rates = rates.rates(guild_id)?;
+            let debts =
+                ledger::convert_debts(ledger::outstanding_debts(&bills), currency, |bill| {
+                    rates::bill_rate(bill, currency, &rates)
+                })?;
+
+            match other {
+                Some(other) if other == cmd.user.id => {
+                    String::from("You can't owe yourself money, pick someone else")
+                }
+                Some(other) => pair_summary(&debts, cmd.user.id, other),
+                None => summary(&debts, cmd.user.id),
+            }
+        }
+        None => String::from("Balances are only available in servers"),
+    };
+
+    cmd.create_interaction_response(&ctx.http, |res| {
+        res.kind(ChannelMessageWithSource)
+            .interaction_response_data(|msg| msg.ephemeral(true).content(content))
+    })
+    .await?;
+
+    Ok(())
+}
+
+fn summary(debts: &[Debt], user: UserId) -> String {
+    let balances = ledger::net_balances(debts, user);
+    if balances.is_empty() {
+        return String::from("You're all square 🎉");
+    }
+
+    let mut s = String::from("**Your balance**");
+    for balance in balances {
+        s.push('\n');
+        s.push_str(&owes_line(
+            balance.counterparty,
+            Money::from_decimal(balance.amount, balance.currency),
+        ));
+    }
+
+    let now = Utc::now();
+    let overdue: Vec<&Debt> = debts
+        .iter()
+        .filter(|debt| debt.debtor == user || debt.creditor == user)
+        .filter(|debt| is_overdue(debt, now))
+        .collect();
+    if !overdue.is_empty() {
+        s.push_str("\n\n**⏰ Overdue**");
+    }
+    for debt in overdue {
+        let (counterparty, amount) = if debt.creditor == user {
+            (debt.debtor, debt.share_amount)
+        } else {
+            (debt.creditor, -debt.share_amount)
+        };
+        s.push_str(&format!(
+            "\n- {}: {}{}",
+            debt.bill.title,
+            owes_line(
+                counterparty,
+                Money::from_decimal(amount, debt.bill.currency)
+            ),
+            due_since(debt)
+        ));
+    }
+
+    s
+}
+
+fn pair_summary(debts: &[Debt], user: UserId, other: UserId) -> String {
+    let between: Vec<&Debt> = debts
+        .iter()
+        .filter(|debt| {
+            (debt.debtor == user && debt.creditor == other)
+                || (debt.debtor == other && debt.creditor == user)
+        })
+        .collect();
+
+    if between.is_empty() {
+        return format!("You and {} are all square 🎉", other.mention());
+    }
+
+    let now = Utc::now();
+    let mut s = format!("**Balance with {}**", other.mention());
+    for debt in &between {
+        // each bill is listed in its own currency, only the net is converted
+        let amount = if debt.creditor == user {
+            debt.share_amount
+        } else {
+            -debt.share_amount
+        };
+        let overdue = if is_overdue(debt, now) {
+            due_since(debt)
+        } else {
+            String::from("")
+        };
+        s.push_str(&format!(
+            "\n- {}: {}{}",
+            debt.bill.title,
+            owes_line(other, Money::from_decimal(amount, debt.bill.currency)),
+            overdue
+        ));
+    }
+
+    let balances = ledger::net_balances(debts, user);
+    let net: Vec<_> = balances
+        .iter()
+        .filter(|balance| balance.counterparty == other)
+        .collect();
+
+    s.push_str("\n\n**Net**");
+    if net.is_empty() {
+        s.push_str("\nEverything cancels out, you're square 🎉");
+    }
+    for balance in net {
+        s.push('\n');
+        s.push_str(&owes_line(
+            other,
+            Money::from_decimal(balance.amount, balance.currency),
+        ));
+    }
+
+    s
+}
+
+fn is_overdue(debt: &Debt, now: DateTime<Utc>) -> bool {
+    debt.bill
+        .share(debt.debtor)
+        .is_some_and(|share| debt.bill.is_overdue(share, now))
+}
+
+/// Says how long ago an overdue debt was due.
+fn due_since(debt: &Debt) -> String {
+    match debt.bill.due_at {
+        Some(due_at) => format!(" ⏰ overdue since <t:{}:R>", due_at.timestamp()),
+        None => String::from(""),
+    }
+}
+
+/// Describes a balance where a positive amount is owed to the invoking user by `counterparty`.
+fn owes_line(counterparty: UserId, amount: Money<'static, rusty_money::iso::Currency>) -> String {
+    if amount.is_negative() {
+        let owed = Money::from_decimal(-*amount.amount(), amount.currency());
+        format!("You owe {} {}", counterparty.mention(), owed)
+    } else {
+        format!("{} owes you {}", counterparty.mention(), amount)
+    }
+}
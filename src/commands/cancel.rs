@@ -0,0 +1,85 @@
+use serenity::builder::CreateApplicationCommandOption;
+use serenity::model::prelude::command::CommandOptionType;
+use serenity::model::prelude::interaction::application_command::{
+    ApplicationCommandInteraction, CommandDataOptionValue,
+};
+use serenity::prelude::Context;
+
+use crate::commands::respond_ephemeral;
+use crate::components::cancel_bill;
+use crate::store::BillStore;
+
+pub fn register(opt: &mut CreateApplicationCommandOption) -> &mut CreateApplicationCommandOption {
+    opt.kind(CommandOptionType::SubCommand)
+        .name("cancel")
+        .description("Cancel a bill you created, taking it out of everyone's balances")
+        .create_sub_option(|opt| {
+            opt.kind(CommandOptionType::Integer)
+                .name("bill")
+                .description("The bill's number, shown next to its title")
+                .min_int_value(1)
+                .required(true)
+        })
+        .create_sub_option(|opt| {
+            opt.kind(CommandOptionType::String)
+                .name("reason")
+                .description("Why the bill is cancelled, ex. posted it twice")
+        })
+}
+
+pub async fn respond(
+    ctx: &Context,
+    cmd: &ApplicationCommandInteraction,
+    store: &dyn BillStore,
+) -> anyhow::Result<()> {
+    let options = cmd
+        .data
+        .options
+        .first()
+        .map(|sub| sub.options.as_slice())
+        .unwrap_or_default();
+
+    let bill_id = options
+        .iter()
+        .find(|opt| opt.name == "bill")
+        .and_then(|opt| match &opt.resolved {
+            Some(CommandDataOptionValue::Integer(id)) => Some(*id),
+            _ => None,
+        })
+        .unwrap_or_default();
+    let reason = options
+        .iter()
+        .find(|opt| opt.name == "reason")
+        .and_then(|opt| match &opt.resolved {
+            Some(CommandDataOptionValue::String(reason)) => Some(reason.trim().to_owned()),
+            _ => None,
+        })
+        .unwrap_or_default();
+
+    // bills from other servers are treated as if they don't exist
+    let bill = store
+        .get_bill(bill_id)?
+        .filter(|bill| Some(bill.guild_id) == cmd.guild_id);
+
+    let bill = match bill {
+        Some(bill) => bill,
+        None => {
+            return respond_ephemeral(
+                ctx,
+                cmd,
+                format!("There's no bill #{} in this server", bill_id),
+            )
+            .await;
+        }
+    };
+    if let Some(why) = cancel_bill::refusal(&bill, cmd.user.id) {
+        return respond_ephemeral(ctx, cmd, why).await;
+    }
+
+    // the interaction is answered first, posting refunds can take longer than discord waits
+    let bill = cancel_bill::cancel(store, bill.id, cmd.user.id, reason)?;
+    respond_ephemeral(ctx, cmd, cancel_bill::cancelled_notice(&bill)).await?;
+    cancel_bill::announce(ctx, store, &bill).await;
+
+    Ok(())
+}
@@ -0,0 +1,195 @@
+use log::info;
+use rusty_money::iso;
+use serenity::builder::CreateApplicationCommandOption;
+use serenity::model::prelude::command::CommandOptionType;
+use serenity::model::prelude::interaction::application_command::{
+    ApplicationCommandInteraction, CommandDataOptionValue,
+};
+use serenity::model::prelude::interaction::InteractionResponseType::ChannelMessageWithSource;
+use serenity::prelude::Context;
+
+use crate::commands::is_admin;
+use crate::store::{BillStore, ReminderSettings};
+
+pub fn register(opt: &mut CreateApplicationCommandOption) -> &mut CreateApplicationCommandOption {
+    opt.kind(CommandOptionType::SubCommandGroup)
+        .name("config")
+        .description("Configure billbot for this server, requires the Manage Server permission")
+        .create_sub_option(|opt| {
+            opt.kind(CommandOptionType::SubCommand)
+                .name("currency")
+                .description("Set the currency new bills are in unless the creator picks another")
+                .create_sub_option(|opt| {
+                    opt.kind(CommandOptionType::String)
+                        .name("code")
+                        .description("ISO currency code, ex. CAD, USD or EUR")
+                        .required(true)
+                })
+        })
+        .create_sub_option(|opt| {
+            opt.kind(CommandOptionType::SubCommand)
+                .name("reminders")
+                .description("Set when payers are reminded of bills with a due date, leave out what to keep")
+                .create_sub_option(|opt| {
+                    opt.kind(CommandOptionType::Boolean)
+                        .name("enabled")
+                        .description("Whether payers are reminded at all")
+                })
+                .create_sub_option(|opt| {
+                    opt.kind(CommandOptionType::Integer)
+                        .name("days_before")
+                        .description("Days before the due date to remind payers, 0 to not remind them before")
+                        .min_int_value(0)
+                        .max_int_value(30)
+                })
+                .create_sub_option(|opt| {
+                    opt.kind(CommandOptionType::Integer)
+                        .name("days_after")
+                        .description("Days after the due date to first remind payers, each reminder after waits twice as long")
+                        .min_int_value(0)
+                        .max_int_value(30)
+                })
+                .create_sub_option(|opt| {
+                    opt.kind(CommandOptionType::Integer)
+                        .name("max_gap")
+                        .description("The most days to wait between reminders")
+                        .min_int_value(1)
+                        .max_int_value(60)
+                })
+                .create_sub_option(|opt| {
+                    opt.kind(CommandOptionType::Integer)
+                        .name("quiet_start")
+                        .description("Hour in UTC when quiet hours start, set it to quiet_end for none")
+                        .min_int_value(0)
+                        .max_int_value(23)
+                })
+                .create_sub_option(|opt| {
+                    opt.kind(CommandOptionType::Integer)
+                        .name("quiet_end")
+                        .description("Hour in UTC when quiet hours end")
+                        .min_int_value(0)
+                        .max_int_value(23)
+                })
+        })
+}
+
+pub async fn respond(
+    ctx: &Context,
+    cmd: &ApplicationCommandInteraction,
+    store: &dyn BillStore,
+) -> anyhow::Result<()> {
+    let content = match cmd.guild_id {
+        Some(guild_id) if is_admin(cmd) => {
+            let setting = cmd
+                .data
+                .options
+                .first()
+                .and_then(|group| group.options.first());
+
+            match setting {
+                Some(setting) if setting.name == "currency" => {
+                    let code = setting
+                        .options
+                        .iter()
+                        .find(|opt| opt.name == "code")
+                        .and_then(|opt| match &opt.resolved {
+                            Some(CommandDataOptionValue::String(code)) => Some(code.trim()),
+                            _ => None,
+                        })
+                        .unwrap_or_default();
+
+                    match iso::find(&code.to_uppercase()) {
+                        Some(currency) => {
+                            store.set_guild_currency(guild_id, currency)?;
+                            info!(
+                                "set default currency for guild {} to {}",
+                                guild_id, currency.iso_alpha_code
+                            );
+                            format!(
+                                "New bills will be in {} ({}) unless their creator picks another currency",
+                                currency.name, currency.iso_alpha_code
+                            )
+                        }
+                        None => format!("{} isn't a currency code billbot knows, try something like CAD, USD or EUR", code),
+                    }
+                }
+                Some(setting) if setting.name == "reminders" => {
+                    let option = |name: &str| {
+                        setting
+                            .options
+                            .iter()
+                            .find(|opt| opt.name == name)
+                            .and_then(|opt| opt.resolved.as_ref())
+                    };
+                    let int_option = |name: &str| match option(name) {
+                        Some(CommandDataOptionValue::Integer(value)) => Some(*value as u32),
+                        _ => None,
+                    };
+
+                    let mut reminders = store.guild_settings(guild_id)?.reminders;
+                    if let Some(CommandDataOptionValue::Boolean(enabled)) = option("enabled") {
+                        reminders.enabled = *enabled;
+                    }
+                    if let Some(days) = int_option("days_before") {
+                        reminders.days_before = days;
+                    }
+                    if let Some(days) = int_option("days_after") {
+                        reminders.days_after = days;
+                    }
+                    if let Some(days) = int_option("max_gap") {
+                        reminders.max_gap_days = days;
+                    }
+                    let (start, end) = reminders.quiet_hours.unzip();
+                    reminders.quiet_hours = match (
+                        int_option("quiet_start").or(start),
+                        int_option("quiet_end").or(end),
+                    ) {
+                        (Some(start), Some(end)) if start != end => Some((start, end)),
+                        _ => None,
+                    };
+
+                    store.set_reminder_settings(guild_id, &reminders)?;
+                    info!(
+                        "set reminder settings for guild {} to {:?}",
+                        guild_id, reminders
+                    );
+                    describe_reminders(&reminders)
+                }
+                _ => String::from("Unknown setting"),
+            }
+        }
+        Some(_) => String::from("You need the Manage Server permission to configure billbot"),
+        None => String::from("Configuration is only available in servers"),
+    };
+
+    cmd.create_interaction_response(&ctx.http, |res| {
+        res.kind(ChannelMessageWithSource)
+            .interaction_response_data(|msg| msg.ephemeral(true).content(content))
+    })
+    .await?;
+
+    Ok(())
+}
+
+fn describe_reminders(reminders: &ReminderSettings) -> String {
+    if !reminders.enabled {
+        return String::from("Payers won't be reminded of bills that are due");
+    }
+
+    let mut s = String::from("Payers of bills with a due date are reminded ");
+    if reminders.days_before > 0 {
+        s.push_str(&format!("{} days before it, then ", reminders.days_before));
+    }
+    s.push_str(&format!(
+        "{} days after it's missed, waiting twice as long before each reminder after that, up to {} days",
+        reminders.days_after, reminders.max_gap_days
+    ));
+    if let Some((start, end)) = reminders.quiet_hours {
+        s.push_str(&format!(
+            ". Nobody's reminded between {:02}:00 and {:02}:00 UTC",
+            start, end
+        ));
+    }
+
+    s
+}
@@ -1,10 +1,44 @@
-use serenity::builder::CreateApplicationCommand;
+use std::collections::HashMap;
+
+use chrono::{DateTime, Utc};
+use log::{error, info, warn};
+use rust_decimal::Decimal;
+use rusty_money::{iso::Currency, Money};
+use serenity::builder::{
+    CreateApplicationCommand, CreateComponents, CreateInputText, CreateInteractionResponseData,
+};
 use serenity::model::prelude::command::CommandOptionType;
 use serenity::model::prelude::component::InputTextStyle::{Paragraph, Short};
+use serenity::model::prelude::component::{ActionRowComponent, ButtonStyle};
 use serenity::model::prelude::interaction::application_command::ApplicationCommandInteraction;
-use serenity::model::prelude::interaction::InteractionResponseType::Modal;
+use serenity::model::prelude::interaction::modal::ModalSubmitInteraction;
+use serenity::model::prelude::interaction::InteractionResponseType::ChannelMessageWithSource;
+use serenity::model::prelude::{ChannelId, GuildId, Member, Role, RoleId, UserId};
 use serenity::prelude::Context;
 
+use crate::bill::{
+    Bill, BillItem, BillShare, ShareStatus, MAX_METHOD_LENGTH, MAX_PAYERS, MAX_TITLE_LENGTH,
+};
+use crate::bill_message;
+use crate::commands::{
+    self, balance, bool_option, cancel, config, currency_option, list, quick, rates, recurring,
+    register_currency, register_include_me, remind, reminders, settle_plan, show,
+};
+use crate::components::pick_payers::{self, CONTINUE_ID, PICK_PAYERS_ID};
+use crate::draft::{Draft, Drafts, Form};
+use crate::due;
+use crate::items::{self, ItemSpec};
+use crate::members::MemberDirectory;
+use crate::payers::{self, Resolution};
+use crate::rates::RateProvider;
+use crate::split::{self, PayerSpec, Portion};
+use crate::store::BillStore;
+
+pub const MODAL_ID: &str = "bill_create_modal";
+
+/// A message has room for five action rows, one of which holds the Continue button.
+const PICKER_MENUS: usize = 4;
+
 pub fn register(command: &mut CreateApplicationCommand) -> &mut CreateApplicationCommand {
     command
         .name("bill")
@@ -13,89 +47,872 @@ pub fn register(command: &mut CreateApplicationCommand) -> &mut CreateApplicatio
             opt.kind(CommandOptionType::SubCommand)
                 .name("create")
                 .description("Create a new bill, which billbot will post to the server")
-            // .create_sub_option(|opt| {
-            //     opt.kind(CommandOptionType::User)
-            //         .name("payer1")
-            //         .description(
-            //             "Person 1 who will receive the bill (at least one payer required)",
-            //         )
-            //         .required(true)
-            // })
-            // .create_sub_option(|opt| {
-            //     opt.kind(CommandOptionType::User)
-            //         .name("payer2")
-            //         .description("Person 2 who will receive the bill")
-            // })
-            // .create_sub_option(|opt| {
-            //     opt.kind(CommandOptionType::User)
-            //         .name("payer3")
-            //         .description("Person 3 who will receive the bill")
-            // })
-            // .create_sub_option(|opt| {
-            //     opt.kind(CommandOptionType::User)
-            //         .name("payer4")
-            //         .description("Person 4 who will receive the bill")
-            // })
-            // .create_sub_option(|opt| {
-            //     opt.kind(CommandOptionType::User)
-            //         .name("payer5")
-            //         .description("Person 5 who will receive the bill")
-            // })
-            // .create_sub_option(|opt| {
-            //     opt.kind(CommandOptionType::User)
-            //         .name("payer6")
-            //         .description("Person 6 who will receive the bill")
-            // })
+                .create_sub_option(register_include_me)
+                .create_sub_option(register_currency)
         })
+        .create_option(|opt| quick::register(opt))
+        .create_option(|opt| balance::register(opt))
+        .create_option(|opt| settle_plan::register(opt))
+        .create_option(|opt| config::register(opt))
+        .create_option(|opt| rates::register(opt))
+        .create_option(|opt| cancel::register(opt))
+        .create_option(|opt| list::register(opt))
+        .create_option(|opt| show::register(opt))
+        .create_option(|opt| recurring::register(opt))
+        .create_option(|opt| reminders::register(opt))
+        .create_option(|opt| remind::register(opt))
 }
 
-pub async fn respond(ctx: &Context, cmd: &ApplicationCommandInteraction) -> anyhow::Result<()> {
+pub async fn respond(
+    ctx: &Context,
+    cmd: &ApplicationCommandInteraction,
+    store: &dyn BillStore,
+    directory: &MemberDirectory,
+    drafts: &Drafts,
+) -> anyhow::Result<()> {
+    let guild_id = match cmd.guild_id {
+        Some(guild_id) => guild_id,
+        None => {
+            return commands::respond_ephemeral(ctx, cmd, "Bills can only be created in servers")
+                .await;
+        }
+    };
+
+    let options = cmd
+        .data
+        .options
+        .first()
+        .map(|sub| sub.options.as_slice())
+        .unwrap_or_default();
+
+    let include_me = bool_option(options, "include_me");
+    let currency = match currency_option(ctx, cmd, options, store, guild_id).await? {
+        Some(currency) => currency,
+        None => return Ok(()),
+    };
+
+    let mut members: Vec<Member> = directory
+        .channel_members(ctx, guild_id, cmd.channel_id)
+        .await?
+        .into_iter()
+        .filter(|member| !member.user.bot)
+        .collect();
+    members.sort_by_key(|member| member.display_name().to_lowercase());
+
+    // the picker message and the modal are separate interactions, so everything chosen so
+    // far is kept in a draft keyed by this interaction until the modal is submitted
+    let key = cmd.id.0;
+    drafts.insert(key, Draft::new(include_me, currency));
+
+    let listed = members.len().min(PICKER_MENUS * 25);
+    let mut content =
+        String::from("Pick who's paying, then press Continue to fill in the rest of the bill.");
+    if listed < members.len() {
+        content.push_str(&format!(
+            "\nOnly the first {} people in this channel are listed, type anyone else into the payers field.",
+            listed
+        ));
+    }
+
     cmd.create_interaction_response(&ctx.http, |res| {
-        res.kind(Modal).interaction_response_data(|modal| {
-            modal
-                .custom_id("bill_create_modal")
-                .title("Create a new bill")
-                .components(|cmp| {
-                    cmp.create_action_row(|row| {
-                        row.create_input_text(|input| {
-                            input
-                                .custom_id("name")
-                                .label("Bill Name")
-                                .style(Short)
-                                .placeholder("dons run")
-                        })
-                    });
-                    cmp.create_action_row(|row| {
-                        row.create_input_text(|input| {
-                            input
-                                .custom_id("amount")
-                                .label("Amount")
-                                .style(Short)
-                                .placeholder("420.69")
-                        })
-                    });
-                    cmp.create_action_row(|row| {
-                        row.create_input_text(|input| {
-                            input
-                                .custom_id("method")
-                                .label("Payment method")
-                                .style(Paragraph)
-                                .placeholder("Ex. etransfer jacob.michels2025@gmail.com")
-                        })
-                    });
-                    cmp.create_action_row(|row| {
-                        row.create_input_text(|input| {
-                            input
-                                .custom_id("payers")
-                                .label("Payers")
-                                .style(Paragraph)
-                                .placeholder("Ex. Jacob, Joel, Justin, ... (uses discord nicknames for account linking)")
-                        })
+        res.kind(ChannelMessageWithSource)
+            .interaction_response_data(|msg| {
+                msg.ephemeral(true)
+                    .content(content)
+                    .components(|cmp| picker_components(cmp, key, &members[..listed]))
+            })
+    })
+    .await?;
+
+    Ok(())
+}
+
+/// Adds a select menu for every 25 channel members, followed by the button that opens the
+/// bill modal. These would be a single user select menu, which searches the whole server, but
+/// serenity 0.11 fails to deserialize messages with one, so they wait on moving to serenity 0.12.
+fn picker_components<'a>(
+    cmp: &'a mut CreateComponents,
+    key: u64,
+    members: &[Member],
+) -> &'a mut CreateComponents {
+    for (i, chunk) in members.chunks(25).enumerate() {
+        cmp.create_action_row(|row| {
+            row.create_select_menu(|menu| {
+                menu.custom_id(format!("{}:{}:{}", PICK_PAYERS_ID, key, i))
+                    .placeholder(match (chunk.first(), chunk.last()) {
+                        (Some(first), Some(last)) if members.len() > 25 => {
+                            format!("Payers {} to {}", first.display_name(), last.display_name())
+                        }
+                        _ => String::from("Payers"),
                     })
+                    .min_values(0)
+                    .max_values(chunk.len().min(MAX_PAYERS) as u64)
+                    .options(|opts| {
+                        for member in chunk {
+                            opts.create_option(|opt| {
+                                opt.label(member.display_name())
+                                    .value(member.user.id)
+                                    .description(member.user.tag())
+                            });
+                        }
+                        opts
+                    })
+            })
+        });
+    }
+
+    cmp.create_action_row(|row| {
+        row.create_button(|btn| {
+            btn.custom_id(format!("{}:{}", CONTINUE_ID, key))
+                .style(ButtonStyle::Primary)
+                .label("Continue")
+        })
+    })
+}
+
+/// Fills in the bill modal for the draft with the given key.
+pub fn modal<'a, 'b>(
+    modal: &'a mut CreateInteractionResponseData<'b>,
+    key: u64,
+    currency: &'static Currency,
+) -> &'a mut CreateInteractionResponseData<'b> {
+    modal
+        .custom_id(format!("{}:{}", MODAL_ID, key))
+        .title("Create a new bill")
+        .components(|cmp| form_inputs(cmp, currency, &Form::default(), false))
+}
+
+/// Adds the bill form's inputs, prefilled with whatever `form` already holds. Payers are only
+/// optional when they can be picked before the form is opened.
+pub fn form_inputs<'a>(
+    cmp: &'a mut CreateComponents,
+    currency: &'static Currency,
+    form: &Form,
+    payers_required: bool,
+) -> &'a mut CreateComponents {
+    // discord rejects empty prefilled values, so inputs without one are left blank
+    let prefill = |input: &mut CreateInputText, value: &str| {
+        if !value.is_empty() {
+            input.value(value);
+        }
+    };
+
+    cmp.create_action_row(|row| {
+        row.create_input_text(|input| {
+            prefill(input, &form.title);
+            input
+                .custom_id("name")
+                .label("Bill Name")
+                .style(Short)
+                .max_length(MAX_TITLE_LENGTH.into())
+                .placeholder("dons run")
+        })
+    });
+    cmp.create_action_row(|row| {
+        row.create_input_text(|input| {
+            prefill(input, &form.amount);
+            input
+                .custom_id("amount")
+                .label(format!("Amount ({})", currency.iso_alpha_code))
+                .style(Short)
+                .placeholder(split::format_money(
+                    Decimal::new(42069, currency.exponent),
+                    currency,
+                ))
+        })
+    });
+    cmp.create_action_row(|row| {
+        row.create_input_text(|input| {
+            prefill(input, &form.method);
+            input
+                .custom_id("method")
+                .label("Payment method")
+                .style(Paragraph)
+                .max_length(MAX_METHOD_LENGTH.into())
+                .placeholder("Ex. etransfer jacob.michels2025@gmail.com")
+        })
+    });
+    cmp.create_action_row(|row| {
+        row.create_input_text(|input| {
+            prefill(input, &form.payers);
+            input
+                .custom_id("payers")
+                .label(if payers_required {
+                    "Payers, split or items"
+                } else {
+                    "Other payers, split or items (optional)"
                 })
+                .style(Paragraph)
+                .required(payers_required)
+                .placeholder(format!(
+                    "Ex. Joel, @roommates, Jacob:2, Joel:1, Jacob 60%, Joel 40% or Jacob {}{}, rest",
+                    currency.symbol,
+                    split::format_money(Decimal::from(50), currency)
+                ))
+        })
+    });
+    cmp.create_action_row(|row| {
+        row.create_input_text(|input| {
+            prefill(input, &form.due);
+            input
+                .custom_id("due")
+                .label("Due date (optional)")
+                .style(Short)
+                .required(false)
+                .placeholder("Ex. 2026-11-01, next friday or in 2 weeks")
         })
     })
+}
+
+/// Reads what was typed into the bill form.
+pub fn read_form(submission: &ModalSubmitInteraction) -> Form {
+    let mut form = Form::default();
+    for row in &submission.data.components {
+        for component in &row.components {
+            match component {
+                ActionRowComponent::InputText(data) => match data.custom_id.as_str() {
+                    "name" => form.title = data.value.clone().trim().to_owned(),
+                    "amount" => form.amount = data.value.clone().trim().to_owned(),
+                    "method" => form.method = data.value.clone().trim().to_owned(),
+                    "payers" => form.payers = data.value.clone(),
+                    "due" => form.due = data.value.trim().to_owned(),
+                    _ => {
+                        error!("invalid input text custom_id")
+                    }
+                },
+                _ => {
+                    error!("invalid compenent type");
+                }
+            }
+        }
+    }
+
+    form
+}
+
+pub async fn submit(
+    ctx: &Context,
+    submission: &ModalSubmitInteraction,
+    store: &dyn BillStore,
+    rates: &dyn RateProvider,
+    directory: &MemberDirectory,
+    drafts: &Drafts,
+) -> anyhow::Result<()> {
+    let guild_id = submission.guild_id;
+    if guild_id.is_none() {
+        error!("no guild id for modal submit, aborting response");
+        return Ok(());
+    }
+
+    let guild_id = guild_id.unwrap();
+
+    let creator = match &submission.member {
+        Some(member) => member,
+        None => {
+            error!("no member for modal submission, aborting response");
+            return Ok(());
+        }
+    };
+
+    let form = read_form(submission);
+
+    // the draft is only removed once the bill is posted, so a rejected submission can be
+    // fixed by pressing Continue again without picking everyone again
+    let key: Option<u64> = submission
+        .data
+        .custom_id
+        .split(':')
+        .nth(1)
+        .and_then(|key| key.parse().ok());
+    let draft = key.and_then(|key| {
+        drafts.update(key, |draft| draft.form = Some(form.clone()));
+        Some((key, drafts.get(key)?))
+    });
+    let (key, draft) = match draft {
+        Some(found) => found,
+        None => {
+            warn!(
+                "no draft for modal submission: {}",
+                submission.data.custom_id
+            );
+            respond_ephemeral(
+                ctx,
+                submission,
+                "This bill took too long to fill in, run `/bill create` again",
+            )
+            .await;
+            return Ok(());
+        }
+    };
+
+    let prepared = prepare(
+        ctx,
+        store,
+        rates,
+        directory,
+        guild_id,
+        submission.channel_id,
+        creator,
+        &draft,
+        &form,
+    )
     .await?;
 
+    let mut bill = match prepared {
+        Prepared::Ready(bill) => *bill,
+        Prepared::Rejected(why) => {
+            warn!("rejected bill submission, returning early: {}", why);
+            respond_ephemeral(ctx, submission, why).await;
+            return Ok(());
+        }
+        Prepared::Ambiguous(ambiguous) => {
+            drafts.update(key, |draft| {
+                draft.ambiguous = ambiguous.iter().map(|(name, _)| name.clone()).collect()
+            });
+            if let Err(why) = submission
+                .create_interaction_response(&ctx.http, |res| {
+                    res.kind(ChannelMessageWithSource)
+                        .interaction_response_data(|msg| {
+                            pick_payers::match_prompt(msg, key, &ambiguous)
+                        })
+                })
+                .await
+            {
+                error!("failed to ask who ambiguous payers are: {}", why);
+            }
+            return Ok(());
+        }
+    };
+
+    if let Err(why) = store.insert_bill(&mut bill) {
+        error!("failed to save bill, aborting response: {:?}", why);
+        if let Err(why) = submission
+            .create_interaction_response(&ctx.http, |res| {
+                res.kind(ChannelMessageWithSource)
+                    .interaction_response_data(|msg| {
+                        msg.ephemeral(true)
+                            .content("Failed to save the bill, please try again")
+                    })
+            })
+            .await
+        {
+            error!("failed to send early response: {}", why);
+        }
+        return Ok(());
+    }
+    info!("saved bill with id {}", bill.id);
+    drafts.remove(key);
+
+    if let Err(why) = submission
+        .create_interaction_response(&ctx.http, |res| {
+            res.kind(ChannelMessageWithSource)
+                .interaction_response_data(|msg| {
+                    msg.content(bill_message::content(&bill))
+                        .components(|cmp| bill_message::components(&bill, cmp))
+                })
+        })
+        .await
+    {
+        error!("failed to respond to modal submission: {}", why);
+        bill_message::discard(store, bill.id);
+        return Ok(());
+    }
+
+    match submission.get_interaction_response(&ctx.http).await {
+        Ok(message) => {
+            if let Err(why) = store.set_message_id(bill.id, message.id) {
+                error!("failed to save message id for bill {}: {:?}", bill.id, why);
+            }
+        }
+        Err(why) => error!("failed to get bill message for bill {}: {}", bill.id, why),
+    }
+
     Ok(())
 }
+
+/// The outcome of turning a draft and its form into a bill.
+pub enum Prepared {
+    /// The bill is ready to be saved and posted.
+    Ready(Box<Bill>),
+    /// Something about the form is wrong, the message is meant for the bill creator.
+    Rejected(String),
+    /// Typed names that match more than one member, along with those members.
+    Ambiguous(Vec<(String, Vec<Member>)>),
+}
+
+/// Works out who pays what for a drafted bill, without saving it.
+#[allow(clippy::too_many_arguments)]
+pub async fn prepare(
+    ctx: &Context,
+    store: &dyn BillStore,
+    rates: &dyn RateProvider,
+    directory: &MemberDirectory,
+    guild_id: GuildId,
+    channel_id: ChannelId,
+    creator: &Member,
+    draft: &Draft,
+    form: &Form,
+) -> anyhow::Result<Prepared> {
+    let settings = store.guild_settings(guild_id)?;
+    let currency = draft.currency;
+
+    let ParsedForm {
+        amount,
+        due_at,
+        specs,
+        item_specs,
+    } = match parse_form(draft, form) {
+        Ok(parsed) => parsed,
+        Err(why) => return Ok(Prepared::Rejected(why)),
+    };
+    let itemized = !item_specs.is_empty();
+
+    let channel_members = match directory.channel_members(ctx, guild_id, channel_id).await {
+        Ok(members) => members,
+        Err(why) => {
+            error!("failed to get channel members: {:?}", why);
+            return Ok(Prepared::Rejected(String::from(
+                "Failed to look up the members of this channel, please try again",
+            )));
+        }
+    };
+
+    let names: Vec<&str> = specs
+        .iter()
+        .map(|spec| spec.name.as_str())
+        .chain(
+            item_specs
+                .iter()
+                .flat_map(|item| item.assignees.iter().map(String::as_str))
+                .filter(|assignee| !items::is_everyone(assignee)),
+        )
+        .collect();
+    let roles = if names.iter().any(|name| payers::may_be_role(name)) {
+        match guild_id.roles(&ctx.http).await {
+            Ok(roles) => roles,
+            Err(why) => {
+                error!("failed to get roles for guild: {}", why);
+                return Ok(Prepared::Rejected(String::from(
+                    "Failed to look up the roles of this server, please try again",
+                )));
+            }
+        }
+    } else {
+        HashMap::new()
+    };
+
+    // picked payers weigh 1 unless the payers field gives them another portion
+    let mut payers: Vec<Member> = Vec::with_capacity(10);
+    let mut portions: Vec<Portion> = Vec::with_capacity(10);
+    for user_id in draft.picked() {
+        match channel_members
+            .iter()
+            .find(|member| member.user.id == user_id)
+        {
+            Some(member) => {
+                payers.push(member.clone());
+                portions.push(Portion::Weight(Decimal::ONE));
+            }
+            None => warn!("picked payer {} can no longer see the channel", user_id),
+        }
+    }
+
+    let payer_nicks: Vec<String> = specs.iter().map(|spec| spec.name.clone()).collect();
+    info!("payer nicks: {:?}", payer_nicks);
+
+    let mut ambiguous = Vec::new();
+    let mut rest = None;
+    for spec in specs {
+        if spec.name == split::REST {
+            rest = Some(spec.portion);
+            continue;
+        }
+
+        // a role stands for everyone in it who can see the channel, and they're stored as
+        // individual shares so later role changes don't touch the bill. Members listed by
+        // name keep their own portion whether they come before or after the role.
+        let member = match lookup(&spec.name, &channel_members, &roles, draft, creator) {
+            Lookup::Member(member) => *member,
+            Lookup::Role(role_members) => {
+                for member in role_members {
+                    if !payers.iter().any(|payer| payer.user.id == member.user.id) {
+                        payers.push(member);
+                        portions.push(spec.portion);
+                    }
+                }
+                continue;
+            }
+            Lookup::Ambiguous(candidates) => {
+                ambiguous.push((spec.name, candidates));
+                continue;
+            }
+            Lookup::Rejected(why) => return Ok(Prepared::Rejected(why)),
+        };
+
+        match payers
+            .iter()
+            .position(|payer| payer.user.id == member.user.id)
+        {
+            Some(i) => portions[i] = spec.portion,
+            None => {
+                payers.push(member);
+                portions.push(spec.portion);
+            }
+        }
+    }
+
+    // `rest` is everyone else who can see the channel, so it's filled in once everyone listed
+    // by name or role is known
+    if let Some(portion) = rest {
+        let others: Vec<&Member> = channel_members
+            .iter()
+            .filter(|member| !member.user.bot)
+            .filter(|member| draft.include_me != Some(false) || member.user.id != creator.user.id)
+            .filter(|member| !payers.iter().any(|payer| payer.user.id == member.user.id))
+            .collect();
+        if others.is_empty() {
+            return Ok(Prepared::Rejected(String::from(
+                "Everyone in this channel is already on the bill, there's nobody left for `rest`",
+            )));
+        }
+
+        info!("expanded rest into {} payers", others.len());
+        for member in others {
+            payers.push(member.clone());
+            portions.push(portion);
+        }
+    }
+
+    // everyone an item is for ends up on the bill, @all is filled in once they're all known
+    let mut bill_items = Vec::with_capacity(item_specs.len());
+    let mut for_everyone = Vec::with_capacity(item_specs.len());
+    for spec in item_specs {
+        let mut item_payers = Vec::new();
+        let mut everyone = false;
+        for assignee in &spec.assignees {
+            if items::is_everyone(assignee) {
+                everyone = true;
+                continue;
+            }
+
+            let members = match lookup(assignee, &channel_members, &roles, draft, creator) {
+                Lookup::Member(member) => vec![*member],
+                Lookup::Role(role_members) => role_members,
+                Lookup::Ambiguous(candidates) => {
+                    if !ambiguous
+                        .iter()
+                        .any(|(name, _): &(String, _)| name.eq_ignore_ascii_case(assignee))
+                    {
+                        ambiguous.push((assignee.clone(), candidates));
+                    }
+                    continue;
+                }
+                Lookup::Rejected(why) => return Ok(Prepared::Rejected(why)),
+            };
+
+            for member in members {
+                if !item_payers.contains(&member.user.id) {
+                    item_payers.push(member.user.id);
+                }
+                if !payers.iter().any(|payer| payer.user.id == member.user.id) {
+                    payers.push(member);
+                    portions.push(Portion::Weight(Decimal::ONE));
+                }
+            }
+        }
+
+        bill_items.push(BillItem {
+            name: spec.name,
+            amount: spec.amount,
+            payers: item_payers,
+        });
+        for_everyone.push(everyone);
+    }
+
+    if ambiguous.len() > pick_payers::MAX_MATCH_MENUS {
+        let names: Vec<&str> = ambiguous.iter().map(|(name, _)| name.as_str()).collect();
+        return Ok(Prepared::Rejected(format!(
+            "More than one person goes by each of {}. Use their usernames or @mention them instead",
+            names.join(", ")
+        )));
+    }
+    if !ambiguous.is_empty() {
+        return Ok(Prepared::Ambiguous(ambiguous));
+    }
+
+    let include_me = draft.include_me;
+    let creator_listed = payers.iter().any(|payer| payer.user.id == creator.user.id);
+
+    match include_me {
+        Some(true) if !creator_listed => {
+            if portions
+                .iter()
+                .any(|portion| matches!(portion, Portion::Percent(_)))
+            {
+                return Ok(Prepared::Rejected(String::from(
+                    "The split uses percentages, add yourself to the payers with your percentage instead of using include_me",
+                )));
+            }
+
+            payers.push(creator.clone());
+            portions.push(Portion::Weight(Decimal::ONE));
+        }
+        Some(false) if creator_listed => {
+            return Ok(Prepared::Rejected(String::from(
+                "You listed yourself as a payer but set include_me to false, remove yourself or leave include_me out",
+            )));
+        }
+        _ => {}
+    }
+
+    if payers.iter().all(|payer| payer.user.id == creator.user.id) {
+        return Ok(Prepared::Rejected(String::from(
+            "A bill needs at least one payer besides you",
+        )));
+    }
+
+    if payers.is_empty() || payers.len() > MAX_PAYERS {
+        return Ok(Prepared::Rejected(format!(
+            "A bill needs between 1 and {} payers",
+            MAX_PAYERS
+        )));
+    }
+
+    let owed = if itemized {
+        let payer_ids: Vec<UserId> = payers.iter().map(|payer| payer.user.id).collect();
+        for (item, everyone) in bill_items.iter_mut().zip(for_everyone) {
+            if everyone {
+                for user_id in &payer_ids {
+                    if !item.payers.contains(user_id) {
+                        item.payers.push(*user_id);
+                    }
+                }
+            }
+        }
+        items::owed(*amount.amount(), &bill_items, &payer_ids, currency)
+    } else {
+        split::split(*amount.amount(), currency, &portions)
+    };
+    let owed = match owed {
+        Ok(owed) => owed,
+        Err(why) => return Ok(Prepared::Rejected(why.to_string())),
+    };
+
+    let creator_name = creator.display_name().trim().to_owned();
+
+    info!(
+        "submission values, title: {}, amount: {}, method: {}, submitter: {}, payer count: {} owed: {:?}",
+        form.title, amount.to_string(), form.method, creator_name, payers.len(), owed
+    );
+
+    let now = Utc::now();
+    Ok(Prepared::Ready(Box::new(Bill {
+        id: 0,
+        guild_id,
+        channel_id,
+        message_id: None,
+        creator_id: creator.user.id,
+        creator_name,
+        title: form.title.clone(),
+        amount: *amount.amount(),
+        currency,
+        method: form.method.clone(),
+        created_at: now,
+        due_at,
+        rate: crate::rates::rate_to_record(currency, settings.currency, &rates.rates(guild_id)?),
+        cancellation: None,
+        shares: payers
+            .iter()
+            .zip(owed)
+            .map(|(payer, amount)| {
+                // the creator's own share is counted in the split but there's nothing to pay
+                let own_share = payer.user.id == creator.user.id;
+                BillShare {
+                    user_id: payer.user.id,
+                    name: payer.display_name().into_owned(),
+                    amount,
+                    status: if own_share {
+                        ShareStatus::Settled
+                    } else {
+                        ShareStatus::Owed
+                    },
+                    claimed_at: None,
+                    settled_at: own_share.then_some(now),
+                }
+            })
+            .collect(),
+        items: bill_items,
+    })))
+}
+
+/// What the form says once it's been checked, before any of the names in it are looked up.
+pub struct ParsedForm {
+    pub amount: Money<'static, Currency>,
+    pub due_at: Option<DateTime<Utc>>,
+    pub specs: Vec<PayerSpec>,
+    /// Only filled in for itemized bills, which have no payer specs.
+    pub item_specs: Vec<ItemSpec>,
+}
+
+/// Checks and parses everything in the form that doesn't depend on who's in the channel.
+/// Errors are meant to be shown to the bill creator as is.
+pub fn parse_form(draft: &Draft, form: &Form) -> Result<ParsedForm, String> {
+    let currency = draft.currency;
+
+    // the form caps these, but slash command options only do if discord honours them
+    if form.title.chars().count() > MAX_TITLE_LENGTH.into() {
+        return Err(format!(
+            "Bill titles can be at most {} characters",
+            MAX_TITLE_LENGTH
+        ));
+    }
+    if form.method.chars().count() > MAX_METHOD_LENGTH.into() {
+        return Err(format!(
+            "Payment methods can be at most {} characters",
+            MAX_METHOD_LENGTH
+        ));
+    }
+
+    let amount = match split::parse_money(&form.amount, currency) {
+        Ok(amount) => amount,
+        Err(why) => {
+            error!(
+                "failed to convert {} to {}: {}",
+                form.amount, currency.iso_alpha_code, why
+            );
+            return Err(format!(
+                "{} is not a valid {} amount",
+                form.amount, currency.iso_alpha_code
+            ));
+        }
+    };
+
+    let due_at = if form.due.is_empty() {
+        None
+    } else {
+        let today = Utc::now().date_naive();
+        match due::parse(&form.due, today) {
+            Ok(date) if date < today && draft.current_due_at != Some(due::due_at(date)) => {
+                return Err(format!(
+                    "{} is in the past, pick a due date from today on",
+                    date
+                ));
+            }
+            Ok(date) => Some(due::due_at(date)),
+            Err(why) => return Err(why.to_string()),
+        }
+    };
+
+    // a payers field of items like `Burger 15.50 @Jacob` splits the bill by what people had
+    let itemized = items::is_itemized(&form.payers, currency);
+    let (specs, item_specs) = if itemized {
+        match items::parse_items(&form.payers, currency) {
+            Ok(item_specs) => (Vec::new(), item_specs),
+            Err(why) => return Err(why.to_string()),
+        }
+    } else {
+        match split::parse_payers(&form.payers, currency) {
+            Ok(specs) => (specs, Vec::new()),
+            Err(why) => return Err(why.to_string()),
+        }
+    };
+
+    Ok(ParsedForm {
+        amount,
+        due_at,
+        specs,
+        item_specs,
+    })
+}
+
+/// What a name typed into the payers field turned out to be.
+enum Lookup {
+    Member(Box<Member>),
+    /// Everyone with the role who can see the channel.
+    Role(Vec<Member>),
+    /// Several members go by the name and the creator hasn't said which they meant yet.
+    Ambiguous(Vec<Member>),
+    /// The message is meant for the bill creator.
+    Rejected(String),
+}
+
+/// Looks a payer up by name, or a role by name or mention. Members picked from the match menus
+/// are taken from the draft.
+fn lookup(
+    name: &str,
+    channel_members: &[Member],
+    roles: &HashMap<RoleId, Role>,
+    draft: &Draft,
+    creator: &Member,
+) -> Lookup {
+    if let Some(role) = payers::resolve_role(name, roles) {
+        let role_members: Vec<Member> = channel_members
+            .iter()
+            .filter(|member| member.roles.contains(&role.id) && !member.user.bot)
+            .filter(|member| draft.include_me != Some(false) || member.user.id != creator.user.id)
+            .cloned()
+            .collect();
+        if role_members.is_empty() {
+            return Lookup::Rejected(format!(
+                "Nobody with the {} role can see this channel",
+                role.name
+            ));
+        }
+
+        info!(
+            "expanded role {} into {} payers",
+            role.name,
+            role_members.len()
+        );
+        return Lookup::Role(role_members);
+    }
+
+    match payers::resolve(name, channel_members) {
+        Resolution::Found(member) => Lookup::Member(Box::new(member.clone())),
+        Resolution::Ambiguous(candidates) => {
+            let chosen = draft
+                .chosen
+                .get(&name.to_lowercase())
+                .and_then(|id| candidates.iter().find(|member| member.user.id == *id));
+            match chosen {
+                Some(member) => Lookup::Member(Box::new((*member).clone())),
+                None => Lookup::Ambiguous(candidates.into_iter().cloned().collect()),
+            }
+        }
+        Resolution::NotFound(suggestions) => {
+            let mut content = format!("Payer not found: {}", name);
+            if !suggestions.is_empty() {
+                content.push_str(&format!(
+                    ". Did you mean {}?",
+                    describe_members(&suggestions)
+                ));
+            }
+            Lookup::Rejected(content)
+        }
+    }
+}
+
+/// Lists members by display name and username, so people who share a name can be told apart.
+fn describe_members(members: &[&Member]) -> String {
+    members
+        .iter()
+        .map(|member| format!("{} ({})", member.display_name(), member.user.tag()))
+        .collect::<Vec<_>>()
+        .join(", ")
+}
+
+pub async fn respond_ephemeral(
+    ctx: &Context,
+    submission: &ModalSubmitInteraction,
+    content: impl ToString,
+) {
+    if let Err(why) = submission
+        .create_interaction_response(&ctx.http, |res| {
+            res.kind(ChannelMessageWithSource)
+                .interaction_response_data(|msg| msg.ephemeral(true).content(content))
+        })
+        .await
+    {
+        error!("failed to send early response: {}", why);
+    }
+}
@@ -0,0 +1,311 @@
+use std::{
+    collections::HashMap,
+    sync::Mutex,
+    time::{Duration, Instant},
+};
+
+use chrono::{Duration as DateDuration, NaiveDate, TimeZone, Utc};
+use serenity::builder::{CreateApplicationCommandOption, CreateInteractionResponseData};
+use serenity::model::prelude::command::CommandOptionType;
+use serenity::model::prelude::component::ButtonStyle;
+use serenity::model::prelude::interaction::application_command::{
+    ApplicationCommandInteraction, CommandDataOptionValue,
+};
+use serenity::model::prelude::interaction::InteractionResponseType::ChannelMessageWithSource;
+use serenity::model::prelude::GuildId;
+use serenity::prelude::{Context, Mentionable};
+
+use crate::bill::{Bill, BillStatus, ShareStatus};
+use crate::commands::respond_ephemeral;
+use crate::components::bill_list::PAGE_ID;
+use crate::store::{BillFilter, BillStore};
+
+const PAGE_SIZE: usize = 10;
+
+/// Lists are only shown to whoever asked for them, so nobody pages through one for long.
+const LISTING_TTL: Duration = Duration::from_secs(60 * 60);
+
+/// The filters of lists shown so far, keyed by the id of the interaction that asked for them,
+/// so their page buttons can load the other pages.
+#[derive(Default)]
+pub struct Listings {
+    filters: Mutex<HashMap<u64, (BillFilter, Instant)>>,
+}
+
+impl Listings {
+    pub fn insert(&self, key: u64, filter: BillFilter) {
+        let mut filters = self.filters.lock().unwrap();
+        filters.retain(|_, (_, created_at)| created_at.elapsed() < LISTING_TTL);
+        filters.insert(key, (filter, Instant::now()));
+    }
+
+    pub fn get(&self, key: u64) -> Option<BillFilter> {
+        self.filters
+            .lock()
+            .unwrap()
+            .get(&key)
+            .filter(|(_, created_at)| created_at.elapsed() < LISTING_TTL)
+            .map(|(filter, _)| filter.clone())
+    }
+}
+
+pub fn register(opt: &mut CreateApplicationCommandOption) -> &mut CreateApplicationCommandOption {
+    opt.kind(CommandOptionType::SubCommand)
+        .name("list")
+        .description("List the server's bills, newest first")
+        .create_sub_option(|opt| {
+            opt.kind(CommandOptionType::String)
+                .name("status")
+                .description("Only list bills that are open, settled or cancelled")
+                .add_string_choice("open", BillStatus::Open.as_str())
+                .add_string_choice("settled", BillStatus::Settled.as_str())
+                .add_string_choice("cancelled", BillStatus::Cancelled.as_str())
+        })
+        .create_sub_option(|opt| {
+            opt.kind(CommandOptionType::User)
+                .name("creator")
+                .description("Only list bills this person created")
+        })
+        .create_sub_option(|opt| {
+            opt.kind(CommandOptionType::User)
+                .name("payer")
+                .description("Only list bills this person has to pay")
+        })
+        .create_sub_option(|opt| {
+            opt.kind(CommandOptionType::String)
+                .name("from")
+                .description("Only list bills created on or after this date, ex. 2026-01-31")
+        })
+        .create_sub_option(|opt| {
+            opt.kind(CommandOptionType::String)
+                .name("to")
+                .description("Only list bills created on or before this date, ex. 2026-02-28")
+        })
+        .create_sub_option(|opt| {
+            opt.kind(CommandOptionType::String)
+                .name("title")
+                .description("Only list bills with this in their title")
+        })
+}
+
+pub async fn respond(
+    ctx: &Context,
+    cmd: &ApplicationCommandInteraction,
+    store: &dyn BillStore,
+    listings: &Listings,
+) -> anyhow::Result<()> {
+    let guild_id = match cmd.guild_id {
+        Some(guild_id) => guild_id,
+        None => return respond_ephemeral(ctx, cmd, "Bills are only listed in servers").await,
+    };
+
+    let filter = match parse_filter(cmd) {
+        Ok(filter) => filter,
+        Err(why) => return respond_ephemeral(ctx, cmd, why).await,
+    };
+
+    let (bills, total) = store.list_bills(guild_id, &filter, 0, PAGE_SIZE)?;
+
+    // the page buttons are separate interactions, so they find the filter by this one's id
+    let key = cmd.id.0;
+    listings.insert(key, filter.clone());
+
+    cmd.create_interaction_response(&ctx.http, |res| {
+        res.kind(ChannelMessageWithSource)
+            .interaction_response_data(|msg| {
+                page(msg.ephemeral(true), key, &filter, &bills, total, 0)
+            })
+    })
+    .await?;
+
+    Ok(())
+}
+
+/// Loads the given page of a list.
+pub fn load_page(
+    store: &dyn BillStore,
+    guild_id: GuildId,
+    filter: &BillFilter,
+    page: usize,
+) -> anyhow::Result<(Vec<Bill>, usize)> {
+    store.list_bills(guild_id, filter, page * PAGE_SIZE, PAGE_SIZE)
+}
+
+fn parse_filter(cmd: &ApplicationCommandInteraction) -> Result<BillFilter, String> {
+    let options = cmd
+        .data
+        .options
+        .first()
+        .map(|sub| sub.options.as_slice())
+        .unwrap_or_default();
+    let option = |name: &str| {
+        options
+            .iter()
+            .find(|opt| opt.name == name)
+            .and_then(|opt| opt.resolved.as_ref())
+    };
+    let string_option = |name: &str| match option(name) {
+        Some(CommandDataOptionValue::String(value)) => Some(value.trim().to_owned()),
+        _ => None,
+    };
+    let user_option = |name: &str| match option(name) {
+        Some(CommandDataOptionValue::User(user, _)) => Some(user.id),
+        _ => None,
+    };
+
+    let from = string_option("from")
+        .map(|from| parse_date(&from))
+        .transpose()?;
+    let to = string_option("to").map(|to| parse_date(&to)).transpose()?;
+
+    Ok(BillFilter {
+        status: string_option("status").and_then(|status| BillStatus::parse(&status).ok()),
+        creator: user_option("creator"),
+        payer: user_option("payer"),
+        from: from.map(|from| Utc.from_utc_datetime(&from.and_hms_opt(0, 0, 0).unwrap())),
+        // the end date is included, so the range ends when the next day starts
+        until: to.map(|to| {
+            Utc.from_utc_datetime(&(to + DateDuration::days(1)).and_hms_opt(0, 0, 0).unwrap())
+        }),
+        title: string_option("title").filter(|title| !title.is_empty()),
+    })
+}
+
+fn parse_date(value: &str) -> Result<NaiveDate, String> {
+    NaiveDate::parse_from_str(value, "%Y-%m-%d")
+        .map_err(|_| format!("{} isn't a date, write dates like 2026-01-31", value))
+}
+
+/// Fills in a page of a list, with buttons to the pages before and after it.
+pub fn page<'a, 'b>(
+    msg: &'a mut CreateInteractionResponseData<'b>,
+    key: u64,
+    filter: &BillFilter,
+    bills: &[Bill],
+    total: usize,
+    page: usize,
+) -> &'a mut CreateInteractionResponseData<'b> {
+    let pages = total.div_ceil(PAGE_SIZE).max(1);
+
+    let mut description = describe_filter(filter);
+    if bills.is_empty() {
+        description.push_str("No bills found");
+    }
+    for bill in bills {
+        description.push_str(&entry(bill));
+        description.push('\n');
+    }
+
+    msg.embed(|embed| {
+        embed
+            .title("Bills")
+            .description(description)
+            .footer(|footer| {
+                footer.text(format!(
+                    "Page {} of {} · {} bill{}",
+                    page + 1,
+                    pages,
+                    total,
+                    if total == 1 { "" } else { "s" }
+                ))
+            })
+    })
+    .components(|cmp| {
+        cmp.create_action_row(|row| {
+            row.create_button(|btn| {
+                btn.custom_id(format!("{}:{}:{}", PAGE_ID, key, page.saturating_sub(1)))
+                    .style(ButtonStyle::Secondary)
+                    .label("Previous")
+                    .disabled(page == 0)
+            })
+            .create_button(|btn| {
+                btn.custom_id(format!("{}:{}:{}", PAGE_ID, key, page + 1))
+                    .style(ButtonStyle::Secondary)
+                    .label("Next")
+                    .disabled(page + 1 >= pages)
+            })
+        })
+    })
+}
+
+/// A line saying which filters are in use, if any are.
+fn describe_filter(filter: &BillFilter) -> String {
+    let mut parts = Vec::new();
+    if let Some(status) = filter.status {
+        parts.push(status.as_str().to_owned());
+    }
+    if let Some(creator) = filter.creator {
+        parts.push(format!("created by {}", creator.mention()));
+    }
+    if let Some(payer) = filter.payer {
+        parts.push(format!("paid by {}", payer.mention()));
+    }
+    if let Some(from) = filter.from {
+        parts.push(format!("from {}", from.format("%Y-%m-%d")));
+    }
+    if let Some(until) = filter.until {
+        parts.push(format!(
+            "to {}",
+            (until - DateDuration::days(1)).format("%Y-%m-%d")
+        ));
+    }
+    if let Some(title) = &filter.title {
+        parts.push(format!("titled \"{}\"", title));
+    }
+
+    if parts.is_empty() {
+        return String::from("");
+    }
+    format!("*Showing bills {}*\n\n", parts.join(", "))
+}
+
+/// A bill's line in the list, linking to its message when it has one.
+fn entry(bill: &Bill) -> String {
+    let overdue = bill.overdue_shares(Utc::now()).count();
+    let status = match bill.status() {
+        BillStatus::Open if overdue > 0 => "⏰",
+        BillStatus::Open => "🚨",
+        BillStatus::Settled => "✅",
+        BillStatus::Cancelled => "🚫",
+    };
+
+    let mut title: String = bill.title.chars().take(60).collect();
+    if title.len() < bill.title.len() {
+        title.push('…');
+    }
+    let title = match bill.message_id {
+        Some(message_id) => format!(
+            "[{}]({})",
+            title,
+            message_id.link(bill.channel_id, Some(bill.guild_id))
+        ),
+        None => title,
+    };
+
+    let payers = bill.payer_shares().count();
+    let paid = bill
+        .payer_shares()
+        .filter(|share| share.status == ShareStatus::Settled)
+        .count();
+
+    let due = match bill.due_at {
+        Some(_) if overdue > 0 => format!(" · **{} overdue**", overdue),
+        Some(due_at) if bill.status() == BillStatus::Open => {
+            format!(" · due <t:{}:R>", due_at.timestamp())
+        }
+        _ => String::from(""),
+    };
+
+    format!(
+        "{} **#{}** {} · {} · by {} · {}/{} paid{} · <t:{}:d>",
+        status,
+        bill.id,
+        title,
+        bill.total(),
+        bill.creator_name,
+        paid,
+        payers,
+        due,
+        bill.created_at.timestamp()
+    )
+}
@@ -1,2 +1,121 @@
+use rusty_money::iso::{self, Currency};
+use serenity::builder::CreateApplicationCommandOption;
+use serenity::model::prelude::command::CommandOptionType;
+use serenity::model::prelude::interaction::application_command::{
+    ApplicationCommandInteraction, CommandDataOption, CommandDataOptionValue,
+};
+use serenity::model::prelude::interaction::InteractionResponseType::ChannelMessageWithSource;
+use serenity::model::prelude::GuildId;
+use serenity::prelude::Context;
+
+use crate::store::BillStore;
+
+pub mod balance;
+pub mod cancel;
+pub mod config;
 pub mod create_bill;
 pub mod help;
+pub mod list;
+pub mod quick;
+pub mod rates;
+pub mod recurring;
+pub mod remind;
+pub mod reminders;
+pub mod settle_plan;
+pub mod show;
+
+/// Whether the invoking member can change billbot's settings for their server.
+pub fn is_admin(cmd: &ApplicationCommandInteraction) -> bool {
+    cmd.member
+        .as_ref()
+        .and_then(|member| member.permissions)
+        .is_some_and(|perms| perms.manage_guild())
+}
+
+/// Replies to the command with a message only the invoking user can see.
+pub async fn respond_ephemeral(
+    ctx: &Context,
+    cmd: &ApplicationCommandInteraction,
+    content: impl ToString,
+) -> anyhow::Result<()> {
+    cmd.create_interaction_response(&ctx.http, |res| {
+        res.kind(ChannelMessageWithSource)
+            .interaction_response_data(|msg| msg.ephemeral(true).content(content))
+    })
+    .await?;
+
+    Ok(())
+}
+
+/// The trimmed value of the string option `name`, if it was given.
+pub fn string_option(options: &[CommandDataOption], name: &str) -> Option<String> {
+    options
+        .iter()
+        .find(|opt| opt.name == name)
+        .and_then(|opt| match &opt.resolved {
+            Some(CommandDataOptionValue::String(value)) => Some(value.trim().to_owned()),
+            _ => None,
+        })
+}
+
+/// The value of the boolean option `name`, if it was given.
+pub fn bool_option(options: &[CommandDataOption], name: &str) -> Option<bool> {
+    options
+        .iter()
+        .find(|opt| opt.name == name)
+        .and_then(|opt| match &opt.resolved {
+            Some(CommandDataOptionValue::Boolean(value)) => Some(*value),
+            _ => None,
+        })
+}
+
+/// The `currency` option of commands that create bills.
+pub fn register_currency(
+    opt: &mut CreateApplicationCommandOption,
+) -> &mut CreateApplicationCommandOption {
+    opt.kind(CommandOptionType::String)
+        .name("currency")
+        .description(
+            "ISO currency code of the bill, ex. USD or EUR, defaults to the server's currency",
+        )
+}
+
+/// The `include_me` option of commands that create bills.
+pub fn register_include_me(
+    opt: &mut CreateApplicationCommandOption,
+) -> &mut CreateApplicationCommandOption {
+    opt.kind(CommandOptionType::Boolean)
+        .name("include_me")
+        .description("Count your own share in the split, or leave yourself out even if listed")
+}
+
+/// The currency picked with the `currency` option, or the server's currency if none was picked.
+/// Returns `None` once the user has been told the code they gave isn't a currency.
+pub async fn currency_option(
+    ctx: &Context,
+    cmd: &ApplicationCommandInteraction,
+    options: &[CommandDataOption],
+    store: &dyn BillStore,
+    guild_id: GuildId,
+) -> anyhow::Result<Option<&'static Currency>> {
+    let code = match string_option(options, "currency") {
+        Some(code) => code,
+        None => return Ok(Some(store.guild_settings(guild_id)?.currency)),
+    };
+
+    match iso::find(&code.to_uppercase()) {
+        Some(currency) => Ok(Some(currency)),
+        None => {
+            respond_ephemeral(
+                ctx,
+                cmd,
+                format!(
+                    "{} isn't a currency code billbot knows, try something like CAD, USD or EUR",
+                    code
+                ),
+            )
+            .await?;
+            Ok(None)
+        }
+    }
+}
@@ -0,0 +1,266 @@
+use std::collections::HashSet;
+
+use log::{error, info, warn};
+use serenity::builder::CreateApplicationCommandOption;
+use serenity::model::prelude::command::CommandOptionType;
+use serenity::model::prelude::interaction::application_command::ApplicationCommandInteraction;
+use serenity::model::prelude::interaction::autocomplete::AutocompleteInteraction;
+use serenity::model::prelude::interaction::InteractionResponseType::ChannelMessageWithSource;
+use serenity::model::prelude::Member;
+use serenity::prelude::Context;
+
+use crate::bill::{MAX_METHOD_LENGTH, MAX_TITLE_LENGTH};
+use crate::bill_message;
+use crate::commands::create_bill::{self, Prepared};
+use crate::commands::{
+    bool_option, currency_option, register_currency, register_include_me, respond_ephemeral,
+    string_option,
+};
+use crate::components::pick_payers;
+use crate::draft::{Draft, Drafts, Form};
+use crate::members::MemberDirectory;
+use crate::rates::RateProvider;
+use crate::store::BillStore;
+
+pub fn register(opt: &mut CreateApplicationCommandOption) -> &mut CreateApplicationCommandOption {
+    opt.kind(CommandOptionType::SubCommand)
+        .name("quick")
+        .description("Create a bill in one line, without the form")
+        .create_sub_option(|opt| {
+            opt.kind(CommandOptionType::String)
+                .name("title")
+                .description("What the bill is for")
+                .max_length(MAX_TITLE_LENGTH)
+                .required(true)
+        })
+        .create_sub_option(|opt| {
+            opt.kind(CommandOptionType::String)
+                .name("amount")
+                .description("The bill total as the currency writes it, ex. 420.69 or 420,69")
+                .required(true)
+        })
+        .create_sub_option(|opt| {
+            opt.kind(CommandOptionType::String)
+                .name("method")
+                .description("How to pay you back, ex. etransfer")
+                .max_length(MAX_METHOD_LENGTH)
+                .required(true)
+        })
+        .create_sub_option(|opt| {
+            opt.kind(CommandOptionType::String)
+                .name("payers")
+                .description(
+                    "Comma separated payers, ex. Jacob, Joel or Jacob:2, Joel:1 or Jacob $50, rest",
+                )
+                .required(true)
+                .set_autocomplete(true)
+        })
+        .create_sub_option(register_currency)
+        .create_sub_option(|opt| {
+            opt.kind(CommandOptionType::String)
+                .name("due")
+                .description("When it has to be paid by, ex. 2026-11-01, next friday or in 2 weeks")
+        })
+        .create_sub_option(register_include_me)
+}
+
+pub async fn respond(
+    ctx: &Context,
+    cmd: &ApplicationCommandInteraction,
+    store: &dyn BillStore,
+    rates: &dyn RateProvider,
+    directory: &MemberDirectory,
+    drafts: &Drafts,
+) -> anyhow::Result<()> {
+    let (guild_id, creator) = match (cmd.guild_id, &cmd.member) {
+        (Some(guild_id), Some(member)) => (guild_id, member),
+        _ => {
+            return respond_ephemeral(ctx, cmd, "Bills can only be created in servers").await;
+        }
+    };
+
+    let options = cmd
+        .data
+        .options
+        .first()
+        .map(|sub| sub.options.as_slice())
+        .unwrap_or_default();
+    let include_me = bool_option(options, "include_me");
+    let currency = match currency_option(ctx, cmd, options, store, guild_id).await? {
+        Some(currency) => currency,
+        None => return Ok(()),
+    };
+
+    let form = Form {
+        title: string_option(options, "title").unwrap_or_default(),
+        amount: string_option(options, "amount").unwrap_or_default(),
+        method: string_option(options, "method").unwrap_or_default(),
+        payers: string_option(options, "payers").unwrap_or_default(),
+        due: string_option(options, "due").unwrap_or_default(),
+    };
+    let mut draft = Draft::new(include_me, currency);
+    draft.form = Some(form.clone());
+
+    let prepared = create_bill::prepare(
+        ctx,
+        store,
+        rates,
+        directory,
+        guild_id,
+        cmd.channel_id,
+        creator,
+        &draft,
+        &form,
+    )
+    .await?;
+
+    let mut bill = match prepared {
+        Prepared::Ready(bill) => *bill,
+        Prepared::Rejected(why) => {
+            warn!("rejected quick bill, returning early: {}", why);
+            return respond_ephemeral(ctx, cmd, why).await;
+        }
+        Prepared::Ambiguous(ambiguous) => {
+            // from here on it's the same as a bill from the modal, so the draft is kept for
+            // the match menus and the Post button
+            let key = cmd.id.0;
+            draft.ambiguous = ambiguous.iter().map(|(name, _)| name.clone()).collect();
+            drafts.insert(key, draft);
+
+            cmd.create_interaction_response(&ctx.http, |res| {
+                res.kind(ChannelMessageWithSource)
+                    .interaction_response_data(|msg| {
+                        pick_payers::match_prompt(msg, key, &ambiguous)
+                    })
+            })
+            .await?;
+            return Ok(());
+        }
+    };
+
+    if let Err(why) = store.insert_bill(&mut bill) {
+        error!("failed to save bill: {:?}", why);
+        return respond_ephemeral(ctx, cmd, "Failed to save the bill, please try again").await;
+    }
+    info!("saved bill with id {}", bill.id);
+
+    if let Err(why) = cmd
+        .create_interaction_response(&ctx.http, |res| {
+            res.kind(ChannelMessageWithSource)
+                .interaction_response_data(|msg| {
+                    msg.content(bill_message::content(&bill))
+                        .components(|cmp| bill_message::components(&bill, cmp))
+                })
+        })
+        .await
+    {
+        bill_message::discard(store, bill.id);
+        return Err(why.into());
+    }
+
+    match cmd.get_interaction_response(&ctx.http).await {
+        Ok(message) => {
+            if let Err(why) = store.set_message_id(bill.id, message.id) {
+                error!("failed to save message id for bill {}: {:?}", bill.id, why);
+            }
+        }
+        Err(why) => error!("failed to get bill message for bill {}: {}", bill.id, why),
+    }
+
+    Ok(())
+}
+
+/// Suggests channel members for the name being typed after the last comma of the payers
+/// option, keeping everything before it as is.
+pub async fn autocomplete(
+    ctx: &Context,
+    autocomplete: &AutocompleteInteraction,
+    directory: &MemberDirectory,
+) -> anyhow::Result<()> {
+    let typed = autocomplete
+        .data
+        .options
+        .first()
+        .and_then(|sub| sub.options.iter().find(|opt| opt.focused))
+        .and_then(|opt| opt.value.as_ref())
+        .and_then(|value| value.as_str())
+        .unwrap_or_default();
+
+    let (listed, partial) = match typed.rsplit_once(',') {
+        Some((listed, partial)) => (format!("{}, ", listed.trim_end()), partial.trim()),
+        None => (String::from(""), typed.trim()),
+    };
+    let partial = partial.to_lowercase();
+
+    let already: HashSet<String> = listed
+        .split(',')
+        .map(|name| name.trim().to_lowercase())
+        .collect();
+
+    let members = match autocomplete.guild_id {
+        Some(guild_id) => {
+            directory
+                .channel_members(ctx, guild_id, autocomplete.channel_id)
+                .await?
+        }
+        None => Vec::new(),
+    };
+    let names = completion_names(&members);
+
+    let mut choices: Vec<&String> = names
+        .iter()
+        .filter(|name| !already.contains(&name.to_lowercase()))
+        .filter(|name| name.to_lowercase().contains(&partial))
+        .collect();
+    // names starting with what was typed come before names merely containing it
+    choices.sort_by_key(|name| {
+        (
+            !name.to_lowercase().starts_with(&partial),
+            name.to_lowercase(),
+        )
+    });
+
+    autocomplete
+        .create_autocomplete_response(&ctx.http, |res| {
+            for name in choices
+                .into_iter()
+                .map(|name| format!("{}{}", listed, name))
+                .filter(|choice| choice.chars().count() <= 100)
+                .take(25)
+            {
+                res.add_string_choice(&name, &name);
+            }
+            res
+        })
+        .await?;
+
+    Ok(())
+}
+
+/// The name to complete each member to, their display name unless someone else shares it, in
+/// which case their username is unambiguous.
+fn completion_names(members: &[Member]) -> Vec<String> {
+    let display_names: Vec<String> = members
+        .iter()
+        .filter(|member| !member.user.bot)
+        .map(|member| member.display_name().into_owned())
+        .collect();
+
+    members
+        .iter()
+        .filter(|member| !member.user.bot)
+        .zip(&display_names)
+        .map(|(member, name)| {
+            let shared = display_names
+                .iter()
+                .filter(|other| other.to_lowercase() == name.to_lowercase())
+                .count()
+                > 1;
+            if shared {
+                member.user.tag()
+            } else {
+                name.clone()
+            }
+        })
+        .collect()
+}
@@ -0,0 +1,119 @@
+use log::info;
+use serenity::builder::CreateApplicationCommandOption;
+use serenity::model::prelude::command::CommandOptionType;
+use serenity::model::prelude::interaction::application_command::{
+    ApplicationCommandInteraction, CommandDataOptionValue,
+};
+use serenity::model::prelude::interaction::InteractionResponseType::ChannelMessageWithSource;
+use serenity::prelude::Context;
+
+use crate::commands::is_admin;
+use crate::rates::{self, RateProvider};
+
+pub fn register(opt: &mut CreateApplicationCommandOption) -> &mut CreateApplicationCommandOption {
+    opt.kind(CommandOptionType::SubCommandGroup)
+        .name("rates")
+        .description("Exchange rates used to convert bills into the server's currency")
+        .create_sub_option(|opt| {
+            opt.kind(CommandOptionType::SubCommand)
+                .name("set")
+                .description(
+                    "Set an exchange rate for this server, requires the Manage Server permission",
+                )
+                .create_sub_option(|opt| {
+                    opt.kind(CommandOptionType::String)
+                        .name("from")
+                        .description("Currency to convert from, ex. USD")
+                        .required(true)
+                })
+                .create_sub_option(|opt| {
+                    opt.kind(CommandOptionType::String)
+                        .name("to")
+                        .description("Currency to convert to, ex. CAD")
+                        .required(true)
+                })
+                .create_sub_option(|opt| {
+                    opt.kind(CommandOptionType::String)
+                        .name("rate")
+                        .description("How much one unit of from is worth in to, ex. 1.36")
+                        .required(true)
+                })
+        })
+        .create_sub_option(|opt| {
+            opt.kind(CommandOptionType::SubCommand)
+                .name("list")
+                .description("List the exchange rates used in this server")
+        })
+}
+
+pub async fn respond(
+    ctx: &Context,
+    cmd: &ApplicationCommandInteraction,
+    rates: &dyn RateProvider,
+) -> anyhow::Result<()> {
+    let sub = cmd
+        .data
+        .options
+        .first()
+        .and_then(|group| group.options.first());
+
+    let content = match (sub, cmd.guild_id) {
+        (_, None) => String::from("Exchange rates are only available in servers"),
+        (Some(sub), Some(guild_id)) if sub.name == "set" => {
+            if is_admin(cmd) {
+                let option = |name: &str| {
+                    sub.options
+                        .iter()
+                        .find(|opt| opt.name == name)
+                        .and_then(|opt| match &opt.resolved {
+                            Some(CommandDataOptionValue::String(value)) => Some(value.as_str()),
+                            _ => None,
+                        })
+                        .unwrap_or_default()
+                };
+
+                match rates::parse_rate([option("from"), option("to"), option("rate")].into_iter())
+                {
+                    Ok((from, to, rate)) => {
+                        rates.set_rate(guild_id, from, to, rate)?;
+                        info!(
+                            "set exchange rate {} {} {} in guild {}",
+                            from.iso_alpha_code, to.iso_alpha_code, rate, guild_id
+                        );
+                        format!(
+                            "1 {} is now worth {} {}, bills created from now on will use this rate",
+                            from.iso_alpha_code, rate, to.iso_alpha_code
+                        )
+                    }
+                    Err(why) => format!("{}", why),
+                }
+            } else {
+                String::from("You need the Manage Server permission to set exchange rates")
+            }
+        }
+        (Some(sub), Some(guild_id)) if sub.name == "list" => {
+            let known = rates.rates(guild_id)?;
+            if known.is_empty() {
+                String::from("No exchange rates set yet, add one with `/bill rates set`")
+            } else {
+                let mut s = String::from("**Exchange rates**");
+                for (from, to, rate) in known.iter() {
+                    s.push_str(&format!(
+                        "\n1 {} = {} {}",
+                        from.iso_alpha_code, rate, to.iso_alpha_code
+                    ));
+                }
+                s
+            }
+        }
+        _ => String::from("Unknown rates command"),
+    };
+
+    cmd.create_interaction_response(&ctx.http, |res| {
+        res.kind(ChannelMessageWithSource)
+            .interaction_response_data(|msg| msg.ephemeral(true).content(content))
+    })
+    .await?;
+
+    Ok(())
+}
@@ -0,0 +1,361 @@
+use chrono::Utc;
+use log::{error, info, warn};
+use serenity::builder::CreateApplicationCommandOption;
+use serenity::model::prelude::command::CommandOptionType;
+use serenity::model::prelude::interaction::application_command::{
+    ApplicationCommandInteraction, CommandDataOption, CommandDataOptionValue,
+};
+use serenity::model::prelude::interaction::InteractionResponseType::ChannelMessageWithSource;
+use serenity::model::prelude::GuildId;
+use serenity::prelude::{Context, Mentionable};
+
+use crate::bill::{MAX_METHOD_LENGTH, MAX_TITLE_LENGTH};
+use crate::commands::create_bill::{self, Prepared};
+use crate::commands::{
+    bool_option, currency_option, is_admin, register_currency, register_include_me,
+    respond_ephemeral, string_option,
+};
+use crate::draft::{Draft, Form};
+use crate::members::MemberDirectory;
+use crate::rates::RateProvider;
+use crate::recurring::{RecurringBill, TemplateShare};
+use crate::schedule::Schedule;
+use crate::store::BillStore;
+
+pub fn register(opt: &mut CreateApplicationCommandOption) -> &mut CreateApplicationCommandOption {
+    opt.kind(CommandOptionType::SubCommandGroup)
+        .name("recurring")
+        .description("Bills billbot posts again on a schedule, like rent every month")
+        .create_sub_option(|opt| {
+            opt.kind(CommandOptionType::SubCommand)
+                .name("create")
+                .description("Set up a bill to be posted to this channel on a schedule")
+                .create_sub_option(|opt| {
+                    opt.kind(CommandOptionType::String)
+                        .name("title")
+                        .description("What the bill is for")
+                        .max_length(MAX_TITLE_LENGTH)
+                        .required(true)
+                })
+                .create_sub_option(|opt| {
+                    opt.kind(CommandOptionType::String)
+                        .name("amount")
+                        .description("The bill total as the currency writes it, ex. 420.69 or 420,69")
+                        .required(true)
+                })
+                .create_sub_option(|opt| {
+                    opt.kind(CommandOptionType::String)
+                        .name("method")
+                        .description("How to pay you back, ex. etransfer")
+                        .max_length(MAX_METHOD_LENGTH)
+                        .required(true)
+                })
+                .create_sub_option(|opt| {
+                    opt.kind(CommandOptionType::String)
+                        .name("payers")
+                        .description("Comma separated payers, ex. Jacob, Joel or Jacob:2, Joel:1 or Jacob $50, rest")
+                        .required(true)
+                })
+                .create_sub_option(|opt| {
+                    opt.kind(CommandOptionType::String)
+                        .name("schedule")
+                        .description(
+                            "When to post it in UTC, ex. monthly 1, weekly fri at 17:00, every 14 days, 0 9 1 * *",
+                        )
+                        .required(true)
+                })
+                .create_sub_option(register_currency)
+                .create_sub_option(register_include_me)
+        })
+        .create_sub_option(|opt| {
+            opt.kind(CommandOptionType::SubCommand)
+                .name("list")
+                .description("List the server's recurring bills")
+        })
+        .create_sub_option(|opt| {
+            opt.kind(CommandOptionType::SubCommand)
+                .name("delete")
+                .description("Stop posting a recurring bill, bills already posted are kept")
+                .create_sub_option(|opt| {
+                    opt.kind(CommandOptionType::Integer)
+                        .name("id")
+                        .description("The recurring bill's number, shown by /bill recurring list")
+                        .min_int_value(1)
+                        .required(true)
+                })
+        })
+}
+
+pub async fn respond(
+    ctx: &Context,
+    cmd: &ApplicationCommandInteraction,
+    store: &dyn BillStore,
+    rates: &dyn RateProvider,
+    directory: &MemberDirectory,
+) -> anyhow::Result<()> {
+    let guild_id = match cmd.guild_id {
+        Some(guild_id) => guild_id,
+        None => {
+            return respond_ephemeral(ctx, cmd, "Recurring bills are only available in servers")
+                .await;
+        }
+    };
+
+    let sub = cmd
+        .data
+        .options
+        .first()
+        .and_then(|group| group.options.first());
+    match sub {
+        Some(sub) if sub.name == "create" => create(ctx, cmd, sub, store, rates, directory).await,
+        Some(sub) if sub.name == "list" => list(ctx, cmd, guild_id, store).await,
+        Some(sub) if sub.name == "delete" => delete(ctx, cmd, sub, guild_id, store).await,
+        _ => respond_ephemeral(ctx, cmd, "Unknown command").await,
+    }
+}
+
+async fn create(
+    ctx: &Context,
+    cmd: &ApplicationCommandInteraction,
+    sub: &CommandDataOption,
+    store: &dyn BillStore,
+    rates: &dyn RateProvider,
+    directory: &MemberDirectory,
+) -> anyhow::Result<()> {
+    let (guild_id, creator) = match (cmd.guild_id, &cmd.member) {
+        (Some(guild_id), Some(member)) => (guild_id, member),
+        _ => {
+            return respond_ephemeral(ctx, cmd, "Recurring bills are only available in servers")
+                .await;
+        }
+    };
+
+    let options = sub.options.as_slice();
+    let include_me = bool_option(options, "include_me");
+
+    let schedule = match Schedule::parse(&string_option(options, "schedule").unwrap_or_default()) {
+        Ok(schedule) => schedule,
+        Err(why) => return respond_ephemeral(ctx, cmd, why).await,
+    };
+
+    let currency = match currency_option(ctx, cmd, options, store, guild_id).await? {
+        Some(currency) => currency,
+        None => return Ok(()),
+    };
+
+    let form = Form {
+        title: string_option(options, "title").unwrap_or_default(),
+        amount: string_option(options, "amount").unwrap_or_default(),
+        method: string_option(options, "method").unwrap_or_default(),
+        payers: string_option(options, "payers").unwrap_or_default(),
+        due: String::from(""),
+    };
+    let draft = Draft::new(include_me, currency);
+
+    // the template is checked the same way as a bill, and keeps the shares worked out for it
+    let prepared = create_bill::prepare(
+        ctx,
+        store,
+        rates,
+        directory,
+        guild_id,
+        cmd.channel_id,
+        creator,
+        &draft,
+        &form,
+    )
+    .await?;
+
+    let bill = match prepared {
+        Prepared::Ready(bill) => *bill,
+        Prepared::Rejected(why) => {
+            warn!("rejected recurring bill, returning early: {}", why);
+            return respond_ephemeral(ctx, cmd, why).await;
+        }
+        Prepared::Ambiguous(ambiguous) => {
+            let names: Vec<&str> = ambiguous.iter().map(|(name, _)| name.as_str()).collect();
+            return respond_ephemeral(
+                ctx,
+                cmd,
+                format!(
+                    "More than one person goes by {}. Use their usernames or @mention them instead",
+                    names.join(", ")
+                ),
+            )
+            .await;
+        }
+    };
+
+    // templates only keep the shares, the items would be lost on the first run
+    if !bill.items.is_empty() {
+        return respond_ephemeral(
+            ctx,
+            cmd,
+            "Recurring bills can't be split by item, list the payers with their portions instead",
+        )
+        .await;
+    }
+
+    let now = Utc::now();
+    let next_run = match schedule.first_run(now) {
+        Some(next_run) => next_run,
+        None => {
+            return respond_ephemeral(
+                ctx,
+                cmd,
+                format!("The schedule `{}` never comes up", schedule),
+            )
+            .await;
+        }
+    };
+
+    let mut template = RecurringBill {
+        id: 0,
+        guild_id,
+        channel_id: cmd.channel_id,
+        creator_id: bill.creator_id,
+        creator_name: bill.creator_name,
+        title: bill.title,
+        amount: bill.amount,
+        currency: bill.currency,
+        method: bill.method,
+        schedule,
+        next_run,
+        created_at: now,
+        shares: bill
+            .shares
+            .into_iter()
+            .map(|share| TemplateShare {
+                user_id: share.user_id,
+                name: share.name,
+                amount: share.amount,
+            })
+            .collect(),
+    };
+
+    if let Err(why) = store.insert_recurring(&mut template) {
+        error!("failed to save recurring bill: {:?}", why);
+        return respond_ephemeral(
+            ctx,
+            cmd,
+            "Failed to save the recurring bill, please try again",
+        )
+        .await;
+    }
+    info!(
+        "saved recurring bill {} ({}), first run at {}",
+        template.id, template.schedule, template.next_run
+    );
+
+    let payers: Vec<String> = template
+        .shares
+        .iter()
+        .filter(|share| share.user_id != template.creator_id)
+        .map(|share| share.user_id.mention().to_string())
+        .collect();
+    let content = format!(
+        "🔁 {} set up recurring bill {} **{}**: {} from {}, posted here `{}`. The first one goes up <t:{}:R>",
+        template.creator_id.mention(),
+        template.id,
+        template.title,
+        template.total(),
+        payers.join(" "),
+        template.schedule,
+        template.next_run.timestamp()
+    );
+
+    cmd.create_interaction_response(&ctx.http, |res| {
+        res.kind(ChannelMessageWithSource)
+            .interaction_response_data(|msg| {
+                msg.content(content)
+                    .allowed_mentions(|mentions| mentions.empty_users())
+            })
+    })
+    .await?;
+
+    Ok(())
+}
+
+async fn list(
+    ctx: &Context,
+    cmd: &ApplicationCommandInteraction,
+    guild_id: GuildId,
+    store: &dyn BillStore,
+) -> anyhow::Result<()> {
+    let templates = store.recurring_bills(guild_id)?;
+    if templates.is_empty() {
+        return respond_ephemeral(ctx, cmd, "This server has no recurring bills").await;
+    }
+
+    let mut description = String::from("");
+    for (i, template) in templates.iter().enumerate() {
+        let entry = format!(
+            "**{}** {} · {} · by {} in {} · `{}` · next <t:{}:R>\n",
+            template.id,
+            template.title,
+            template.total(),
+            template.creator_name,
+            template.channel_id.mention(),
+            template.schedule,
+            template.next_run.timestamp()
+        );
+        // embed descriptions are capped at 4096 characters
+        if description.chars().count() + entry.chars().count() > 4000 {
+            description.push_str(&format!("…and {} more", templates.len() - i));
+            break;
+        }
+        description.push_str(&entry);
+    }
+
+    cmd.create_interaction_response(&ctx.http, |res| {
+        res.kind(ChannelMessageWithSource)
+            .interaction_response_data(|msg| {
+                msg.ephemeral(true)
+                    .embed(|embed| embed.title("Recurring bills").description(description))
+            })
+    })
+    .await?;
+
+    Ok(())
+}
+
+async fn delete(
+    ctx: &Context,
+    cmd: &ApplicationCommandInteraction,
+    sub: &CommandDataOption,
+    guild_id: GuildId,
+    store: &dyn BillStore,
+) -> anyhow::Result<()> {
+    let recurring_id = sub
+        .options
+        .iter()
+        .find(|opt| opt.name == "id")
+        .and_then(|opt| match &opt.resolved {
+            Some(CommandDataOptionValue::Integer(id)) => Some(*id),
+            _ => None,
+        })
+        .unwrap_or_default();
+
+    // templates from other servers are treated as if they don't exist
+    let template = store
+        .get_recurring(recurring_id)?
+        .filter(|template| template.guild_id == guild_id);
+
+    let content = match template {
+        None => format!("There's no recurring bill {} in this server", recurring_id),
+        Some(template) if template.creator_id != cmd.user.id && !is_admin(cmd) => format!(
+            "Only {} or someone with the Manage Server permission can delete this recurring bill",
+            template.creator_name
+        ),
+        Some(template) => {
+            store.delete_recurring(template.id)?;
+            info!("recurring bill {} deleted by {}", template.id, cmd.user.id);
+            format!(
+                "**{}** won't be posted anymore, bills already posted from it are kept",
+                template.title
+            )
+        }
+    };
+
+    respond_ephemeral(ctx, cmd, content).await
+}
@@ -0,0 +1,64 @@
+use serenity::builder::CreateApplicationCommandOption;
+use serenity::model::prelude::command::CommandOptionType;
+use serenity::model::prelude::interaction::application_command::{
+    ApplicationCommandInteraction, CommandDataOptionValue,
+};
+use serenity::prelude::Context;
+
+use crate::commands::respond_ephemeral;
+use crate::components::nudge;
+use crate::store::BillStore;
+
+pub fn register(opt: &mut CreateApplicationCommandOption) -> &mut CreateApplicationCommandOption {
+    opt.kind(CommandOptionType::SubCommand)
+        .name("remind")
+        .description("Ping the payers of a bill you created who haven't paid yet")
+        .create_sub_option(|opt| {
+            opt.kind(CommandOptionType::Integer)
+                .name("bill")
+                .description("The bill's number, or start typing its title")
+                .min_int_value(1)
+                .required(true)
+                .set_autocomplete(true)
+        })
+}
+
+pub async fn respond(
+    ctx: &Context,
+    cmd: &ApplicationCommandInteraction,
+    store: &dyn BillStore,
+) -> anyhow::Result<()> {
+    let bill_id = cmd
+        .data
+        .options
+        .first()
+        .and_then(|sub| sub.options.iter().find(|opt| opt.name == "bill"))
+        .and_then(|opt| match &opt.resolved {
+            Some(CommandDataOptionValue::Integer(id)) => Some(*id),
+            _ => None,
+        })
+        .unwrap_or_default();
+
+    // bills from other servers are treated as if they don't exist
+    let bill = store
+        .get_bill(bill_id)?
+        .filter(|bill| Some(bill.guild_id) == cmd.guild_id);
+
+    let bill = match bill {
+        Some(bill) => bill,
+        None => {
+            return respond_ephemeral(
+                ctx,
+                cmd,
+                format!("There's no bill #{} in this server", bill_id),
+            )
+            .await;
+        }
+    };
+    if let Some(why) = nudge::refusal(store, &bill, cmd.user.id)? {
+        return respond_ephemeral(ctx, cmd, why).await;
+    }
+
+    nudge::nudge(ctx, store, &bill).await?;
+    respond_ephemeral(ctx, cmd, nudge::nudged_notice(&bill)).await
+}
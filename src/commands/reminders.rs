@@ -0,0 +1,103 @@
+use chrono::{Duration, Utc};
+use log::info;
+use serenity::builder::CreateApplicationCommandOption;
+use serenity::model::prelude::command::CommandOptionType;
+use serenity::model::prelude::interaction::application_command::{
+    ApplicationCommandInteraction, CommandDataOptionValue,
+};
+use serenity::model::prelude::interaction::InteractionResponseType::ChannelMessageWithSource;
+use serenity::prelude::Context;
+
+use crate::store::BillStore;
+
+pub fn register(opt: &mut CreateApplicationCommandOption) -> &mut CreateApplicationCommandOption {
+    opt.kind(CommandOptionType::SubCommandGroup)
+        .name("reminders")
+        .description("Choose how billbot reminds you of bills that are due")
+        .create_sub_option(|opt| {
+            opt.kind(CommandOptionType::SubCommand)
+                .name("snooze")
+                .description("Hold off all reminders for a while")
+                .create_sub_option(|opt| {
+                    opt.kind(CommandOptionType::Integer)
+                        .name("days")
+                        .description("How many days to hold them off for")
+                        .min_int_value(1)
+                        .max_int_value(30)
+                        .required(true)
+                })
+        })
+        .create_sub_option(|opt| {
+            opt.kind(CommandOptionType::SubCommand)
+                .name("resume")
+                .description("Get reminders again before a snooze runs out")
+        })
+        .create_sub_option(|opt| {
+            opt.kind(CommandOptionType::SubCommand)
+                .name("dm")
+                .description("Get reminders by DM, or as a ping in the bill's channel instead")
+                .create_sub_option(|opt| {
+                    opt.kind(CommandOptionType::Boolean)
+                        .name("enabled")
+                        .description("Whether to send reminders by DM")
+                        .required(true)
+                })
+        })
+}
+
+pub async fn respond(
+    ctx: &Context,
+    cmd: &ApplicationCommandInteraction,
+    store: &dyn BillStore,
+) -> anyhow::Result<()> {
+    let user_id = cmd.user.id;
+    let sub = cmd
+        .data
+        .options
+        .first()
+        .and_then(|group| group.options.first());
+    let option = |name: &str| {
+        sub.and_then(|sub| sub.options.iter().find(|opt| opt.name == name))
+            .and_then(|opt| opt.resolved.as_ref())
+    };
+
+    let content = match sub.map(|sub| sub.name.as_str()) {
+        Some("snooze") => {
+            let days = match option("days") {
+                Some(CommandDataOptionValue::Integer(days)) => *days,
+                _ => 1,
+            };
+            let until = Utc::now() + Duration::days(days);
+            store.snooze_reminders(user_id, Some(until))?;
+            info!("{} snoozed reminders until {}", user_id, until);
+            format!("No reminders until <t:{}:f>", until.timestamp())
+        }
+        Some("resume") => {
+            store.snooze_reminders(user_id, None)?;
+            info!("{} resumed reminders", user_id);
+            String::from("Reminders are back on")
+        }
+        Some("dm") => {
+            let enabled = matches!(
+                option("enabled"),
+                Some(CommandDataOptionValue::Boolean(true))
+            );
+            store.set_reminders_opted_out(user_id, !enabled)?;
+            info!("{} set reminder DMs to {}", user_id, enabled);
+            if enabled {
+                String::from("Reminders will be sent to you by DM")
+            } else {
+                String::from("No more reminder DMs, you'll be pinged in the bill's channel instead")
+            }
+        }
+        _ => String::from("Unknown command"),
+    };
+
+    cmd.create_interaction_response(&ctx.http, |res| {
+        res.kind(ChannelMessageWithSource)
+            .interaction_response_data(|msg| msg.ephemeral(true).content(content))
+    })
+    .await?;
+
+    Ok(())
+}
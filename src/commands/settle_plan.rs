@@ -0,0 +1,146 @@
+use std::collections::{BTreeSet, HashSet};
+
+use chrono::Utc;
+use log::info;
+use rusty_money::Money;
+use serenity::builder::CreateApplicationCommandOption;
+use serenity::model::prelude::command::CommandOptionType;
+use serenity::model::prelude::component::ButtonStyle;
+use serenity::model::prelude::interaction::application_command::ApplicationCommandInteraction;
+use serenity::model::prelude::interaction::InteractionResponseType::ChannelMessageWithSource;
+use serenity::prelude::{Context, Mentionable};
+
+use crate::components::settle_plan::SETTLE_PLAN_ID;
+use crate::ledger::{self, Debt, Transfer};
+use crate::rates::{self, RateProvider};
+use crate::store::{BillStore, PlanShare, SettlePlan};
+
+pub fn register(opt: &mut CreateApplicationCommandOption) -> &mut CreateApplicationCommandOption {
+    opt.kind(CommandOptionType::SubCommand)
+        .name("settle-plan")
+        .description("Work out the fewest transfers needed to settle every unsettled bill")
+}
+
+pub async fn respond(
+    ctx: &Context,
+    cmd: &ApplicationCommandInteraction,
+    store: &dyn BillStore,
+    rates: &dyn RateProvider,
+) -> anyhow::Result<()> {
+    let guild_id = match cmd.guild_id {
+        Some(id) => id,
+        None => {
+            cmd.create_interaction_response(&ctx.http, |res| {
+                res.kind(ChannelMessageWithSource)
+                    .interaction_response_data(|msg| {
+                        msg.ephemeral(true)
+                            .content("Settle plans are only available in servers")
+                    })
+            })
+            .await?;
+            return Ok(());
+        }
+    };
+
+    let bills = store.open_bills(guild_id)?;
+    let currency = store.guild_settings(guild_id)?.currency;
+    let rates = rates.rates(guild_id)?;
+    let debts = ledger::convert_debts(ledger::outstanding_debts(&bills), currency, |bill| {
+        rates::bill_rate(bill, currency, &rates)
+    })?;
+
+    if debts.is_empty() {
+        cmd.create_interaction_response(&ctx.http, |res| {
+            res.kind(ChannelMessageWithSource)
+                .interaction_response_data(|msg| {
+                    msg.ephemeral(true)
+                        .content("Nothing to settle, everyone's square 🎉")
+                })
+        })
+        .await?;
+        return Ok(());
+    }
+
+    let transfers = ledger::settle_plan(&debts);
+
+    let mut plan = SettlePlan {
+        id: 0,
+        guild_id,
+        created_at: Utc::now(),
+        settled_by: None,
+        shares: debts
+            .iter()
+            .map(|debt| PlanShare {
+                bill_id: debt.bill.id,
+                user_id: debt.debtor,
+                amount: debt.share_amount,
+                settled_at: None,
+            })
+            .collect(),
+    };
+    store.insert_settle_plan(&mut plan)?;
+    info!(
+        "created settle plan {} with {} transfers covering {} shares",
+        plan.id,
+        transfers.len(),
+        plan.shares.len()
+    );
+
+    cmd.create_interaction_response(&ctx.http, |res| {
+        res.kind(ChannelMessageWithSource)
+            .interaction_response_data(|msg| {
+                msg.content(content(&transfers, &debts)).components(|cmp| {
+                    cmp.create_action_row(|row| {
+                        row.create_button(|btn| {
+                            btn.custom_id(format!("{}:{}", SETTLE_PLAN_ID, plan.id))
+                                .style(ButtonStyle::Success)
+                                .label("Mark my transfers as received")
+                        })
+                    })
+                })
+            })
+    })
+    .await?;
+
+    Ok(())
+}
+
+fn content(transfers: &[Transfer], debts: &[Debt]) -> String {
+    let mut lines = String::from("");
+    for transfer in transfers {
+        lines.push_str(&format!(
+            "{} pays {} {}\n",
+            transfer.from.mention(),
+            transfer.to.mention(),
+            Money::from_decimal(transfer.amount, transfer.currency)
+        ));
+    }
+    if transfers.is_empty() {
+        lines.push_str("Everything cancels out, nobody needs to send anything\n");
+    }
+
+    let bill_count = debts
+        .iter()
+        .map(|debt| debt.bill.id)
+        .collect::<HashSet<_>>()
+        .len();
+
+    let converted = debts
+        .iter()
+        .filter(|debt| debt.currency != debt.bill.currency)
+        .map(|debt| debt.bill.currency.iso_alpha_code)
+        .collect::<BTreeSet<_>>();
+    let converted = if converted.is_empty() {
+        String::from("")
+    } else {
+        format!(
+            "Bills in {} were converted to the server's currency.\n",
+            converted.into_iter().collect::<Vec<_>>().join(", ")
+        )
+    };
+
+    format!(
+        "**💸 SETTLE PLAN 💸**\n >>> {}\n{}This covers {} unsettled shares across {} bills. Once your transfers have arrived, press the button to settle what you're owed.",
+        lines, converted, debts.len(), bill_count
+    )
+}
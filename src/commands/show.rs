@@ -0,0 +1,328 @@
+use chrono::Utc;
+use rusty_money::Money;
+use serenity::builder::{CreateApplicationCommandOption, CreateEmbed};
+use serenity::model::prelude::command::CommandOptionType;
+use serenity::model::prelude::interaction::application_command::{
+    ApplicationCommandInteraction, CommandDataOptionValue,
+};
+use serenity::model::prelude::interaction::autocomplete::AutocompleteInteraction;
+use serenity::model::prelude::interaction::InteractionResponseType::ChannelMessageWithSource;
+use serenity::prelude::{Context, Mentionable};
+
+use crate::bill::{Bill, BillStatus, ShareStatus};
+use crate::bill_message;
+use crate::rates;
+use crate::store::{BillFilter, BillStore};
+
+/// Discord rejects embeds with longer descriptions.
+const MAX_DESCRIPTION_LENGTH: usize = 4096;
+
+const PAYERS_HEADING: &str = "\n\n**Payers**\n";
+const ITEMS_HEADING: &str = "\n\n**Items**\n";
+
+pub fn register(opt: &mut CreateApplicationCommandOption) -> &mut CreateApplicationCommandOption {
+    opt.kind(CommandOptionType::SubCommand)
+        .name("show")
+        .description("Show a bill in full, even once its message has scrolled away")
+        .create_sub_option(|opt| {
+            opt.kind(CommandOptionType::Integer)
+                .name("bill")
+                .description("The bill's number, or start typing its title")
+                .min_int_value(1)
+                .required(true)
+                .set_autocomplete(true)
+        })
+        .create_sub_option(|opt| {
+            opt.kind(CommandOptionType::Boolean)
+                .name("public")
+                .description("Show the bill to everyone in the channel instead of just you")
+        })
+}
+
+pub async fn respond(
+    ctx: &Context,
+    cmd: &ApplicationCommandInteraction,
+    store: &dyn BillStore,
+) -> anyhow::Result<()> {
+    let options = cmd
+        .data
+        .options
+        .first()
+        .map(|sub| sub.options.as_slice())
+        .unwrap_or_default();
+
+    let bill_id = options
+        .iter()
+        .find(|opt| opt.name == "bill")
+        .and_then(|opt| match &opt.resolved {
+            Some(CommandDataOptionValue::Integer(id)) => Some(*id),
+            _ => None,
+        })
+        .unwrap_or_default();
+    let public = options
+        .iter()
+        .find(|opt| opt.name == "public")
+        .and_then(|opt| match &opt.resolved {
+            Some(CommandDataOptionValue::Boolean(public)) => Some(*public),
+            _ => None,
+        })
+        .unwrap_or(false);
+
+    // bills from other servers are treated as if they don't exist
+    let bill = store
+        .get_bill(bill_id)?
+        .filter(|bill| Some(bill.guild_id) == cmd.guild_id);
+
+    cmd.create_interaction_response(&ctx.http, |res| {
+        res.kind(ChannelMessageWithSource)
+            .interaction_response_data(|msg| match &bill {
+                Some(bill) => msg.ephemeral(!public).embed(|e| embed(e, bill)),
+                None => msg
+                    .ephemeral(true)
+                    .content(format!("There's no bill #{} in this server", bill_id)),
+            })
+    })
+    .await?;
+
+    Ok(())
+}
+
+/// Renders everything known about a bill.
+fn embed<'a>(e: &'a mut CreateEmbed, bill: &Bill) -> &'a mut CreateEmbed {
+    let status = match bill.status() {
+        BillStatus::Open => "🚨 Open",
+        BillStatus::Settled => "✅ Settled",
+        BillStatus::Cancelled => "🚫 Cancelled",
+    };
+
+    let converted = bill
+        .rate
+        .and_then(|rate| rates::convert(bill.total(), rate.currency, rate.rate).ok())
+        .map(|converted| format!(" (≈ {} {})", converted, converted.currency().iso_alpha_code))
+        .unwrap_or_default();
+
+    let mut description = format!(
+        "**{}**\nTotal amount: {}{}\nCreated by {} <t:{}:f>\nPayment method: {}",
+        status,
+        bill.total(),
+        converted,
+        bill.creator_id.mention(),
+        bill.created_at.timestamp(),
+        bill.method
+    );
+    if let Some(due_at) = bill.due_at {
+        description.push_str(&format!(
+            "\nDue <t:{}:f> (<t:{}:R>)",
+            due_at.timestamp(),
+            due_at.timestamp()
+        ));
+    }
+    if let Some(cancellation) = &bill.cancellation {
+        description.push_str(&format!(
+            "\nCancelled by {} <t:{}:f>",
+            cancellation.cancelled_by.mention(),
+            cancellation.cancelled_at.timestamp()
+        ));
+        if !cancellation.reason.is_empty() {
+            description.push_str(&format!(": {}", cancellation.reason));
+        }
+    }
+    if let Some(message_id) = bill.message_id {
+        description.push_str(&format!(
+            "\n[Jump to the bill]({})",
+            message_id.link(bill.channel_id, Some(bill.guild_id))
+        ));
+    }
+
+    let now = Utc::now();
+    let payers = bill
+        .shares
+        .iter()
+        .map(|share| {
+            let state = if share.user_id == bill.creator_id {
+                String::from("their own share")
+            } else if bill.is_overdue(share, now) {
+                String::from("⏰ overdue")
+            } else {
+                match (share.status, share.claimed_at, share.settled_at) {
+                    (ShareStatus::Settled, _, Some(settled_at)) => {
+                        format!("✅ paid <t:{}:R>", settled_at.timestamp())
+                    }
+                    (ShareStatus::Settled, _, None) => String::from("✅ paid"),
+                    (ShareStatus::Claimed, Some(claimed_at), _) => format!(
+                        "📨 sent <t:{}:R>, awaiting confirmation",
+                        claimed_at.timestamp()
+                    ),
+                    (ShareStatus::Claimed, None, _) => {
+                        String::from("📨 sent, awaiting confirmation")
+                    }
+                    (ShareStatus::Owed, _, _) => String::from("owes"),
+                }
+            };
+
+            format!(
+                "{} {} · {}",
+                share.user_id.mention(),
+                Money::from_decimal(share.amount, bill.currency),
+                state
+            )
+        })
+        .collect::<Vec<_>>();
+
+    // payers go in the description since 20 of them don't fit in a field, items get whatever
+    // room they leave
+    let payers_room =
+        MAX_DESCRIPTION_LENGTH.saturating_sub(description.chars().count() + PAYERS_HEADING.len());
+    let payers_room = if bill.items.is_empty() {
+        payers_room
+    } else {
+        payers_room.saturating_sub(ITEMS_HEADING.len()) / 2
+    };
+    description.push_str(PAYERS_HEADING);
+    description.push_str(&fit_lines(&payers, payers_room));
+
+    if !bill.items.is_empty() {
+        let items: Vec<String> = bill
+            .items
+            .iter()
+            .map(|item| {
+                format!(
+                    "{} {} · {}",
+                    item.name,
+                    Money::from_decimal(item.amount, bill.currency),
+                    item.payers
+                        .iter()
+                        .map(|user_id| user_id.mention().to_string())
+                        .collect::<Vec<_>>()
+                        .join(" ")
+                )
+            })
+            .collect();
+        let items_room = MAX_DESCRIPTION_LENGTH
+            .saturating_sub(description.chars().count() + ITEMS_HEADING.len());
+        description.push_str(ITEMS_HEADING);
+        description.push_str(&fit_lines(&items, items_room));
+    }
+    // bills from before the input limits can have a longer method than fits on its own
+    let description = bill_message::truncate(&description, MAX_DESCRIPTION_LENGTH);
+
+    let title: String = bill.title.chars().take(200).collect();
+    e.title(format!("#{} {}", bill.id, title))
+        .description(description);
+    if let Some(message_id) = bill.message_id {
+        e.url(message_id.link(bill.channel_id, Some(bill.guild_id)));
+    }
+
+    e
+}
+
+/// Joins as many of `lines` as fit in `max` characters, ending with how many more there are
+/// when they don't all fit.
+fn fit_lines(lines: &[String], max: usize) -> String {
+    let mut fitted = String::new();
+    for (i, line) in lines.iter().enumerate() {
+        let separator = if i == 0 { "" } else { "\n" };
+        let more = if i + 1 < lines.len() {
+            format!("\n+{} more", lines.len() - i - 1)
+        } else {
+            String::new()
+        };
+        let len = fitted.chars().count() + separator.len() + line.chars().count();
+        if len + more.len() > max {
+            let more = format!("+{} more", lines.len() - i);
+            return if fitted.is_empty() {
+                more
+            } else {
+                format!("{}\n{}", fitted, more)
+            };
+        }
+        fitted.push_str(separator);
+        fitted.push_str(line);
+    }
+
+    fitted
+}
+
+/// Suggests the guild's bills whose title contains what's been typed, most recent first. A
+/// typed number also suggests the bill with that number.
+pub async fn autocomplete(
+    ctx: &Context,
+    autocomplete: &AutocompleteInteraction,
+    store: &dyn BillStore,
+) -> anyhow::Result<()> {
+    let typed = autocomplete
+        .data
+        .options
+        .first()
+        .and_then(|sub| sub.options.iter().find(|opt| opt.focused))
+        .and_then(|opt| opt.value.as_ref())
+        .map(|value| match value.as_str() {
+            Some(typed) => typed.trim().to_owned(),
+            None => value.to_string(),
+        })
+        .unwrap_or_default();
+
+    let mut bills = Vec::new();
+    if let Some(guild_id) = autocomplete.guild_id {
+        if let Ok(id) = typed.parse() {
+            if let Some(bill) = store.get_bill(id)?.filter(|bill| bill.guild_id == guild_id) {
+                bills.push(bill);
+            }
+        }
+
+        let filter = BillFilter {
+            title: Some(typed).filter(|typed| !typed.is_empty()),
+            ..Default::default()
+        };
+        let (recent, _) = store.list_bills(guild_id, &filter, 0, 25)?;
+        for bill in recent {
+            if !bills.iter().any(|listed| listed.id == bill.id) {
+                bills.push(bill);
+            }
+        }
+    }
+
+    autocomplete
+        .create_autocomplete_response(&ctx.http, |res| {
+            for bill in bills.iter().take(25) {
+                let suffix = format!(" · {} · {}", bill.total(), bill.status().as_str());
+                let title: String = bill
+                    .title
+                    .chars()
+                    .take(100 - suffix.chars().count() - format!("#{} ", bill.id).len())
+                    .collect();
+                res.add_int_choice(format!("#{} {}{}", bill.id, title, suffix), bill.id);
+            }
+            res
+        })
+        .await?;
+
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn lines(count: usize) -> Vec<String> {
+        (1..=count).map(|i| format!("line {:02}", i)).collect()
+    }
+
+    #[test]
+    fn lines_that_fit_are_kept_whole() {
+        assert_eq!(fit_lines(&lines(3), 100), "line 01\nline 02\nline 03");
+        assert_eq!(fit_lines(&lines(3), 23), "line 01\nline 02\nline 03");
+    }
+
+    #[test]
+    fn lines_that_dont_fit_are_counted() {
+        let fitted = fit_lines(&lines(20), 50);
+        assert_eq!(
+            fitted,
+            "line 01\nline 02\nline 03\nline 04\nline 05\n+15 more"
+        );
+        assert!(fitted.chars().count() <= 50);
+
+        assert_eq!(fit_lines(&lines(3), 5), "+3 more");
+    }
+}
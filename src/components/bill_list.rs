@@ -0,0 +1,53 @@
+use anyhow::Context as _;
+use serenity::model::prelude::interaction::message_component::MessageComponentInteraction;
+use serenity::model::prelude::interaction::InteractionResponseType::UpdateMessage;
+use serenity::prelude::Context;
+
+use crate::commands::list::{self, Listings};
+use crate::components::respond_ephemeral;
+use crate::store::BillStore;
+
+pub const PAGE_ID: &str = "bill_list_page";
+
+/// Shows another page of a list, keeping the list's filters.
+pub async fn respond(
+    ctx: &Context,
+    component: &MessageComponentInteraction,
+    store: &dyn BillStore,
+    listings: &Listings,
+) -> anyhow::Result<()> {
+    let mut parts = component.data.custom_id.split(':').skip(1);
+    let key: u64 = parts
+        .next()
+        .context("missing list key")?
+        .parse()
+        .context("invalid list key")?;
+    let page: usize = parts
+        .next()
+        .context("missing page")?
+        .parse()
+        .context("invalid page")?;
+    let guild_id = component.guild_id.context("no guild id for bill list")?;
+
+    let filter = match listings.get(key) {
+        Some(filter) => filter,
+        None => {
+            return respond_ephemeral(
+                ctx,
+                component,
+                "This list is out of date, run `/bill list` again",
+            )
+            .await;
+        }
+    };
+    let (bills, total) = list::load_page(store, guild_id, &filter, page)?;
+
+    component
+        .create_interaction_response(&ctx.http, |res| {
+            res.kind(UpdateMessage)
+                .interaction_response_data(|msg| list::page(msg, key, &filter, &bills, total, page))
+        })
+        .await?;
+
+    Ok(())
+}
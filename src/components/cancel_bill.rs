@@ -0,0 +1,234 @@
+use chrono::Utc;
+use log::{error, info, warn};
+use rusty_money::Money;
+use serenity::model::prelude::component::ActionRowComponent;
+use serenity::model::prelude::component::InputTextStyle::Paragraph;
+use serenity::model::prelude::interaction::message_component::MessageComponentInteraction;
+use serenity::model::prelude::interaction::modal::ModalSubmitInteraction;
+use serenity::model::prelude::interaction::InteractionResponseType::Modal;
+use serenity::model::prelude::UserId;
+use serenity::prelude::Context;
+
+use crate::bill::{Bill, BillShare, Cancellation, ShareStatus};
+use crate::bill_message;
+use crate::commands::create_bill;
+use crate::components::{load_bill, parse_bill_custom_id, respond_ephemeral};
+use crate::store::BillStore;
+
+pub const CANCEL_MODAL_ID: &str = "bill_cancel_modal";
+
+/// Why `user_id` can't cancel the bill, if they can't.
+pub fn refusal(bill: &Bill, user_id: UserId) -> Option<String> {
+    if user_id != bill.creator_id {
+        Some(format!("Only {} can cancel this bill", bill.creator_name))
+    } else if bill.is_cancelled() {
+        Some(String::from("This bill is already cancelled"))
+    } else {
+        None
+    }
+}
+
+/// Asks the bill creator why they're cancelling the bill.
+pub async fn respond(
+    ctx: &Context,
+    component: &MessageComponentInteraction,
+    store: &dyn BillStore,
+) -> anyhow::Result<()> {
+    let bill = load_bill(store, parse_bill_custom_id(&component.data.custom_id)?)?;
+
+    if let Some(why) = refusal(&bill, component.user.id) {
+        return respond_ephemeral(ctx, component, why).await;
+    }
+
+    component
+        .create_interaction_response(&ctx.http, |res| {
+            res.kind(Modal).interaction_response_data(|modal| {
+                modal
+                    .custom_id(format!("{}:{}", CANCEL_MODAL_ID, bill.id))
+                    .title("Cancel bill")
+                    .components(|cmp| {
+                        cmp.create_action_row(|row| {
+                            row.create_input_text(|input| {
+                                input
+                                    .custom_id("reason")
+                                    .label("Reason (optional)")
+                                    .style(Paragraph)
+                                    .required(false)
+                                    .placeholder("Ex. posted it twice")
+                            })
+                        })
+                    })
+            })
+        })
+        .await?;
+
+    Ok(())
+}
+
+pub async fn submit(
+    ctx: &Context,
+    submission: &ModalSubmitInteraction,
+    store: &dyn BillStore,
+) -> anyhow::Result<()> {
+    let bill = load_bill(store, parse_bill_custom_id(&submission.data.custom_id)?)?;
+
+    if let Some(why) = refusal(&bill, submission.user.id) {
+        create_bill::respond_ephemeral(ctx, submission, why).await;
+        return Ok(());
+    }
+
+    let mut reason = String::from("");
+    for row in &submission.data.components {
+        for component in &row.components {
+            if let ActionRowComponent::InputText(data) = component {
+                if data.custom_id == "reason" {
+                    reason = data.value.trim().to_owned();
+                }
+            }
+        }
+    }
+
+    // the interaction is answered first, posting refunds can take longer than discord waits
+    let bill = cancel(store, bill.id, submission.user.id, reason)?;
+    create_bill::respond_ephemeral(ctx, submission, cancelled_notice(&bill)).await;
+    announce(ctx, store, &bill).await;
+
+    Ok(())
+}
+
+/// What the creator is told once their bill is cancelled.
+pub fn cancelled_notice(bill: &Bill) -> String {
+    match paid_shares(bill).len() {
+        0 => format!("Cancelled **{}**", bill.title),
+        1 => format!(
+            "Cancelled **{}**, you owe a refund to the payer who already sent their share",
+            bill.title
+        ),
+        n => format!(
+            "Cancelled **{}**, you owe refunds to the {} payers who already sent their share",
+            bill.title, n
+        ),
+    }
+}
+
+/// Shares whose payer already sent the money, which is owed back once the bill is cancelled.
+fn paid_shares(bill: &Bill) -> Vec<&BillShare> {
+    bill.payer_shares()
+        .filter(|share| share.status != ShareStatus::Owed)
+        .collect()
+}
+
+/// Cancels the bill, returning it as it is now.
+pub fn cancel(
+    store: &dyn BillStore,
+    bill_id: i64,
+    cancelled_by: UserId,
+    reason: String,
+) -> anyhow::Result<Bill> {
+    let cancellation = Cancellation {
+        cancelled_by,
+        reason,
+        cancelled_at: Utc::now(),
+    };
+    store.cancel_bill(bill_id, &cancellation)?;
+    info!(
+        "bill {} cancelled by {}: {}",
+        bill_id, cancelled_by, cancellation.reason
+    );
+
+    load_bill(store, bill_id)
+}
+
+/// Marks a cancelled bill's message as cancelled. Payers who already sent their share are
+/// owed it back, which is posted as a refund bill from each of them to the creator so it shows
+/// up in balances like any other debt.
+pub async fn announce(ctx: &Context, store: &dyn BillStore, bill: &Bill) {
+    if let Err(why) = bill_message::update(ctx, bill).await {
+        warn!("failed to update message for bill {}: {:?}", bill.id, why);
+    }
+
+    let paid = paid_shares(bill);
+    if paid.is_empty() {
+        return;
+    }
+
+    let notice = format!(
+        "{} {} cancelled **{}**, so they owe back what you already sent:\n{}",
+        bill_message::create_payer_mention_string(paid.iter().copied()),
+        bill.creator_name,
+        bill.title,
+        paid.iter()
+            .map(|share| format!(
+                "{}: {}",
+                share.name,
+                Money::from_decimal(share.amount, bill.currency)
+            ))
+            .collect::<Vec<_>>()
+            .join("\n")
+    );
+    if let Err(why) = bill
+        .channel_id
+        .send_message(&ctx.http, |msg| {
+            if let Some(message_id) = bill.message_id {
+                msg.reference_message((bill.channel_id, message_id));
+            }
+            msg.content(notice)
+        })
+        .await
+    {
+        warn!(
+            "failed to notify payers of cancelled bill {}: {}",
+            bill.id, why
+        );
+    }
+
+    for share in paid {
+        if let Err(why) = post_refund(ctx, store, bill, share).await {
+            error!(
+                "failed to post refund of bill {} to {}: {:?}",
+                bill.id, share.user_id, why
+            );
+        }
+    }
+}
+
+/// Posts a bill for the creator to pay a share back to the payer who sent it.
+async fn post_refund(
+    ctx: &Context,
+    store: &dyn BillStore,
+    bill: &Bill,
+    share: &BillShare,
+) -> anyhow::Result<()> {
+    let mut refund = Bill {
+        id: 0,
+        guild_id: bill.guild_id,
+        channel_id: bill.channel_id,
+        message_id: None,
+        creator_id: share.user_id,
+        creator_name: share.name.clone(),
+        title: format!("Refund for {}", bill.title),
+        amount: share.amount,
+        currency: bill.currency,
+        method: String::from("Same way it was sent"),
+        created_at: Utc::now(),
+        due_at: None,
+        rate: bill.rate,
+        cancellation: None,
+        shares: vec![BillShare {
+            user_id: bill.creator_id,
+            name: bill.creator_name.clone(),
+            amount: share.amount,
+            status: ShareStatus::Owed,
+            claimed_at: None,
+            settled_at: None,
+        }],
+        items: Vec::new(),
+    };
+    store.insert_bill(&mut refund)?;
+    info!(
+        "saved refund bill {} for cancelled bill {}",
+        refund.id, bill.id
+    );
+
+    bill_message::send(ctx, store, &mut refund).await
+}
@@ -0,0 +1,286 @@
+use anyhow::Context as _;
+use log::{info, warn};
+use rusty_money::Money;
+use serenity::builder::CreateComponents;
+use serenity::model::prelude::component::ActionRowComponent;
+use serenity::model::prelude::component::ButtonStyle;
+use serenity::model::prelude::component::InputTextStyle::Paragraph;
+use serenity::model::prelude::interaction::message_component::MessageComponentInteraction;
+use serenity::model::prelude::interaction::modal::ModalSubmitInteraction;
+use serenity::model::prelude::interaction::InteractionResponseType::{
+    ChannelMessageWithSource, Modal, UpdateMessage,
+};
+use serenity::model::prelude::UserId;
+use serenity::prelude::{Context, Mentionable};
+
+use crate::bill::{Bill, ShareStatus};
+use crate::bill_message;
+use crate::components::{load_bill, parse_share_custom_id, respond_ephemeral};
+use crate::store::BillStore;
+
+pub const CONFIRM_ID: &str = "bill_confirm";
+pub const REJECT_ID: &str = "bill_reject";
+pub const REJECT_MODAL_ID: &str = "bill_reject_reason";
+
+/// Asks the bill creator to confirm they received a payer's payment. The prompt is sent by
+/// DM, falling back to a message in the bill's channel if the creator doesn't accept DMs.
+pub async fn prompt(
+    ctx: &Context,
+    component: &MessageComponentInteraction,
+    bill: &Bill,
+    payer_id: UserId,
+) -> anyhow::Result<()> {
+    let share = bill
+        .share(payer_id)
+        .with_context(|| format!("bill {} has no share for user {}", bill.id, payer_id))?;
+
+    let content = format!(
+        "{} says they sent you {} for **{}**, did you receive it?",
+        payer_id.mention(),
+        Money::from_decimal(share.amount, bill.currency),
+        bill.title
+    );
+
+    let dm_result = async {
+        let channel = bill.creator_id.create_dm_channel(&ctx.http).await?;
+        channel
+            .send_message(&ctx.http, |msg| {
+                msg.content(&content)
+                    .components(|cmp| prompt_components(cmp, bill.id, payer_id))
+            })
+            .await
+    }
+    .await;
+
+    if let Err(why) = dm_result {
+        warn!(
+            "failed to DM creator of bill {}, prompting in channel instead: {}",
+            bill.id, why
+        );
+        component
+            .create_followup_message(&ctx.http, |msg| {
+                msg.content(format!("{} {}", bill.creator_id.mention(), content))
+                    .components(|cmp| prompt_components(cmp, bill.id, payer_id))
+            })
+            .await?;
+    }
+
+    Ok(())
+}
+
+fn prompt_components(
+    cmp: &mut CreateComponents,
+    bill_id: i64,
+    payer_id: UserId,
+) -> &mut CreateComponents {
+    cmp.create_action_row(|row| {
+        row.create_button(|btn| {
+            btn.custom_id(format!("{}:{}:{}", CONFIRM_ID, bill_id, payer_id))
+                .style(ButtonStyle::Success)
+                .label("Confirm")
+        })
+        .create_button(|btn| {
+            btn.custom_id(format!("{}:{}:{}", REJECT_ID, bill_id, payer_id))
+                .style(ButtonStyle::Danger)
+                .label("Reject")
+        })
+    })
+}
+
+pub async fn confirm(
+    ctx: &Context,
+    component: &MessageComponentInteraction,
+    store: &dyn BillStore,
+) -> anyhow::Result<()> {
+    let (bill_id, payer_id) = parse_share_custom_id(&component.data.custom_id)?;
+    let bill = load_bill(store, bill_id)?;
+
+    if component.user.id != bill.creator_id {
+        return respond_ephemeral(ctx, component, "Only the bill creator can confirm payments")
+            .await;
+    }
+
+    let share = bill
+        .share(payer_id)
+        .with_context(|| format!("bill {} has no share for user {}", bill_id, payer_id))?;
+
+    if share.status != ShareStatus::Claimed || bill.is_cancelled() {
+        return close_prompt(
+            ctx,
+            component,
+            format!(
+                "{}'s payment for **{}** is no longer awaiting confirmation",
+                share.name, bill.title
+            ),
+        )
+        .await;
+    }
+
+    store.set_share_status(bill_id, payer_id, ShareStatus::Settled)?;
+    info!("payment of {} on bill {} confirmed", payer_id, bill_id);
+
+    let bill = load_bill(store, bill_id)?;
+    if let Err(why) = bill_message::update(ctx, &bill).await {
+        warn!("failed to update message for bill {}: {:?}", bill_id, why);
+    }
+
+    close_prompt(
+        ctx,
+        component,
+        format!(
+            "✅ Confirmed {}'s payment for **{}**",
+            share.name, bill.title
+        ),
+    )
+    .await
+}
+
+pub async fn reject(
+    ctx: &Context,
+    component: &MessageComponentInteraction,
+    store: &dyn BillStore,
+) -> anyhow::Result<()> {
+    let (bill_id, payer_id) = parse_share_custom_id(&component.data.custom_id)?;
+    let bill = load_bill(store, bill_id)?;
+
+    if component.user.id != bill.creator_id {
+        return respond_ephemeral(ctx, component, "Only the bill creator can reject payments")
+            .await;
+    }
+
+    let share = bill
+        .share(payer_id)
+        .with_context(|| format!("bill {} has no share for user {}", bill_id, payer_id))?;
+
+    if share.status != ShareStatus::Claimed || bill.is_cancelled() {
+        return close_prompt(
+            ctx,
+            component,
+            format!(
+                "{}'s payment for **{}** is no longer awaiting confirmation",
+                share.name, bill.title
+            ),
+        )
+        .await;
+    }
+
+    component
+        .create_interaction_response(&ctx.http, |res| {
+            res.kind(Modal).interaction_response_data(|modal| {
+                modal
+                    .custom_id(format!("{}:{}:{}", REJECT_MODAL_ID, bill_id, payer_id))
+                    .title("Reject payment")
+                    .components(|cmp| {
+                        cmp.create_action_row(|row| {
+                            row.create_input_text(|input| {
+                                input
+                                    .custom_id("reason")
+                                    .label("Reason")
+                                    .style(Paragraph)
+                                    .placeholder("Ex. nothing showed up in my account")
+                            })
+                        })
+                    })
+            })
+        })
+        .await?;
+
+    Ok(())
+}
+
+pub async fn submit_rejection(
+    ctx: &Context,
+    submission: &ModalSubmitInteraction,
+    store: &dyn BillStore,
+) -> anyhow::Result<()> {
+    let (bill_id, payer_id) = parse_share_custom_id(&submission.data.custom_id)?;
+    let bill = load_bill(store, bill_id)?;
+
+    let share = bill
+        .share(payer_id)
+        .with_context(|| format!("bill {} has no share for user {}", bill_id, payer_id))?;
+
+    if submission.user.id != bill.creator_id
+        || share.status != ShareStatus::Claimed
+        || bill.is_cancelled()
+    {
+        submission
+            .create_interaction_response(&ctx.http, |res| {
+                res.kind(ChannelMessageWithSource)
+                    .interaction_response_data(|msg| {
+                        msg.ephemeral(true)
+                            .content("This payment is no longer awaiting confirmation")
+                    })
+            })
+            .await?;
+        return Ok(());
+    }
+
+    let mut reason = String::from("");
+    for row in &submission.data.components {
+        for component in &row.components {
+            if let ActionRowComponent::InputText(data) = component {
+                if data.custom_id == "reason" {
+                    reason = data.value.trim().to_owned();
+                }
+            }
+        }
+    }
+
+    store.set_share_status(bill_id, payer_id, ShareStatus::Owed)?;
+    info!(
+        "payment of {} on bill {} rejected: {}",
+        payer_id, bill_id, reason
+    );
+
+    let bill = load_bill(store, bill_id)?;
+    if let Err(why) = bill_message::update(ctx, &bill).await {
+        warn!("failed to update message for bill {}: {:?}", bill_id, why);
+    }
+
+    let notice = format!(
+        "{} {} didn't receive your payment for **{}**: {}",
+        payer_id.mention(),
+        bill.creator_name,
+        bill.title,
+        reason
+    );
+    bill.channel_id
+        .send_message(&ctx.http, |msg| {
+            if let Some(message_id) = bill.message_id {
+                msg.reference_message((bill.channel_id, message_id));
+            }
+            msg.content(notice)
+        })
+        .await?;
+
+    submission
+        .create_interaction_response(&ctx.http, |res| {
+            res.kind(UpdateMessage).interaction_response_data(|msg| {
+                msg.content(format!(
+                    "❌ Rejected {}'s payment for **{}**: {}",
+                    share.name, bill.title, reason
+                ))
+                .components(|cmp| cmp)
+            })
+        })
+        .await?;
+
+    Ok(())
+}
+
+/// Replaces a confirmation prompt with a final message and removes its buttons.
+async fn close_prompt(
+    ctx: &Context,
+    component: &MessageComponentInteraction,
+    content: String,
+) -> anyhow::Result<()> {
+    component
+        .create_interaction_response(&ctx.http, |res| {
+            res.kind(UpdateMessage)
+                .interaction_response_data(|msg| msg.content(content).components(|cmp| cmp))
+        })
+        .await?;
+
+    Ok(())
+}
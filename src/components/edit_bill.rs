@@ -0,0 +1,537 @@
+use anyhow::Context as _;
+use log::{info, warn};
+use rust_decimal::Decimal;
+use rusty_money::Money;
+use serenity::model::prelude::interaction::message_component::MessageComponentInteraction;
+use serenity::model::prelude::interaction::modal::ModalSubmitInteraction;
+use serenity::model::prelude::interaction::InteractionResponseType::{
+    ChannelMessageWithSource, Modal,
+};
+use serenity::model::prelude::{Member, UserId};
+use serenity::prelude::{Context, Mentionable};
+
+use crate::bill::{Bill, ShareStatus, MAX_METHOD_LENGTH, MAX_TITLE_LENGTH};
+use crate::bill_message;
+use crate::commands::create_bill::{self, Prepared};
+use crate::components::{load_bill, parse_bill_custom_id, pick_payers, respond_ephemeral};
+use crate::draft::{Draft, Drafts, Form};
+use crate::members::MemberDirectory;
+use crate::payers::{self, Resolution};
+use crate::rates::RateProvider;
+use crate::split::{self, Portion};
+use crate::store::BillStore;
+
+pub const EDIT_MODAL_ID: &str = "bill_edit_modal";
+
+/// Opens the bill form prefilled with the bill as it is, for its creator only.
+pub async fn respond(
+    ctx: &Context,
+    component: &MessageComponentInteraction,
+    store: &dyn BillStore,
+    directory: &MemberDirectory,
+) -> anyhow::Result<()> {
+    let bill = load_bill(store, parse_bill_custom_id(&component.data.custom_id)?)?;
+
+    if let Some(why) = refusal(&bill, component.user.id) {
+        return respond_ephemeral(ctx, component, why).await;
+    }
+
+    let members = match directory
+        .channel_members(ctx, bill.guild_id, bill.channel_id)
+        .await
+    {
+        Ok(members) => members,
+        Err(why) => {
+            warn!(
+                "failed to get channel members for bill {}: {:?}",
+                bill.id, why
+            );
+            Vec::new()
+        }
+    };
+
+    let form = prefill(&bill, &members);
+
+    component
+        .create_interaction_response(&ctx.http, |res| {
+            res.kind(Modal).interaction_response_data(|modal| {
+                modal
+                    .custom_id(format!("{}:{}", EDIT_MODAL_ID, bill.id))
+                    .title("Edit bill")
+                    .components(|cmp| create_bill::form_inputs(cmp, bill.currency, &form, true))
+            })
+        })
+        .await?;
+
+    Ok(())
+}
+
+/// The bill form filled in with the bill as it is, so submitting it untouched changes nothing.
+fn prefill(bill: &Bill, members: &[Member]) -> Form {
+    // discord won't open a form prefilled with more than an input allows, which bills from
+    // before the limits could have
+    Form {
+        title: bill.title.chars().take(MAX_TITLE_LENGTH.into()).collect(),
+        amount: split::format_money(bill.amount, bill.currency),
+        method: bill.method.chars().take(MAX_METHOD_LENGTH.into()).collect(),
+        payers: payers_field(bill, members),
+        due: bill
+            .due_at
+            .map(|due_at| due_at.format("%Y-%m-%d").to_string())
+            .unwrap_or_default(),
+    }
+}
+
+/// Writes the bill's shares the way they'd be typed into the payers field, so submitting the
+/// form untouched splits the bill the same way. Payers go by display name unless it wouldn't
+/// find them again, in which case they're mentioned.
+fn payers_field(bill: &Bill, members: &[Member]) -> String {
+    if !bill.items.is_empty() {
+        return items_field(bill, members);
+    }
+
+    let even = split::split(
+        bill.amount,
+        bill.currency,
+        &vec![Portion::Weight(Decimal::ONE); bill.shares.len()],
+    )
+    .map(|amounts| {
+        amounts
+            .iter()
+            .eq(bill.shares.iter().map(|share| &share.amount))
+    })
+    .unwrap_or(false);
+
+    bill.shares
+        .iter()
+        .map(|share| {
+            let name = typed_name(share.user_id, members, |name| {
+                !name.contains([',', ':']) && !payers::may_be_role(name) && !split::is_rest(name)
+            });
+
+            // uneven splits weigh each payer by what they owe, which keeps the proportions if
+            // only the total changes
+            if even {
+                name
+            } else {
+                format!("{}:{}", name, share.amount.normalize())
+            }
+        })
+        .collect::<Vec<_>>()
+        .join(", ")
+}
+
+/// Writes an itemized bill's items a line each, the way they'd be typed into the payers field.
+/// Everyone an item is for is listed by name, even if it was typed as @all.
+fn items_field(bill: &Bill, members: &[Member]) -> String {
+    bill.items
+        .iter()
+        .map(|item| {
+            let assignees = item
+                .payers
+                .iter()
+                .map(|user_id| {
+                    let name = typed_name(*user_id, members, |name| {
+                        !name.contains([',', '@']) && !payers::may_be_role(name)
+                    });
+                    if name.starts_with("<@") {
+                        name
+                    } else {
+                        format!("@{}", name)
+                    }
+                })
+                .collect::<Vec<_>>()
+                .join(" ");
+
+            format!(
+                "{} {} {}",
+                item.name,
+                split::format_money(item.amount, bill.currency),
+                assignees
+            )
+        })
+        .collect::<Vec<_>>()
+        .join("\n")
+}
+
+/// A member's display name if `usable` allows it and it finds them again, their mention if not.
+fn typed_name(user_id: UserId, members: &[Member], usable: impl Fn(&str) -> bool) -> String {
+    let member = members.iter().find(|member| member.user.id == user_id);
+    match member {
+        Some(member) => {
+            let name = member.display_name();
+            let finds_them = match payers::resolve(&name, members) {
+                Resolution::Found(found) => found.user.id == user_id,
+                _ => false,
+            };
+            if usable(&name) && finds_them {
+                name.into_owned()
+            } else {
+                user_id.mention().to_string()
+            }
+        }
+        None => user_id.mention().to_string(),
+    }
+}
+
+/// Why `user_id` can't edit the bill, if they can't.
+pub fn refusal(bill: &Bill, user_id: UserId) -> Option<String> {
+    if user_id != bill.creator_id {
+        Some(format!("Only {} can edit this bill", bill.creator_name))
+    } else if bill.is_cancelled() {
+        Some(String::from("Cancelled bills can't be edited"))
+    } else {
+        None
+    }
+}
+
+/// Recomputes the bill from the submitted form and edits its message in place. Names that
+/// match more than one member are asked about first, the same way as for new bills.
+pub async fn submit(
+    ctx: &Context,
+    submission: &ModalSubmitInteraction,
+    store: &dyn BillStore,
+    rates: &dyn RateProvider,
+    directory: &MemberDirectory,
+    drafts: &Drafts,
+) -> anyhow::Result<()> {
+    let old = load_bill(store, parse_bill_custom_id(&submission.data.custom_id)?)?;
+    let creator = submission
+        .member
+        .as_ref()
+        .context("no member for bill edit")?;
+
+    if let Some(why) = refusal(&old, creator.user.id) {
+        create_bill::respond_ephemeral(ctx, submission, why).await;
+        return Ok(());
+    }
+
+    let form = create_bill::read_form(submission);
+    let mut draft = Draft::new(None, old.currency);
+    draft.editing = Some(old.id);
+    draft.current_due_at = old.due_at;
+
+    let prepared = create_bill::prepare(
+        ctx,
+        store,
+        rates,
+        directory,
+        old.guild_id,
+        old.channel_id,
+        creator,
+        &draft,
+        &form,
+    )
+    .await?;
+
+    let bill = match prepared {
+        Prepared::Ready(bill) => *bill,
+        Prepared::Rejected(why) => {
+            warn!("rejected edit of bill {}: {}", old.id, why);
+            create_bill::respond_ephemeral(ctx, submission, why).await;
+            return Ok(());
+        }
+        Prepared::Ambiguous(ambiguous) => {
+            // the match menus and the Post button finish the edit once it's clear who's who
+            let key = submission.id.0;
+            draft.form = Some(form);
+            draft.ambiguous = ambiguous.iter().map(|(name, _)| name.clone()).collect();
+            drafts.insert(key, draft);
+
+            submission
+                .create_interaction_response(&ctx.http, |res| {
+                    res.kind(ChannelMessageWithSource)
+                        .interaction_response_data(|msg| {
+                            pick_payers::match_prompt(msg, key, &ambiguous)
+                        })
+                })
+                .await?;
+            return Ok(());
+        }
+    };
+
+    let content = save(ctx, store, &old, bill, creator.user.id).await?;
+    create_bill::respond_ephemeral(ctx, submission, content).await;
+
+    Ok(())
+}
+
+/// Saves `bill` in place of `old` and edits its message. Payers who stay on the bill keep
+/// whatever they already paid or claimed. Returns what to tell the creator.
+pub async fn save(
+    ctx: &Context,
+    store: &dyn BillStore,
+    old: &Bill,
+    mut bill: Bill,
+    editor: UserId,
+) -> anyhow::Result<String> {
+    // the bill stays the same bill, only what was in the form changes
+    bill.id = old.id;
+    bill.message_id = old.message_id;
+    bill.creator_name = old.creator_name.clone();
+    bill.created_at = old.created_at;
+    bill.rate = old.rate;
+
+    let mut changed = Vec::new();
+    for share in &mut bill.shares {
+        let old_share = match old.share(share.user_id) {
+            Some(old_share) => old_share,
+            None => continue,
+        };
+        if share.user_id == bill.creator_id || old_share.status == ShareStatus::Owed {
+            continue;
+        }
+
+        share.status = old_share.status;
+        share.claimed_at = old_share.claimed_at;
+        share.settled_at = old_share.settled_at;
+        if share.amount != old_share.amount {
+            changed.push(format!(
+                "{} already sent {} but their share is now {}",
+                share.name,
+                Money::from_decimal(old_share.amount, bill.currency),
+                Money::from_decimal(share.amount, bill.currency)
+            ));
+        }
+    }
+
+    store.update_bill(&bill)?;
+    info!("bill {} edited by {}", bill.id, editor);
+
+    if let Err(why) = bill_message::update(ctx, &bill).await {
+        warn!("failed to update message for bill {}: {:?}", bill.id, why);
+    }
+
+    let mut content = String::from("Bill updated");
+    if !changed.is_empty() {
+        content.push_str(&format!(
+            ". Sort out the difference with whoever already paid:\n{}",
+            changed.join("\n")
+        ));
+    }
+
+    Ok(content)
+}
+
+#[cfg(test)]
+mod tests {
+    use chrono::Utc;
+    use rusty_money::iso;
+    use serde_json::json;
+    use serenity::model::prelude::{ChannelId, GuildId};
+
+    use super::*;
+    use crate::bill::{BillItem, BillShare};
+    use crate::items;
+
+    fn member(id: u64, name: &str, nick: Option<&str>) -> Member {
+        serde_json::from_value(json!({
+            "deaf": false,
+            "guild_id": "1",
+            "joined_at": null,
+            "mute": false,
+            "nick": nick,
+            "roles": [],
+            "user": {
+                "id": id.to_string(),
+                "username": name,
+                "discriminator": "0000",
+                "avatar": null,
+            },
+        }))
+        .unwrap()
+    }
+
+    /// Names that can't be typed back as they are, next to ones that can.
+    fn members() -> Vec<Member> {
+        vec![
+            member(1, "jacobm", Some("Jacob M")),
+            member(2, "joel", None),
+            member(3, "sam", Some("Jake")),
+            member(4, "samantha", Some("Jake")),
+            member(5, "rest", None),
+            member(6, "smith", Some("Smith, Jo")),
+            member(7, "ratio", Some("a:b")),
+            member(8, "at", Some("@home")),
+        ]
+    }
+
+    fn bill(amount: &str, shares: &[(u64, &str)]) -> Bill {
+        Bill {
+            id: 1,
+            guild_id: GuildId(1),
+            channel_id: ChannelId(1),
+            message_id: None,
+            creator_id: UserId(1),
+            creator_name: String::from("Jacob M"),
+            title: String::from("Groceries"),
+            amount: amount.parse().unwrap(),
+            currency: iso::CAD,
+            method: String::from("etransfer"),
+            created_at: Utc::now(),
+            due_at: None,
+            rate: None,
+            cancellation: None,
+            shares: shares
+                .iter()
+                .map(|(user, amount)| BillShare {
+                    user_id: UserId(*user),
+                    name: user.to_string(),
+                    amount: amount.parse().unwrap(),
+                    status: ShareStatus::Owed,
+                    claimed_at: None,
+                    settled_at: None,
+                })
+                .collect(),
+            items: Vec::new(),
+        }
+    }
+
+    fn resolved(name: &str, members: &[Member]) -> UserId {
+        match payers::resolve(name, members) {
+            Resolution::Found(member) => member.user.id,
+            _ => panic!("{} doesn't find anyone", name),
+        }
+    }
+
+    /// Parses the payers field the way the form does and splits the bill with it again.
+    fn reparsed(bill: &Bill, members: &[Member]) -> Vec<(UserId, Decimal)> {
+        let field = payers_field(bill, members);
+        let specs = split::parse_payers(&field, bill.currency).unwrap();
+        let portions: Vec<Portion> = specs.iter().map(|spec| spec.portion).collect();
+        let amounts = split::split(bill.amount, bill.currency, &portions).unwrap();
+
+        specs
+            .iter()
+            .map(|spec| resolved(&spec.name, members))
+            .zip(amounts)
+            .collect()
+    }
+
+    fn shares(bill: &Bill) -> Vec<(UserId, Decimal)> {
+        bill.shares
+            .iter()
+            .map(|share| (share.user_id, share.amount))
+            .collect()
+    }
+
+    #[test]
+    fn even_splits_list_names_only() {
+        let members = members();
+        let bill = bill("30", &[(1, "10"), (2, "10"), (7, "10")]);
+
+        assert_eq!(payers_field(&bill, &members), "Jacob M, joel, <@7>");
+        assert_eq!(reparsed(&bill, &members), shares(&bill));
+    }
+
+    #[test]
+    fn uneven_splits_round_trip() {
+        let members = members();
+        let bill = bill(
+            "100.01",
+            &[
+                (1, "20.01"),
+                (3, "10"),
+                (4, "10"),
+                (5, "30"),
+                (6, "10"),
+                (7, "10"),
+                (8, "10"),
+            ],
+        );
+
+        assert_eq!(reparsed(&bill, &members), shares(&bill));
+        // shared, comma'd, colon'd and role-like names and rest are mentioned instead
+        let field = payers_field(&bill, &members);
+        for user in [3, 4, 5, 6, 7, 8] {
+            assert!(field.contains(&format!("<@{}>:", user)), "{}", field);
+        }
+    }
+
+    #[test]
+    fn items_round_trip() {
+        let members = members();
+        let mut bill = bill("31.50", &[(1, "10.75"), (2, "10.75"), (6, "10")]);
+        bill.items = vec![
+            BillItem {
+                name: String::from("Burger"),
+                amount: "20".parse().unwrap(),
+                payers: vec![UserId(1), UserId(6)],
+            },
+            BillItem {
+                name: String::from("Fries"),
+                amount: "11.50".parse().unwrap(),
+                payers: vec![UserId(1), UserId(2)],
+            },
+        ];
+
+        let field = payers_field(&bill, &members);
+        assert_eq!(field, "Burger 20 @Jacob M <@6>\nFries 11.50 @Jacob M @joel");
+
+        let specs = items::parse_items(&field, bill.currency).unwrap();
+        assert_eq!(specs.len(), bill.items.len());
+        for (spec, item) in specs.iter().zip(&bill.items) {
+            assert_eq!(spec.name, item.name);
+            assert_eq!(spec.amount, item.amount);
+            let payers: Vec<UserId> = spec
+                .assignees
+                .iter()
+                .map(|assignee| resolved(assignee, &members))
+                .collect();
+            assert_eq!(payers, item.payers);
+        }
+    }
+
+    #[test]
+    fn prefilled_eur_forms_parse_back_to_the_same_bill() {
+        let members = members();
+        let draft = Draft::new(None, iso::EUR);
+
+        let mut bill = bill("1234.50", &[(1, "1000.25"), (2, "234.25")]);
+        bill.currency = iso::EUR;
+        let form = prefill(&bill, &members);
+        assert_eq!(form.amount, "1.234,50");
+
+        let parsed = create_bill::parse_form(&draft, &form).unwrap();
+        assert_eq!(*parsed.amount.amount(), bill.amount);
+        let portions: Vec<Portion> = parsed.specs.iter().map(|spec| spec.portion).collect();
+        let amounts = split::split(bill.amount, bill.currency, &portions).unwrap();
+        assert!(amounts
+            .iter()
+            .eq(bill.shares.iter().map(|share| &share.amount)));
+
+        bill.items = vec![
+            BillItem {
+                name: String::from("Burger"),
+                amount: "1000.25".parse().unwrap(),
+                payers: vec![UserId(1)],
+            },
+            BillItem {
+                name: String::from("Fries"),
+                amount: "234.25".parse().unwrap(),
+                payers: vec![UserId(2)],
+            },
+        ];
+        let form = prefill(&bill, &members);
+
+        let parsed = create_bill::parse_form(&draft, &form).unwrap();
+        assert_eq!(*parsed.amount.amount(), bill.amount);
+        let amounts: Vec<Decimal> = parsed.item_specs.iter().map(|spec| spec.amount).collect();
+        assert_eq!(amounts, vec![bill.items[0].amount, bill.items[1].amount]);
+    }
+
+    #[test]
+    fn only_the_creator_can_edit_bills_that_arent_cancelled() {
+        let mut bill = bill("10", &[(1, "5"), (2, "5")]);
+
+        assert!(refusal(&bill, UserId(1)).is_none());
+        assert!(refusal(&bill, UserId(2)).is_some());
+
+        bill.cancellation = Some(crate::bill::Cancellation {
+            cancelled_by: UserId(1),
+            reason: String::from("oops"),
+            cancelled_at: Utc::now(),
+        });
+        assert!(refusal(&bill, UserId(1)).is_some());
+    }
+}
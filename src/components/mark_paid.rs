@@ -0,0 +1,103 @@
+use anyhow::Context as _;
+use log::{info, warn};
+use serenity::model::prelude::interaction::message_component::MessageComponentInteraction;
+use serenity::model::prelude::interaction::InteractionResponseType::UpdateMessage;
+use serenity::prelude::Context;
+
+use crate::bill::ShareStatus;
+use crate::bill_message;
+use crate::components::{confirm_payment, load_bill, parse_share_custom_id, respond_ephemeral};
+use crate::store::BillStore;
+
+pub async fn respond(
+    ctx: &Context,
+    component: &MessageComponentInteraction,
+    store: &dyn BillStore,
+) -> anyhow::Result<()> {
+    let (bill_id, payer_id) = parse_share_custom_id(&component.data.custom_id)?;
+
+    let bill = load_bill(store, bill_id)?;
+    if bill.is_cancelled() {
+        return respond_ephemeral(ctx, component, "This bill was cancelled").await;
+    }
+
+    let share = bill
+        .share(payer_id)
+        .with_context(|| format!("bill {} has no share for user {}", bill_id, payer_id))?;
+
+    let status = if component.user.id == bill.creator_id {
+        // the creator received the money, so they can settle a share directly
+        match share.status {
+            ShareStatus::Owed | ShareStatus::Claimed => ShareStatus::Settled,
+            ShareStatus::Settled => ShareStatus::Owed,
+        }
+    } else if component.user.id == share.user_id {
+        match share.status {
+            ShareStatus::Owed => ShareStatus::Claimed,
+            ShareStatus::Claimed => {
+                return respond_ephemeral(
+                    ctx,
+                    component,
+                    format!(
+                        "Waiting for {} to confirm they received your payment",
+                        bill.creator_name
+                    ),
+                )
+                .await;
+            }
+            ShareStatus::Settled => {
+                return respond_ephemeral(
+                    ctx,
+                    component,
+                    "Your share of this bill is already settled",
+                )
+                .await;
+            }
+        }
+    } else {
+        info!(
+            "user {} is not allowed to mark the share of {} as paid on bill {}",
+            component.user.id, payer_id, bill_id
+        );
+        return respond_ephemeral(
+            ctx,
+            component,
+            format!(
+                "Only {} or the bill creator can mark this share as paid",
+                share.name
+            ),
+        )
+        .await;
+    };
+
+    store.set_share_status(bill_id, payer_id, status)?;
+    info!(
+        "share of {} on bill {} marked {} by {}",
+        payer_id,
+        bill_id,
+        status.as_str(),
+        component.user.id
+    );
+
+    let bill = load_bill(store, bill_id)?;
+
+    component
+        .create_interaction_response(&ctx.http, |res| {
+            res.kind(UpdateMessage).interaction_response_data(|msg| {
+                msg.content(bill_message::content(&bill))
+                    .components(|cmp| bill_message::components(&bill, cmp))
+            })
+        })
+        .await?;
+
+    if status == ShareStatus::Claimed {
+        if let Err(why) = confirm_payment::prompt(ctx, component, &bill, payer_id).await {
+            warn!(
+                "failed to prompt creator of bill {} to confirm payment: {:?}",
+                bill_id, why
+            );
+        }
+    }
+
+    Ok(())
+}
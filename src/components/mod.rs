@@ -0,0 +1,67 @@
+use anyhow::Context as _;
+use serenity::model::prelude::interaction::message_component::MessageComponentInteraction;
+use serenity::model::prelude::interaction::InteractionResponseType::ChannelMessageWithSource;
+use serenity::model::prelude::UserId;
+use serenity::prelude::Context;
+
+use crate::bill::Bill;
+use crate::store::BillStore;
+
+pub mod bill_list;
+pub mod cancel_bill;
+pub mod confirm_payment;
+pub mod edit_bill;
+pub mod mark_paid;
+pub mod nudge;
+pub mod pick_payers;
+pub mod reminder;
+pub mod settle_plan;
+
+/// Parses a `prefix:bill_id:user_id` custom id, used by components acting on a single share.
+pub fn parse_share_custom_id(custom_id: &str) -> anyhow::Result<(i64, UserId)> {
+    let mut parts = custom_id.split(':').skip(1);
+
+    let bill_id = parts
+        .next()
+        .context("missing bill id")?
+        .parse()
+        .context("invalid bill id")?;
+    let user_id = parts
+        .next()
+        .context("missing user id")?
+        .parse()
+        .context("invalid user id")?;
+
+    Ok((bill_id, UserId(user_id)))
+}
+
+/// Parses a `prefix:bill_id` custom id, used by components acting on a whole bill.
+pub fn parse_bill_custom_id(custom_id: &str) -> anyhow::Result<i64> {
+    custom_id
+        .split(':')
+        .nth(1)
+        .context("missing bill id")?
+        .parse()
+        .context("invalid bill id")
+}
+
+pub fn load_bill(store: &dyn BillStore, bill_id: i64) -> anyhow::Result<Bill> {
+    store
+        .get_bill(bill_id)?
+        .with_context(|| format!("bill {} does not exist", bill_id))
+}
+
+pub async fn respond_ephemeral(
+    ctx: &Context,
+    component: &MessageComponentInteraction,
+    content: impl ToString,
+) -> anyhow::Result<()> {
+    component
+        .create_interaction_response(&ctx.http, |res| {
+            res.kind(ChannelMessageWithSource)
+                .interaction_response_data(|msg| msg.ephemeral(true).content(content))
+        })
+        .await?;
+
+    Ok(())
+}
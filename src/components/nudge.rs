@@ -0,0 +1,117 @@
+use chrono::{Duration, Utc};
+use log::info;
+use rusty_money::Money;
+use serenity::model::prelude::interaction::message_component::MessageComponentInteraction;
+use serenity::model::prelude::UserId;
+use serenity::prelude::Context;
+
+use crate::bill::{Bill, BillShare, ShareStatus};
+use crate::bill_message;
+use crate::components::{load_bill, parse_bill_custom_id, respond_ephemeral};
+use crate::store::BillStore;
+
+/// How long the creator has to wait between nudges of the same bill.
+const COOLDOWN_HOURS: i64 = 12;
+
+/// Why `user_id` can't nudge the bill's payers right now, if they can't.
+pub fn refusal(
+    store: &dyn BillStore,
+    bill: &Bill,
+    user_id: UserId,
+) -> anyhow::Result<Option<String>> {
+    if user_id != bill.creator_id {
+        return Ok(Some(format!(
+            "Only {} can nudge the payers of this bill",
+            bill.creator_name
+        )));
+    }
+    if bill.is_cancelled() {
+        return Ok(Some(String::from("This bill is cancelled")));
+    }
+    if unpaid_shares(bill).is_empty() {
+        return Ok(Some(String::from(
+            "Everyone has sent their share, there's nobody to nudge",
+        )));
+    }
+
+    let why = match store.nudged_at(bill.id)? {
+        Some(nudged_at) if nudged_at + Duration::hours(COOLDOWN_HOURS) > Utc::now() => {
+            Some(format!(
+                "Payers were nudged <t:{}:R>, you can nudge them again <t:{}:R>",
+                nudged_at.timestamp(),
+                (nudged_at + Duration::hours(COOLDOWN_HOURS)).timestamp()
+            ))
+        }
+        _ => None,
+    };
+
+    Ok(why)
+}
+
+pub async fn respond(
+    ctx: &Context,
+    component: &MessageComponentInteraction,
+    store: &dyn BillStore,
+) -> anyhow::Result<()> {
+    let bill = load_bill(store, parse_bill_custom_id(&component.data.custom_id)?)?;
+
+    if let Some(why) = refusal(store, &bill, component.user.id)? {
+        return respond_ephemeral(ctx, component, why).await;
+    }
+
+    nudge(ctx, store, &bill).await?;
+    respond_ephemeral(ctx, component, nudged_notice(&bill)).await
+}
+
+/// What the creator is told once the bill's payers are nudged.
+pub fn nudged_notice(bill: &Bill) -> String {
+    match unpaid_shares(bill).len() {
+        1 => String::from("Nudged the payer who hasn't sent their share yet"),
+        n => format!("Nudged the {} payers who haven't sent their share yet", n),
+    }
+}
+
+/// Shares whose payer hasn't said they sent the money yet.
+fn unpaid_shares(bill: &Bill) -> Vec<&BillShare> {
+    bill.payer_shares()
+        .filter(|share| share.status == ShareStatus::Owed)
+        .collect()
+}
+
+/// Pings the payers who haven't paid in a reply to the bill's message, and starts the cooldown.
+pub async fn nudge(ctx: &Context, store: &dyn BillStore, bill: &Bill) -> anyhow::Result<()> {
+    let unpaid = unpaid_shares(bill);
+
+    let notice = format!(
+        "{} friendly reminder from {}, **{}** still needs paying:\n{}\nPayment method: {}",
+        bill_message::create_payer_mention_string(unpaid.iter().copied()),
+        bill.creator_name,
+        bill.title,
+        unpaid
+            .iter()
+            .map(|share| format!(
+                "{}: {}",
+                share.name,
+                Money::from_decimal(share.amount, bill.currency)
+            ))
+            .collect::<Vec<_>>()
+            .join("\n"),
+        bill.method
+    );
+    bill.channel_id
+        .send_message(&ctx.http, |msg| {
+            if let Some(message_id) = bill.message_id {
+                msg.reference_message((bill.channel_id, message_id));
+            }
+            msg.content(bill_message::truncate(
+                &notice,
+                bill_message::MAX_CONTENT_LENGTH,
+            ))
+        })
+        .await?;
+
+    store.set_nudged_at(bill.id, Utc::now())?;
+    info!("nudged {} payers of bill {}", unpaid.len(), bill.id);
+
+    Ok(())
+}
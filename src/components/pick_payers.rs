@@ -0,0 +1,302 @@
+use anyhow::Context as _;
+use log::{error, info, warn};
+use serenity::builder::CreateInteractionResponseData;
+use serenity::model::prelude::component::ButtonStyle;
+use serenity::model::prelude::interaction::message_component::MessageComponentInteraction;
+use serenity::model::prelude::interaction::InteractionResponseType::{
+    ChannelMessageWithSource, DeferredUpdateMessage, Modal,
+};
+use serenity::model::prelude::{Member, UserId};
+use serenity::prelude::{Context, Mentionable};
+
+use crate::bill_message;
+use crate::commands::create_bill::{self, Prepared};
+use crate::components::{edit_bill, load_bill, respond_ephemeral};
+use crate::draft::Drafts;
+use crate::members::MemberDirectory;
+use crate::rates::RateProvider;
+use crate::store::BillStore;
+
+pub const PICK_PAYERS_ID: &str = "bill_pick_payers";
+pub const CONTINUE_ID: &str = "bill_pick_continue";
+pub const PICK_MATCH_ID: &str = "bill_pick_match";
+pub const POST_ID: &str = "bill_post";
+
+/// A message has room for five action rows, one of which holds the Post button.
+pub const MAX_MATCH_MENUS: usize = 4;
+
+/// Discord shows at most 10 embeds per message.
+const MAX_MATCH_EMBEDS: usize = 10;
+
+const EXPIRED: &str = "This bill took too long to fill in, run `/bill create` again";
+
+/// Parses the `prefix:key:menu` custom id of a draft's select menus.
+fn parse_menu_custom_id(custom_id: &str) -> anyhow::Result<(u64, usize)> {
+    let mut parts = custom_id.split(':').skip(1);
+    let key = parts
+        .next()
+        .context("missing draft key")?
+        .parse()
+        .context("invalid draft key")?;
+    let menu = parts
+        .next()
+        .context("missing menu index")?
+        .parse()
+        .context("invalid menu index")?;
+
+    Ok((key, menu))
+}
+
+fn parse_picked(component: &MessageComponentInteraction) -> anyhow::Result<Vec<UserId>> {
+    component
+        .data
+        .values
+        .iter()
+        .map(|id| id.parse().map(UserId))
+        .collect::<Result<Vec<_>, _>>()
+        .context("invalid user id in picked payers")
+}
+
+/// Records the payers picked in one of the draft's select menus, replacing whatever was
+/// picked in that menu before.
+pub async fn pick(
+    ctx: &Context,
+    component: &MessageComponentInteraction,
+    drafts: &Drafts,
+) -> anyhow::Result<()> {
+    let (key, menu) = parse_menu_custom_id(&component.data.custom_id)?;
+    let picked = parse_picked(component)?;
+    info!("picked payers for draft {}: {:?}", key, picked);
+
+    if !drafts.update(key, |draft| {
+        draft.picked.insert(menu, picked);
+    }) {
+        return respond_ephemeral(ctx, component, EXPIRED).await;
+    }
+
+    component
+        .create_interaction_response(&ctx.http, |res| res.kind(DeferredUpdateMessage))
+        .await?;
+
+    Ok(())
+}
+
+/// Opens the bill modal for the draft.
+pub async fn proceed(
+    ctx: &Context,
+    component: &MessageComponentInteraction,
+    drafts: &Drafts,
+) -> anyhow::Result<()> {
+    let key: u64 = component
+        .data
+        .custom_id
+        .split(':')
+        .nth(1)
+        .context("missing draft key")?
+        .parse()
+        .context("invalid draft key")?;
+
+    let draft = match drafts.get(key) {
+        Some(draft) => draft,
+        None => return respond_ephemeral(ctx, component, EXPIRED).await,
+    };
+
+    component
+        .create_interaction_response(&ctx.http, |res| {
+            res.kind(Modal)
+                .interaction_response_data(|modal| create_bill::modal(modal, key, draft.currency))
+        })
+        .await?;
+
+    Ok(())
+}
+
+/// Asks the bill creator who they meant by names that match more than one member, with an
+/// embed per candidate so they can be told apart by avatar.
+pub fn match_prompt<'a, 'b>(
+    msg: &'a mut CreateInteractionResponseData<'b>,
+    key: u64,
+    ambiguous: &[(String, Vec<Member>)],
+) -> &'a mut CreateInteractionResponseData<'b> {
+    let candidates = ambiguous
+        .iter()
+        .flat_map(|(_, members)| members)
+        .take(MAX_MATCH_EMBEDS);
+    for member in candidates {
+        msg.embed(|embed| {
+            embed
+                .author(|author| {
+                    author
+                        .name(member.display_name())
+                        .icon_url(member.user.face())
+                })
+                .description(format!("{} ({})", member.mention(), member.user.tag()))
+        });
+    }
+
+    msg.ephemeral(true)
+        .content("More than one person goes by some of the names you typed, pick who you meant then press Post bill.")
+        .components(|cmp| {
+            for (i, (name, members)) in ambiguous.iter().enumerate() {
+                cmp.create_action_row(|row| {
+                    row.create_select_menu(|menu| {
+                        menu.custom_id(format!("{}:{}:{}", PICK_MATCH_ID, key, i))
+                            .placeholder(format!("Who did you mean by {}?", name))
+                            .options(|opts| {
+                                for member in members.iter().take(25) {
+                                    opts.create_option(|opt| {
+                                        opt.label(member.display_name())
+                                            .value(member.user.id)
+                                            .description(member.user.tag())
+                                    });
+                                }
+                                opts
+                            })
+                    })
+                });
+            }
+
+            cmp.create_action_row(|row| {
+                row.create_button(|btn| {
+                    btn.custom_id(format!("{}:{}", POST_ID, key))
+                        .style(ButtonStyle::Success)
+                        .label("Post bill")
+                })
+            })
+        })
+}
+
+/// Records who the creator meant by one of the draft's ambiguous names.
+pub async fn pick_match(
+    ctx: &Context,
+    component: &MessageComponentInteraction,
+    drafts: &Drafts,
+) -> anyhow::Result<()> {
+    let (key, menu) = parse_menu_custom_id(&component.data.custom_id)?;
+    let picked = parse_picked(component)?;
+
+    let updated = drafts.update(key, |draft| {
+        if let (Some(name), Some(user_id)) = (draft.ambiguous.get(menu), picked.first()) {
+            info!("draft {}: {} means {}", key, name, user_id);
+            draft.chosen.insert(name.to_lowercase(), *user_id);
+        }
+    });
+    if !updated {
+        return respond_ephemeral(ctx, component, EXPIRED).await;
+    }
+
+    component
+        .create_interaction_response(&ctx.http, |res| res.kind(DeferredUpdateMessage))
+        .await?;
+
+    Ok(())
+}
+
+/// Posts the draft's bill, or saves the bill being edited, once every ambiguous name has been
+/// cleared up.
+pub async fn post(
+    ctx: &Context,
+    component: &MessageComponentInteraction,
+    store: &dyn BillStore,
+    rates: &dyn RateProvider,
+    directory: &MemberDirectory,
+    drafts: &Drafts,
+) -> anyhow::Result<()> {
+    let key: u64 = component
+        .data
+        .custom_id
+        .split(':')
+        .nth(1)
+        .context("missing draft key")?
+        .parse()
+        .context("invalid draft key")?;
+
+    let (draft, form) = match drafts.get(key) {
+        Some(draft) => match draft.form.clone() {
+            Some(form) => (draft, form),
+            None => return respond_ephemeral(ctx, component, EXPIRED).await,
+        },
+        None => return respond_ephemeral(ctx, component, EXPIRED).await,
+    };
+    let creator = component
+        .member
+        .as_ref()
+        .context("no member for bill post")?;
+
+    // drafts from a bill's Edit button save over that bill instead of posting a new one
+    let editing = match draft.editing {
+        Some(bill_id) => {
+            let old = load_bill(store, bill_id)?;
+            if let Some(why) = edit_bill::refusal(&old, creator.user.id) {
+                return respond_ephemeral(ctx, component, why).await;
+            }
+            Some(old)
+        }
+        None => None,
+    };
+    let (guild_id, channel_id) = match &editing {
+        Some(old) => (old.guild_id, old.channel_id),
+        None => (
+            component.guild_id.context("no guild id for bill post")?,
+            component.channel_id,
+        ),
+    };
+
+    let prepared = create_bill::prepare(
+        ctx, store, rates, directory, guild_id, channel_id, creator, &draft, &form,
+    )
+    .await?;
+
+    let mut bill = match prepared {
+        Prepared::Ready(bill) => *bill,
+        Prepared::Rejected(why) => return respond_ephemeral(ctx, component, why).await,
+        Prepared::Ambiguous(ambiguous) => {
+            let names: Vec<&str> = ambiguous.iter().map(|(name, _)| name.as_str()).collect();
+            return respond_ephemeral(
+                ctx,
+                component,
+                format!("Pick who you meant by {} first", names.join(", ")),
+            )
+            .await;
+        }
+    };
+
+    if let Some(old) = editing {
+        let content = edit_bill::save(ctx, store, &old, bill, creator.user.id).await?;
+        drafts.remove(key);
+        return respond_ephemeral(ctx, component, content).await;
+    }
+
+    if let Err(why) = store.insert_bill(&mut bill) {
+        error!("failed to save bill: {:?}", why);
+        return respond_ephemeral(ctx, component, "Failed to save the bill, please try again")
+            .await;
+    }
+    info!("saved bill with id {}", bill.id);
+    drafts.remove(key);
+
+    if let Err(why) = component
+        .create_interaction_response(&ctx.http, |res| {
+            res.kind(ChannelMessageWithSource)
+                .interaction_response_data(|msg| {
+                    msg.content(bill_message::content(&bill))
+                        .components(|cmp| bill_message::components(&bill, cmp))
+                })
+        })
+        .await
+    {
+        bill_message::discard(store, bill.id);
+        return Err(why.into());
+    }
+
+    match component.get_interaction_response(&ctx.http).await {
+        Ok(message) => {
+            if let Err(why) = store.set_message_id(bill.id, message.id) {
+                error!("failed to save message id for bill {}: {:?}", bill.id, why);
+            }
+        }
+        Err(why) => warn!("failed to get bill message for bill {}: {}", bill.id, why),
+    }
+
+    Ok(())
+}
@@ -0,0 +1,145 @@
+use chrono::{Duration, Utc};
+use log::{info, warn};
+use serenity::builder::CreateComponents;
+use serenity::model::prelude::component::ButtonStyle;
+use serenity::model::prelude::interaction::message_component::MessageComponentInteraction;
+use serenity::model::prelude::interaction::InteractionResponseType::UpdateMessage;
+use serenity::model::prelude::UserId;
+use serenity::prelude::Context;
+
+use crate::bill::ShareStatus;
+use crate::bill_message;
+use crate::components::{confirm_payment, load_bill, parse_share_custom_id, respond_ephemeral};
+use crate::store::BillStore;
+
+pub const PAID_ID: &str = "reminder_paid";
+pub const SNOOZE_ID: &str = "reminder_snooze";
+pub const STOP_ID: &str = "reminder_stop";
+
+/// How long the Snooze button holds off reminders for.
+const SNOOZE_DAYS: i64 = 7;
+
+/// Buttons under a reminder DM, for the payer of the share it's about.
+pub fn components(
+    cmp: &mut CreateComponents,
+    bill_id: i64,
+    payer_id: UserId,
+) -> &mut CreateComponents {
+    cmp.create_action_row(|row| {
+        row.create_button(|btn| {
+            btn.custom_id(format!("{}:{}:{}", PAID_ID, bill_id, payer_id))
+                .style(ButtonStyle::Success)
+                .label("Mark paid")
+        })
+        .create_button(|btn| {
+            btn.custom_id(format!("{}:{}:{}", SNOOZE_ID, bill_id, payer_id))
+                .style(ButtonStyle::Secondary)
+                .label(format!("Snooze {} days", SNOOZE_DAYS))
+        })
+        .create_button(|btn| {
+            btn.custom_id(format!("{}:{}:{}", STOP_ID, bill_id, payer_id))
+                .style(ButtonStyle::Secondary)
+                .label("Stop DMs")
+        })
+    })
+}
+
+/// Claims the payer's share from a reminder DM, the same as their button on the bill.
+pub async fn paid(
+    ctx: &Context,
+    component: &MessageComponentInteraction,
+    store: &dyn BillStore,
+) -> anyhow::Result<()> {
+    let (bill_id, payer_id) = parse_share_custom_id(&component.data.custom_id)?;
+    if component.user.id != payer_id {
+        return respond_ephemeral(ctx, component, "This reminder isn't for you").await;
+    }
+
+    let bill = load_bill(store, bill_id)?;
+    let status = bill.share(payer_id).map(|share| share.status);
+    let content = if bill.is_cancelled() {
+        format!("**{}** was cancelled, there's nothing to pay", bill.title)
+    } else {
+        match status {
+            Some(ShareStatus::Owed) => {
+                store.set_share_status(bill_id, payer_id, ShareStatus::Claimed)?;
+                info!(
+                    "share of {} on bill {} marked claimed from a reminder",
+                    payer_id, bill_id
+                );
+                format!(
+                    "Marked your share of **{}** as sent, {} will confirm they got it",
+                    bill.title, bill.creator_name
+                )
+            }
+            Some(ShareStatus::Claimed) => format!(
+                "Waiting for {} to confirm they received your payment for **{}**",
+                bill.creator_name, bill.title
+            ),
+            Some(ShareStatus::Settled) => {
+                format!("Your share of **{}** is already settled", bill.title)
+            }
+            None => format!("You're no longer a payer of **{}**", bill.title),
+        }
+    };
+
+    component
+        .create_interaction_response(&ctx.http, |res| {
+            res.kind(UpdateMessage)
+                .interaction_response_data(|msg| msg.content(content).components(|cmp| cmp))
+        })
+        .await?;
+
+    if status != Some(ShareStatus::Owed) || bill.is_cancelled() {
+        return Ok(());
+    }
+
+    let bill = load_bill(store, bill_id)?;
+    if let Err(why) = bill_message::update(ctx, &bill).await {
+        warn!("failed to update message for bill {}: {:?}", bill_id, why);
+    }
+    if let Err(why) = confirm_payment::prompt(ctx, component, &bill, payer_id).await {
+        warn!(
+            "failed to prompt creator of bill {} to confirm payment: {:?}",
+            bill_id, why
+        );
+    }
+
+    Ok(())
+}
+
+pub async fn snooze(
+    ctx: &Context,
+    component: &MessageComponentInteraction,
+    store: &dyn BillStore,
+) -> anyhow::Result<()> {
+    let until = Utc::now() + Duration::days(SNOOZE_DAYS);
+    store.snooze_reminders(component.user.id, Some(until))?;
+    info!("{} snoozed reminders until {}", component.user.id, until);
+
+    respond_ephemeral(
+        ctx,
+        component,
+        format!(
+            "No reminders until <t:{}:f>, use `/bill reminders resume` to get them sooner",
+            until.timestamp()
+        ),
+    )
+    .await
+}
+
+pub async fn stop(
+    ctx: &Context,
+    component: &MessageComponentInteraction,
+    store: &dyn BillStore,
+) -> anyhow::Result<()> {
+    store.set_reminders_opted_out(component.user.id, true)?;
+    info!("{} opted out of reminder DMs", component.user.id);
+
+    respond_ephemeral(
+        ctx,
+        component,
+        "No more reminder DMs, you'll be pinged in the bill's channel instead. Use `/bill reminders dm` to get them by DM again",
+    )
+    .await
+}
@@ -0,0 +1,132 @@
+use std::collections::{hash_map::Entry, HashMap};
+
+use anyhow::Context as _;
+use log::{info, warn};
+use serenity::model::prelude::interaction::message_component::MessageComponentInteraction;
+use serenity::model::prelude::interaction::InteractionResponseType::UpdateMessage;
+use serenity::prelude::{Context, Mentionable};
+
+use crate::bill::{Bill, ShareStatus};
+use crate::bill_message;
+use crate::components::{load_bill, respond_ephemeral};
+use crate::store::BillStore;
+
+pub const SETTLE_PLAN_ID: &str = "bill_settle_plan";
+
+pub async fn respond(
+    ctx: &Context,
+    component: &MessageComponentInteraction,
+    store: &dyn BillStore,
+) -> anyhow::Result<()> {
+    let plan_id: i64 = component
+        .data
+        .custom_id
+        .split(':')
+        .nth(1)
+        .context("missing plan id")?
+        .parse()
+        .context("invalid plan id")?;
+
+    let plan = store
+        .get_settle_plan(plan_id)?
+        .with_context(|| format!("settle plan {} does not exist", plan_id))?;
+
+    if plan.settled_by.is_some() {
+        return respond_ephemeral(ctx, component, "This plan has already been settled").await;
+    }
+
+    let mut bills: HashMap<i64, Bill> = HashMap::new();
+    for share in &plan.shares {
+        if let Entry::Vacant(entry) = bills.entry(share.bill_id) {
+            entry.insert(load_bill(store, share.bill_id)?);
+        }
+    }
+
+    // only the person a share is owed to can say it's been paid, the same as on the bill
+    let user = component.user.id;
+    let creditor = plan
+        .shares
+        .iter()
+        .any(|share| bills[&share.bill_id].creator_id == user);
+    if !creditor {
+        return respond_ephemeral(
+            ctx,
+            component,
+            "Only people who are owed money in this plan can mark it as settled, once their transfers arrive",
+        )
+        .await;
+    }
+
+    // shares may have been paid or bills changed since the plan was posted, in which case
+    // the transfers no longer add up and the plan has to be recalculated. Shares already
+    // settled through the plan are expected to be settled.
+    let unchanged = plan
+        .shares
+        .iter()
+        .filter(|planned| planned.settled_at.is_none())
+        .all(|planned| {
+            let bill = &bills[&planned.bill_id];
+            !bill.is_cancelled()
+                && bill
+                    .share(planned.user_id)
+                    .map(|share| {
+                        share.status != ShareStatus::Settled && share.amount == planned.amount
+                    })
+                    .unwrap_or(false)
+        });
+    if !unchanged {
+        return respond_ephemeral(
+            ctx,
+            component,
+            "Some of these bills changed since the plan was made, run `/bill settle-plan` again",
+        )
+        .await;
+    }
+
+    let settled = store.settle_plan(plan.id, user)?;
+    if settled == 0 {
+        return respond_ephemeral(
+            ctx,
+            component,
+            "What you're owed in this plan is already settled",
+        )
+        .await;
+    }
+    info!(
+        "{} settled {} shares owed to them in settle plan {}",
+        user, settled, plan.id
+    );
+
+    let mut content = format!(
+        "{}\n✅ {} received what they're owed",
+        component.message.content,
+        user.mention()
+    );
+    let done = store
+        .get_settle_plan(plan.id)?
+        .is_some_and(|plan| plan.settled_by.is_some());
+    if done {
+        content.push_str("\n\n**Everything in this plan is settled**");
+    }
+
+    component
+        .create_interaction_response(&ctx.http, |res| {
+            res.kind(UpdateMessage).interaction_response_data(|msg| {
+                msg.content(content);
+                if done {
+                    msg.components(|cmp| cmp);
+                }
+                msg
+            })
+        })
+        .await?;
+
+    for bill in bills.values().filter(|bill| bill.creator_id == user) {
+        let bill = load_bill(store, bill.id)?;
+        if let Err(why) = bill_message::update(ctx, &bill).await {
+            warn!("failed to update message for bill {}: {:?}", bill.id, why);
+        }
+    }
+
+    Ok(())
+}
@@ -0,0 +1,109 @@
+use std::{
+    collections::{BTreeMap, HashMap},
+    sync::Mutex,
+    time::{Duration, Instant},
+};
+
+use chrono::{DateTime, Utc};
+use rusty_money::iso::Currency;
+use serenity::model::prelude::UserId;
+
+/// Interaction tokens expire after 15 minutes, after which a draft can't be finished anyway.
+const DRAFT_TTL: Duration = Duration::from_secs(15 * 60);
+
+/// A bill that's being put together over several interactions, from `/bill create` through
+/// picking payers to submitting the modal.
+#[derive(Clone, Debug)]
+pub struct Draft {
+    pub include_me: Option<bool>,
+    pub currency: &'static Currency,
+    /// The payers picked in each of the draft's select menus, by menu index.
+    pub picked: BTreeMap<usize, Vec<UserId>>,
+    /// The last thing submitted through the bill modal.
+    pub form: Option<Form>,
+    /// Typed names that match more than one member, by the index of their menu.
+    pub ambiguous: Vec<String>,
+    /// Who the creator meant by each ambiguous name, keyed by the lowercased name.
+    pub chosen: HashMap<String, UserId>,
+    /// The id of the bill being edited, for drafts started from a bill's Edit button.
+    pub editing: Option<i64>,
+    /// The due date of the bill being edited, which it keeps even once it's passed.
+    pub current_due_at: Option<DateTime<Utc>>,
+    created_at: Instant,
+}
+
+/// What was typed into the bill modal, kept as is so it can be processed again once
+/// ambiguous names are cleared up.
+#[derive(Clone, Debug, Default)]
+pub struct Form {
+    pub title: String,
+    pub amount: String,
+    pub method: String,
+    pub payers: String,
+    /// Left empty for bills without a due date.
+    pub due: String,
+}
+
+impl Draft {
+    pub fn new(include_me: Option<bool>, currency: &'static Currency) -> Draft {
+        Draft {
+            include_me,
+            currency,
+            picked: BTreeMap::new(),
+            form: None,
+            ambiguous: Vec::new(),
+            chosen: HashMap::new(),
+            editing: None,
+            current_due_at: None,
+            created_at: Instant::now(),
+        }
+    }
+
+    /// Every picked payer, in menu order.
+    pub fn picked(&self) -> impl Iterator<Item = UserId> + '_ {
+        self.picked.values().flatten().copied()
+    }
+}
+
+/// Drafts in progress, keyed by the id of the interaction that started them.
+#[derive(Default)]
+pub struct Drafts {
+    drafts: Mutex<HashMap<u64, Draft>>,
+}
+
+impl Drafts {
+    pub fn insert(&self, key: u64, draft: Draft) {
+        let mut drafts = self.drafts.lock().unwrap();
+        drafts.retain(|_, draft| draft.created_at.elapsed() < DRAFT_TTL);
+        drafts.insert(key, draft);
+    }
+
+    pub fn get(&self, key: u64) -> Option<Draft> {
+        self.drafts
+            .lock()
+            .unwrap()
+            .get(&key)
+            .filter(|draft| draft.created_at.elapsed() < DRAFT_TTL)
+            .cloned()
+    }
+
+    /// Applies `f` to a draft, returning false if the draft doesn't exist or has expired.
+    pub fn update(&self, key: u64, f: impl FnOnce(&mut Draft)) -> bool {
+        let mut drafts = self.drafts.lock().unwrap();
+        let draft = drafts
+            .get_mut(&key)
+            .filter(|draft| draft.created_at.elapsed() < DRAFT_TTL);
+
+        match draft {
+            Some(draft) => {
+                f(draft);
+                true
+            }
+            None => false,
+        }
+    }
+
+    pub fn remove(&self, key: u64) {
+        self.drafts.lock().unwrap().remove(&key);
+    }
+}
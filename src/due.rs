@@ -0,0 +1,139 @@
+use anyhow::bail;
+use chrono::{DateTime, Datelike, Duration, Months, NaiveDate, NaiveTime, TimeZone, Utc, Weekday};
+
+/// Parses a due date relative to `today`: a date like `2026-11-01`, `today`, `tomorrow`, a day
+/// of the week like `friday` or `next friday`, which both mean the first one after today, or
+/// `in 3 days`, `in 2 weeks` and `in 1 month`. Dates written out are returned as is even if
+/// they're in the past, whether that's allowed is up to the caller. Errors are meant to be
+/// shown to the bill creator as is.
+pub fn parse(input: &str, today: NaiveDate) -> anyhow::Result<NaiveDate> {
+    let input = input.trim().to_lowercase();
+
+    if let Ok(date) = NaiveDate::parse_from_str(&input, "%Y-%m-%d") {
+        return Ok(date);
+    }
+
+    let words: Vec<&str> = input.split_whitespace().collect();
+    let date = match words.as_slice() {
+        ["today"] => Some(today),
+        ["tomorrow"] => today.succ_opt(),
+        [weekday] | ["next", weekday] | ["this", weekday] => match weekday.parse::<Weekday>() {
+            Ok(weekday) => {
+                let days_ahead = (weekday.num_days_from_monday() + 7
+                    - today.weekday().num_days_from_monday())
+                    % 7;
+                // a weekday always means one that's still to come
+                let days_ahead = if days_ahead == 0 { 7 } else { days_ahead };
+                today.checked_add_signed(Duration::days(days_ahead as i64))
+            }
+            Err(_) => None,
+        },
+        ["in", count, unit] => {
+            let count = match *count {
+                "a" | "an" | "one" => 1,
+                count => match count.parse::<u32>() {
+                    Ok(count) => count,
+                    Err(_) => bail!("{} isn't a number", count),
+                },
+            };
+            match unit.trim_end_matches('s') {
+                "day" => today.checked_add_signed(Duration::days(count as i64)),
+                "week" => today.checked_add_signed(Duration::weeks(count as i64)),
+                "month" => today.checked_add_months(Months::new(count)),
+                _ => None,
+            }
+        }
+        _ => None,
+    };
+
+    match date {
+        Some(date) => Ok(date),
+        None => bail!(
+            "Couldn't understand the due date `{}`, write it like 2026-11-01, next friday or in 2 weeks",
+            input
+        ),
+    }
+}
+
+/// When a bill due on `date` becomes overdue. Dates are in UTC and a bill is due by the end of
+/// its day, so it still shows up as the same day in most of the Americas and Europe.
+pub fn due_at(date: NaiveDate) -> DateTime<Utc> {
+    Utc.from_utc_datetime(&date.and_time(NaiveTime::from_hms_opt(23, 59, 59).unwrap()))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn date(y: i32, m: u32, d: u32) -> NaiveDate {
+        NaiveDate::from_ymd_opt(y, m, d).unwrap()
+    }
+
+    /// A wednesday.
+    fn today() -> NaiveDate {
+        date(2026, 3, 4)
+    }
+
+    fn parsed(input: &str) -> NaiveDate {
+        parse(input, today()).unwrap()
+    }
+
+    #[test]
+    fn parses_dates() {
+        assert_eq!(parsed(" 2026-11-01 "), date(2026, 11, 1));
+        assert_eq!(parsed("2020-01-01"), date(2020, 1, 1));
+        assert_eq!(parsed("Today"), today());
+        assert_eq!(parsed("tomorrow"), date(2026, 3, 5));
+    }
+
+    #[test]
+    fn weekdays_are_always_still_to_come() {
+        assert_eq!(parsed("friday"), date(2026, 3, 6));
+        assert_eq!(parsed("next fri"), date(2026, 3, 6));
+        assert_eq!(parsed("this monday"), date(2026, 3, 9));
+        assert_eq!(parsed("Next Mon"), date(2026, 3, 9));
+        // today's weekday means next week's
+        assert_eq!(parsed("wednesday"), date(2026, 3, 11));
+        assert_eq!(parsed("this wed"), date(2026, 3, 11));
+    }
+
+    #[test]
+    fn parses_days_weeks_and_months_from_today() {
+        assert_eq!(parsed("in 3 days"), date(2026, 3, 7));
+        assert_eq!(parsed("in 1 day"), date(2026, 3, 5));
+        assert_eq!(parsed("in 2 weeks"), date(2026, 3, 18));
+        assert_eq!(parsed("in a week"), date(2026, 3, 11));
+        assert_eq!(parsed("in one month"), date(2026, 4, 4));
+        assert_eq!(parsed("in 0 days"), today());
+        // months without the day end on their last day
+        assert_eq!(
+            parse("in 1 month", date(2026, 1, 31)).unwrap(),
+            date(2026, 2, 28)
+        );
+    }
+
+    #[test]
+    fn rejects_anything_else() {
+        for input in [
+            "",
+            "someday",
+            "next",
+            "next month",
+            "in x days",
+            "in -1 days",
+            "in 2 hours",
+            "2026-02-30",
+            "03/04/2026",
+        ] {
+            assert!(parse(input, today()).is_err(), "{}", input);
+        }
+    }
+
+    #[test]
+    fn bills_are_due_by_the_end_of_the_day() {
+        assert_eq!(
+            due_at(today()),
+            Utc.with_ymd_and_hms(2026, 3, 4, 23, 59, 59).unwrap()
+        );
+    }
+}
@@ -1,31 +1,61 @@
-use std::{collections::HashMap, process::exit};
+use std::{
+    process::exit,
+    sync::{
+        atomic::{AtomicBool, Ordering},
+        Arc,
+    },
+};
 
 use log::{error, info, warn};
-use rust_decimal::prelude::*;
-use rusty_money::{
-    iso::{self},
-    Money,
-};
 use serenity::{
     async_trait,
     model::prelude::{
-        command::Command,
-        component::ActionRowComponent,
-        interaction::{Interaction, InteractionResponseType::ChannelMessageWithSource},
-        GuildId, Member, Ready,
+        command::Command, interaction::Interaction, Guild, GuildId, Member, Ready,
+        UnavailableGuild, User,
     },
     prelude::*,
 };
 
-use crate::commands;
+use crate::{
+    bill_message, commands,
+    commands::list::Listings,
+    components::{
+        self, bill_list, cancel_bill, confirm_payment, edit_bill, nudge, pick_payers, reminder,
+        settle_plan,
+    },
+    draft::Drafts,
+    members::MemberDirectory,
+    rates::RateProvider,
+    recurring, reminders,
+    store::BillStore,
+};
 
 pub struct Handler {
     guild_ids: Vec<String>,
+    store: Arc<dyn BillStore>,
+    rates: Arc<dyn RateProvider>,
+    members: MemberDirectory,
+    drafts: Drafts,
+    listings: Listings,
+    /// Set once the background tasks are running, ready fires again on every reconnect.
+    tasks_started: AtomicBool,
 }
 
 impl Handler {
-    pub fn new(guild_ids: Vec<String>) -> Handler {
-        Handler { guild_ids }
+    pub fn new(
+        guild_ids: Vec<String>,
+        store: Arc<dyn BillStore>,
+        rates: Arc<dyn RateProvider>,
+    ) -> Handler {
+        Handler {
+            guild_ids,
+            store,
+            rates,
+            members: MemberDirectory::default(),
+            drafts: Drafts::default(),
+            listings: Listings::default(),
+            tasks_started: AtomicBool::new(false),
+        }
     }
 }
 
@@ -34,6 +64,18 @@ impl EventHandler for Handler {
     async fn ready(&self, ctx: Context, bot_info: Ready) {
         info!("{} is connected", bot_info.user.name);
 
+        if !self.tasks_started.swap(true, Ordering::SeqCst) {
+            tokio::spawn(recurring::run(
+                ctx.clone(),
+                self.store.clone(),
+                self.rates.clone(),
+            ));
+            info!("recurring bill scheduler started");
+
+            tokio::spawn(reminders::run(ctx.clone(), self.store.clone()));
+            info!("reminder engine started");
+        }
+
         // register commands globally if guild_ids is empty
         if self.guild_ids.is_empty() {
             if let Err(why) = register_global_commands(&ctx).await {
@@ -79,6 +121,44 @@ impl EventHandler for Handler {
         }
     }
 
+    async fn guild_create(&self, ctx: Context, guild: Guild, _is_new: bool) {
+        // discord sends every guild billbot is in after connecting, and again whenever the
+        // bot is added to one
+        if let Err(why) = self.members.bootstrap(&ctx.http, guild.id).await {
+            error!("failed to load members of guild {}: {:?}", guild.id, why);
+        }
+    }
+
+    async fn guild_delete(
+        &self,
+        _ctx: Context,
+        incomplete: UnavailableGuild,
+        _full: Option<Guild>,
+    ) {
+        // an unavailable guild is only in an outage, billbot is still in it
+        if !incomplete.unavailable {
+            self.members.forget(incomplete.id);
+        }
+    }
+
+    async fn guild_member_addition(&self, _ctx: Context, new_member: Member) {
+        self.members.upsert(new_member);
+    }
+
+    async fn guild_member_update(&self, _ctx: Context, _old: Option<Member>, new: Member) {
+        self.members.upsert(new);
+    }
+
+    async fn guild_member_removal(
+        &self,
+        _ctx: Context,
+        guild_id: GuildId,
+        user: User,
+        _member: Option<Member>,
+    ) {
+        self.members.remove(guild_id, user.id);
+    }
+
     async fn interaction_create(&self, ctx: Context, interaction: Interaction) {
         match interaction {
             Interaction::ApplicationCommand(command) => {
@@ -88,7 +168,84 @@ impl EventHandler for Handler {
                 );
 
                 let interaction_response_result = match command.data.name.as_str() {
-                    "bill" => commands::create_bill::respond(&ctx, &command).await,
+                    "bill" => match command.data.options.first().map(|opt| opt.name.as_str()) {
+                        Some("balance") => {
+                            commands::balance::respond(
+                                &ctx,
+                                &command,
+                                self.store.as_ref(),
+                                self.rates.as_ref(),
+                            )
+                            .await
+                        }
+                        Some("settle-plan") => {
+                            commands::settle_plan::respond(
+                                &ctx,
+                                &command,
+                                self.store.as_ref(),
+                                self.rates.as_ref(),
+                            )
+                            .await
+                        }
+                        Some("config") => {
+                            commands::config::respond(&ctx, &command, self.store.as_ref()).await
+                        }
+                        Some("quick") => {
+                            commands::quick::respond(
+                                &ctx,
+                                &command,
+                                self.store.as_ref(),
+                                self.rates.as_ref(),
+                                &self.members,
+                                &self.drafts,
+                            )
+                            .await
+                        }
+                        Some("cancel") => {
+                            commands::cancel::respond(&ctx, &command, self.store.as_ref()).await
+                        }
+                        Some("list") => {
+                            commands::list::respond(
+                                &ctx,
+                                &command,
+                                self.store.as_ref(),
+                                &self.listings,
+                            )
+                            .await
+                        }
+                        Some("show") => {
+                            commands::show::respond(&ctx, &command, self.store.as_ref()).await
+                        }
+                        Some("rates") => {
+                            commands::rates::respond(&ctx, &command, self.rates.as_ref()).await
+                        }
+                        Some("remind") => {
+                            commands::remind::respond(&ctx, &command, self.store.as_ref()).await
+                        }
+                        Some("reminders") => {
+                            commands::reminders::respond(&ctx, &command, self.store.as_ref()).await
+                        }
+                        Some("recurring") => {
+                            commands::recurring::respond(
+                                &ctx,
+                                &command,
+                                self.store.as_ref(),
+                                self.rates.as_ref(),
+                                &self.members,
+                            )
+                            .await
+                        }
+                        _ => {
+                            commands::create_bill::respond(
+                                &ctx,
+                                &command,
+                                self.store.as_ref(),
+                                &self.members,
+                                &self.drafts,
+                            )
+                            .await
+                        }
+                    },
                     _ => commands::help::respond(&ctx, &command).await,
                 };
 
@@ -101,198 +258,173 @@ impl EventHandler for Handler {
                 );
             }
             Interaction::ModalSubmit(submission) => {
-                info!("received modal submit interaction");
-
-                let guild_id = submission.guild_id;
-                if guild_id.is_none() {
-                    error!("no guild id for modal submit, aborting response");
-                    return;
-                }
-
-                let guild_id = guild_id.unwrap();
-
-                let mut title = String::from("");
-                let mut amount = Money::from_major(10, iso::CAD);
-                let mut method = String::from("");
-                let mut payers: Vec<Member> = Vec::with_capacity(10);
-                let submitter;
+                info!(
+                    "received modal submit interaction, custom ID: {}, interaction ID: {}",
+                    submission.data.custom_id, submission.id
+                );
 
-                let submitter_nick = submission.user.nick_in(&ctx, guild_id).await;
+                // custom ids are prefixed with the kind of modal, followed by its arguments
+                let prefix = submission
+                    .data
+                    .custom_id
+                    .split(':')
+                    .next()
+                    .unwrap_or_default();
+
+                let interaction_response_result = match prefix {
+                    commands::create_bill::MODAL_ID => {
+                        commands::create_bill::submit(
+                            &ctx,
+                            &submission,
+                            self.store.as_ref(),
+                            self.rates.as_ref(),
+                            &self.members,
+                            &self.drafts,
+                        )
+                        .await
+                    }
+                    edit_bill::EDIT_MODAL_ID => {
+                        edit_bill::submit(
+                            &ctx,
+                            &submission,
+                            self.store.as_ref(),
+                            self.rates.as_ref(),
+                            &self.members,
+                            &self.drafts,
+                        )
+                        .await
+                    }
+                    cancel_bill::CANCEL_MODAL_ID => {
+                        cancel_bill::submit(&ctx, &submission, self.store.as_ref()).await
+                    }
+                    confirm_payment::REJECT_MODAL_ID => {
+                        confirm_payment::submit_rejection(&ctx, &submission, self.store.as_ref())
+                            .await
+                    }
+                    _ => {
+                        warn!("unknown modal custom_id: {}", submission.data.custom_id);
+                        Ok(())
+                    }
+                };
 
-                if let Some(nick) = submitter_nick {
-                    submitter = nick.trim().to_owned()
-                } else {
-                    warn!("submitter has no nickname, using username");
-                    submitter = submission.user.name.clone().trim().to_owned();
+                if let Err(why) = interaction_response_result {
+                    error!("failed to respond to modal submission: {:?}", why);
                 }
+            }
+            Interaction::MessageComponent(component) => {
+                info!(
+                    "received message component interaction, custom ID: {}, interaction ID: {}",
+                    component.data.custom_id, component.id
+                );
 
-                for row in &submission.data.components {
-                    for component in &row.components {
-                        match component {
-                            ActionRowComponent::InputText(data) => match data.custom_id.as_str() {
-                                "name" => title = data.value.clone().trim().to_owned(),
-                                "amount" => {
-                                    let amount_str = data.value.trim();
-                                    let cad = Money::from_str(amount_str, iso::CAD);
-                                    if let Err(why) = cad {
-                                        error!("failed to convert {} to CAD: {}", amount_str, why);
-                                        if let Err(why) = submission
-                                            .create_interaction_response(&ctx.http, |res| {
-                                                res.kind(ChannelMessageWithSource)
-                                                    .interaction_response_data(|msg| {
-                                                        msg.ephemeral(true).content(format!(
-                                                            "{} is not a valid CAD amount",
-                                                            amount_str
-                                                        ))
-                                                    })
-                                            })
-                                            .await
-                                        {
-                                            error!(
-                                                "failed to send early interaction response: {}",
-                                                why
-                                            );
-                                            return;
-                                        }
-                                        return;
-                                    }
-                                    amount = cad.unwrap();
-                                }
-                                "method" => method = data.value.clone().trim().to_owned(),
-                                "payers" => {
-                                    let payer_string = data.value.clone();
-                                    let payer_nicks: Vec<String> = payer_string
-                                        .split(',')
-                                        .map(|s| s.trim().to_owned())
-                                        .collect();
-                                    info!("payer nicks: {:?}", payer_nicks);
-
-                                    if submission.guild_id.is_none() {
-                                        error!(
-                                            "no guild_id for modal submission, aborting response"
-                                        );
-                                        return;
-                                    }
-
-                                    let guild_id = submission.guild_id.unwrap();
-                                    let guild_members =
-                                        guild_id.members(&ctx.http, None, None).await;
-                                    if let Err(why) = guild_members {
-                                        error!("failed to get members for guild, aborting response: {}", why);
-                                        return;
-                                    }
-                                    let guild_members = guild_members.unwrap();
-
-                                    let mut channel_members: Vec<Member> =
-                                        Vec::with_capacity(guild_members.len());
-
-                                    let channel_id = submission.channel_id;
-                                    let channels = guild_id.channels(&ctx.http).await;
-                                    if let Err(why) = channels {
-                                        error!("failed to list channels in guild: {}", why);
-                                        return;
-                                    }
-
-                                    let channels = channels.unwrap();
-                                    let channel = channels.get(&channel_id);
-                                    if channel.is_none() {
-                                        error!("submission channel does not exist in guild",);
-                                        return;
-                                    }
-
-                                    let channel = channel.unwrap();
-
-                                    for guild_member in &guild_members {
-                                        let perms = channel
-                                            .permissions_for_user(&ctx, guild_member.user.id);
-                                        if let Err(why) = perms {
-                                            error!("failed to get perms for a user: {}", why);
-                                            return;
-                                        }
-
-                                        if perms.unwrap().view_channel() {
-                                            channel_members.push(guild_member.clone());
-                                        }
-                                    }
-
-                                    let mut members = HashMap::with_capacity(channel_members.len());
-
-                                    for member in channel_members {
-                                        let name = member
-                                            .nick
-                                            .clone()
-                                            .unwrap_or_else(|| member.user.name.clone());
-
-                                        if members.contains_key(&name) {
-                                            warn!("two members with same name found in guild, name: {}", name);
-                                            continue;
-                                        }
-
-                                        members.insert(name, member.clone());
-                                    }
-
-                                    for payer in payer_nicks {
-                                        let found_member = members.get(&payer);
-                                        if found_member.is_none() {
-                                            warn!("payer not found, returning early: {}", payer);
-                                            if let Err(why) = submission
-                                                .create_interaction_response(&ctx.http, |res| {
-                                                    res.kind(ChannelMessageWithSource)
-                                                        .interaction_response_data(|msg| {
-                                                            msg.ephemeral(true).content(format!(
-                                                                "Payer not found: {}",
-                                                                payer
-                                                            ))
-                                                        })
-                                                })
-                                                .await
-                                            {
-                                                error!("failed to send early response: {}", why);
-                                            }
-                                            return;
-                                        }
-                                        payers.push(found_member.unwrap().clone())
-                                    }
-                                }
-                                _ => {
-                                    error!("invalid input text custom_id")
-                                }
-                            },
-                            _ => {
-                                error!("invalid compenent type");
-                            }
-                        }
+                // custom ids are prefixed with the kind of component, followed by its arguments
+                let prefix = component
+                    .data
+                    .custom_id
+                    .split(':')
+                    .next()
+                    .unwrap_or_default();
+
+                let interaction_response_result = match prefix {
+                    bill_message::MARK_PAID_ID => {
+                        components::mark_paid::respond(&ctx, &component, self.store.as_ref()).await
                     }
-                }
-
-                let each_owes = Money::from_decimal(
-                    amount
-                        .amount()
-                        .checked_div(Decimal::new(payers.len().try_into().unwrap(), 0))
-                        .unwrap(),
-                    iso::CAD,
-                );
+                    bill_message::EDIT_ID => {
+                        edit_bill::respond(&ctx, &component, self.store.as_ref(), &self.members)
+                            .await
+                    }
+                    bill_message::CANCEL_ID => {
+                        cancel_bill::respond(&ctx, &component, self.store.as_ref()).await
+                    }
+                    bill_message::NUDGE_ID => {
+                        nudge::respond(&ctx, &component, self.store.as_ref()).await
+                    }
+                    bill_list::PAGE_ID => {
+                        bill_list::respond(&ctx, &component, self.store.as_ref(), &self.listings)
+                            .await
+                    }
+                    reminder::PAID_ID => {
+                        reminder::paid(&ctx, &component, self.store.as_ref()).await
+                    }
+                    reminder::SNOOZE_ID => {
+                        reminder::snooze(&ctx, &component, self.store.as_ref()).await
+                    }
+                    reminder::STOP_ID => {
+                        reminder::stop(&ctx, &component, self.store.as_ref()).await
+                    }
+                    settle_plan::SETTLE_PLAN_ID => {
+                        settle_plan::respond(&ctx, &component, self.store.as_ref()).await
+                    }
+                    confirm_payment::CONFIRM_ID => {
+                        confirm_payment::confirm(&ctx, &component, self.store.as_ref()).await
+                    }
+                    confirm_payment::REJECT_ID => {
+                        confirm_payment::reject(&ctx, &component, self.store.as_ref()).await
+                    }
+                    pick_payers::PICK_PAYERS_ID => {
+                        pick_payers::pick(&ctx, &component, &self.drafts).await
+                    }
+                    pick_payers::CONTINUE_ID => {
+                        pick_payers::proceed(&ctx, &component, &self.drafts).await
+                    }
+                    pick_payers::PICK_MATCH_ID => {
+                        pick_payers::pick_match(&ctx, &component, &self.drafts).await
+                    }
+                    pick_payers::POST_ID => {
+                        pick_payers::post(
+                            &ctx,
+                            &component,
+                            self.store.as_ref(),
+                            self.rates.as_ref(),
+                            &self.members,
+                            &self.drafts,
+                        )
+                        .await
+                    }
+                    _ => {
+                        warn!("unknown component custom_id: {}", component.data.custom_id);
+                        Ok(())
+                    }
+                };
 
+                if let Err(why) = interaction_response_result {
+                    error!("failed to respond to component interaction: {:?}", why);
+                }
+            }
+            Interaction::Autocomplete(autocomplete) => {
                 info!(
-                    "submission values, title: {}, amount: {}, method: {}, submitter: {}, payer count: {} each pays: {}",
-                    title, amount.to_string(), method, submitter, payers.len(), each_owes
+                    "received autocomplete interaction, name: {}, interaction ID: {}",
+                    autocomplete.data.name, autocomplete.id
                 );
 
-                let payer_mentions = create_payer_mention_string(&payers);
+                let subcommand = autocomplete
+                    .data
+                    .options
+                    .first()
+                    .map(|opt| opt.name.as_str());
 
-                if let Err(why) = submission
-                    .create_interaction_response(&ctx.http, |res| {
-                        res.kind(ChannelMessageWithSource)
-                            .interaction_response_data(|msg| msg.content(format!("**🚨 AYO NEW BILL AVAILABLE 🚨**\n >>> Title: {}\nTotal amount: {}\nBill created by: {}\nPayment method: {}\nPayers: {}\nEach pays: {}\n\n *Thanks lads ❤️*", title, amount, submitter, method, payer_mentions, each_owes)))
-                    })
-                    .await
-                {
-                    error!("failed to respond to modal submission: {}", why);
+                let interaction_response_result =
+                    match (autocomplete.data.name.as_str(), subcommand) {
+                        ("bill", Some("quick")) => {
+                            commands::quick::autocomplete(&ctx, &autocomplete, &self.members).await
+                        }
+                        ("bill", Some("show")) | ("bill", Some("remind")) => {
+                            commands::show::autocomplete(&ctx, &autocomplete, self.store.as_ref())
+                                .await
+                        }
+                        _ => {
+                            warn!(
+                                "unknown autocomplete for command: {}",
+                                autocomplete.data.name
+                            );
+                            Ok(())
+                        }
+                    };
+
+                if let Err(why) = interaction_response_result {
+                    error!("failed to respond to autocomplete interaction: {:?}", why);
                 }
             }
-            Interaction::Autocomplete(_autocomplete) => {
-                info!("received autocomplete interaction");
-            }
             _ => {}
         }
     }
@@ -351,15 +483,3 @@ async fn deregister_guild_commands(ctx: &Context, guild_ids: &Vec<String>) -> an
 
     Ok(())
 }
-
-#[allow(clippy::single_char_add_str)]
-fn create_payer_mention_string(payers: &Vec<Member>) -> String {
-    let mut s = String::from("");
-
-    for payer in payers {
-        s.push_str(payer.mention().to_string().as_str());
-        s.push_str(" ");
-    }
-
-    s.trim_end().to_owned()
-}
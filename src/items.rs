@@ -0,0 +1,366 @@
+use anyhow::{bail, Context};
+use rust_decimal::Decimal;
+use rusty_money::{iso::Currency, Money};
+use serenity::model::prelude::UserId;
+use serenity::prelude::Mentionable;
+
+use crate::bill::BillItem;
+use crate::split::{self, Portion};
+
+/// A line typed into the payers field of an itemized bill, like `Fries 6 @Jacob @Joel`.
+#[derive(Clone, Debug)]
+pub struct ItemSpec {
+    pub name: String,
+    pub amount: Decimal,
+    /// Who the item is for as typed, each starting with an @.
+    pub assignees: Vec<String>,
+}
+
+/// Whether the payers field lists items rather than payers, which it does as soon as one line
+/// is shaped like `Burger 15.50 @Jacob`.
+pub fn is_itemized(input: &str, currency: &'static Currency) -> bool {
+    input.lines().any(|line| {
+        split_line(line).is_some_and(|(_, amount, _)| parse_amount(amount, currency).is_some())
+    })
+}
+
+/// Parses an item per line. Each item is a name, its amount and everyone it's for, each starting
+/// with an @ or separated by commas, where `@all` stands for everyone on the bill. Errors are
+/// meant to be shown to the bill creator as is.
+pub fn parse_items(input: &str, currency: &'static Currency) -> anyhow::Result<Vec<ItemSpec>> {
+    let mut items = Vec::new();
+
+    for line in input.lines().map(str::trim).filter(|line| !line.is_empty()) {
+        let (name, amount, assignees) = match split_line(line) {
+            Some(parts) => parts,
+            None => bail!(
+                "Couldn't understand `{}`, write each item on its own line like `Fries 6 @Jacob @Joel` or `Pitcher 24 @all`",
+                line
+            ),
+        };
+
+        let amount = match parse_amount(amount, currency) {
+            Some(amount) => amount,
+            None => bail!(
+                "{} isn't a valid {} amount for {}",
+                amount,
+                currency.iso_alpha_code,
+                name
+            ),
+        };
+
+        if let Some(other) = assignees.iter().find(|assignee| !is_assignee(assignee)) {
+            bail!(
+                "Couldn't understand `{}` in `{}`, put an @ in front of everyone the item is for",
+                other,
+                line
+            );
+        }
+
+        items.push(ItemSpec {
+            name,
+            amount,
+            assignees: assignees.into_iter().map(str::to_owned).collect(),
+        });
+    }
+
+    Ok(items)
+}
+
+/// Whether an assignee stands for everyone on the bill rather than a member or role.
+pub fn is_everyone(assignee: &str) -> bool {
+    assignee.eq_ignore_ascii_case("@all") || assignee.eq_ignore_ascii_case("@everyone")
+}
+
+/// Splits a line into the item's name, its amount and what follows it, if the line is shaped
+/// like an item at all.
+fn split_line(line: &str) -> Option<(String, &str, Vec<&str>)> {
+    let first_assignee = *assignee_starts(line).first()?;
+    let (name, amount) = line[..first_assignee]
+        .trim()
+        .rsplit_once(char::is_whitespace)?;
+    let name = name.trim();
+    if name.is_empty() {
+        return None;
+    }
+
+    Some((
+        name.to_owned(),
+        amount,
+        split_assignees(&line[first_assignee..]),
+    ))
+}
+
+/// Splits everyone an item is for into separate assignees. They're separated by commas or by
+/// the @ each one starts with, so names can have spaces in them, as in `@Jacob M @Joel`.
+fn split_assignees(s: &str) -> Vec<&str> {
+    let mut assignees = Vec::new();
+    for part in s.split(',') {
+        let mut starts = assignee_starts(part);
+        if starts.first() != Some(&0) {
+            starts.insert(0, 0);
+        }
+        starts.push(part.len());
+
+        assignees.extend(
+            starts
+                .windows(2)
+                .map(|bounds| part[bounds[0]..bounds[1]].trim())
+                .filter(|assignee| !assignee.is_empty()),
+        );
+    }
+    assignees
+}
+
+/// Where each assignee in `s` starts, which is at an @ or mention at the start of a word.
+fn assignee_starts(s: &str) -> Vec<usize> {
+    s.char_indices()
+        .filter(|(i, _)| {
+            is_assignee(&s[*i..])
+                && s[..*i]
+                    .chars()
+                    .next_back()
+                    .is_none_or(|prev| prev.is_whitespace() || prev == ',')
+        })
+        .map(|(i, _)| i)
+        .collect()
+}
+
+fn is_assignee(word: &str) -> bool {
+    word.starts_with('@') || word.starts_with("<@")
+}
+
+fn parse_amount(s: &str, currency: &'static Currency) -> Option<Decimal> {
+    let s = s
+        .strip_prefix(currency.symbol)
+        .or_else(|| s.strip_prefix('$'))
+        .unwrap_or(s);
+    if s.ends_with(',') {
+        return None;
+    }
+
+    split::parse_money(s, currency)
+        .ok()
+        .filter(|m| m.is_positive() && split::is_whole_minor_units(*m.amount(), currency))
+        .map(|m| *m.amount())
+}
+
+/// What each of the item's payers owes for it, in the same order. Items are split evenly, with
+/// leftover cents going to the earlier payers.
+pub fn portions(item: &BillItem, currency: &'static Currency) -> anyhow::Result<Vec<Decimal>> {
+    split::split(
+        item.amount,
+        currency,
+        &vec![Portion::Weight(Decimal::ONE); item.payers.len()],
+    )
+}
+
+/// What each payer's part of the items adds up to, in the order of `payers`.
+pub fn subtotals(
+    items: &[BillItem],
+    payers: &[UserId],
+    currency: &'static Currency,
+) -> anyhow::Result<Vec<Decimal>> {
+    let mut subtotals = vec![Decimal::ZERO; payers.len()];
+
+    for item in items {
+        for (user_id, amount) in item.payers.iter().zip(portions(item, currency)?) {
+            if let Some(i) = payers.iter().position(|payer| payer == user_id) {
+                subtotals[i] += amount;
+            }
+        }
+    }
+
+    Ok(subtotals)
+}
+
+/// What each payer owes on an itemized bill of `total`, in the order of `payers`. Whatever the
+/// total has on top of the items, like tax and tip, is shared in proportion to what each payer
+/// had. Errors are meant to be shown to the bill creator as is.
+pub fn owed(
+    total: Decimal,
+    items: &[BillItem],
+    payers: &[UserId],
+    currency: &'static Currency,
+) -> anyhow::Result<Vec<Decimal>> {
+    let items_total = items
+        .iter()
+        .try_fold(Decimal::ZERO, |sum, item| sum.checked_add(item.amount))
+        .context("The items add up to more than billbot can count")?;
+    if items_total > total {
+        bail!(
+            "The items add up to {}, which is more than the bill total of {}",
+            Money::from_decimal(items_total, currency),
+            Money::from_decimal(total, currency)
+        );
+    }
+
+    let subtotals = subtotals(items, payers, currency)?;
+    if let Some(i) = subtotals.iter().position(|subtotal| subtotal.is_zero()) {
+        bail!(
+            "{} isn't on any item, add them to one or to an item for @all",
+            payers[i].mention()
+        );
+    }
+
+    let weights: Vec<Portion> = subtotals.into_iter().map(Portion::Weight).collect();
+    split::split(total, currency, &weights)
+}
+
+/// The items a payer has a part in, along with what their part comes to.
+pub fn breakdown<'a>(
+    items: &'a [BillItem],
+    user_id: UserId,
+    currency: &'static Currency,
+) -> Vec<(&'a BillItem, Decimal)> {
+    items
+        .iter()
+        .filter_map(|item| {
+            let i = item.payers.iter().position(|payer| *payer == user_id)?;
+            let amount = portions(item, currency).ok()?.get(i).copied()?;
+            Some((item, amount))
+        })
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use rusty_money::iso;
+
+    use super::*;
+
+    fn cents(amount: i64) -> Decimal {
+        Decimal::new(amount, 2)
+    }
+
+    fn item(name: &str, amount: i64, payers: &[u64]) -> BillItem {
+        BillItem {
+            name: name.to_owned(),
+            amount: cents(amount),
+            payers: payers.iter().copied().map(UserId).collect(),
+        }
+    }
+
+    #[test]
+    fn recognises_itemized_payers_fields() {
+        assert!(is_itemized("Burger 15.50 @Jacob", iso::CAD));
+        assert!(is_itemized("Jacob\nFries $6 @Jacob @Joel", iso::CAD));
+        assert!(!is_itemized("Jacob, Joel", iso::CAD));
+        assert!(!is_itemized("Jacob $50, @roommates", iso::CAD));
+        assert!(!is_itemized("Jacob 60%, @roommates", iso::CAD));
+        assert!(!is_itemized("@roommates", iso::CAD));
+    }
+
+    #[test]
+    fn parses_items() {
+        let items = parse_items(
+            "Burger 15.50 @Jacob\n\n  Large fries $6 @Jacob @Joel  \nPitcher 24 @all",
+            iso::CAD,
+        )
+        .unwrap();
+
+        assert_eq!(items.len(), 3);
+        assert_eq!(items[0].name, "Burger");
+        assert_eq!(items[0].amount, cents(1550));
+        assert_eq!(items[0].assignees, vec!["@Jacob"]);
+        assert_eq!(items[1].name, "Large fries");
+        assert_eq!(items[1].amount, cents(600));
+        assert_eq!(items[1].assignees, vec!["@Jacob", "@Joel"]);
+        assert!(is_everyone(&items[2].assignees[0]));
+    }
+
+    #[test]
+    fn assignees_can_have_spaces_in_their_names() {
+        let items = parse_items(
+            "Wings 12 @Jacob Michels @Night Owls\nNachos 9 @Joel, @Sam Lee\nSalad 8 <@123> @Joel",
+            iso::CAD,
+        )
+        .unwrap();
+
+        assert_eq!(items[0].assignees, vec!["@Jacob Michels", "@Night Owls"]);
+        assert_eq!(items[1].assignees, vec!["@Joel", "@Sam Lee"]);
+        assert_eq!(items[2].assignees, vec!["<@123>", "@Joel"]);
+    }
+
+    #[test]
+    fn rejects_malformed_items() {
+        // no amount, a bad amount, fractions of a cent and an assignee without an @
+        for input in [
+            "Burger @Jacob",
+            "Burger abc @Jacob",
+            "Burger 1.234 @Jacob",
+            "Burger 0 @Jacob",
+            "Burger 5 @Jacob, Joel",
+            "Burger 5 @Jacob\nJoel",
+            "Burger 1.-5 @Jacob",
+            "Burger $1.+5 @Jacob",
+        ] {
+            assert!(parse_items(input, iso::CAD).is_err(), "{}", input);
+        }
+    }
+
+    #[test]
+    fn items_are_split_evenly() {
+        let fries = item("Fries", 1000, &[1, 2, 3]);
+
+        assert_eq!(
+            portions(&fries, iso::CAD).unwrap(),
+            vec![cents(334), cents(333), cents(333)]
+        );
+    }
+
+    #[test]
+    fn subtotals_add_up_each_payers_items() {
+        let items = vec![
+            item("Burger", 1550, &[1]),
+            item("Fries", 600, &[1, 2]),
+            item("Pitcher", 2400, &[1, 2, 3]),
+        ];
+        let payers = [UserId(1), UserId(2), UserId(3)];
+
+        assert_eq!(
+            subtotals(&items, &payers, iso::CAD).unwrap(),
+            vec![cents(2650), cents(1100), cents(800)]
+        );
+        assert_eq!(
+            breakdown(&items, UserId(2), iso::CAD)
+                .into_iter()
+                .map(|(item, amount)| (item.name.as_str(), amount))
+                .collect::<Vec<_>>(),
+            vec![("Fries", cents(300)), ("Pitcher", cents(800))]
+        );
+    }
+
+    #[test]
+    fn whatever_the_total_adds_is_shared_in_proportion() {
+        // items come to 45.50 on a 55.00 bill, the 9.50 of tax and tip follows the subtotals
+        let items = vec![
+            item("Burger", 1550, &[1]),
+            item("Fries", 600, &[1, 2]),
+            item("Pitcher", 2400, &[1, 2, 3]),
+        ];
+        let payers = [UserId(1), UserId(2), UserId(3)];
+        let owed = owed(cents(5500), &items, &payers, iso::CAD).unwrap();
+
+        assert_eq!(owed.iter().sum::<Decimal>(), cents(5500));
+        assert_eq!(owed, vec![cents(3203), cents(1330), cents(967)]);
+    }
+
+    #[test]
+    fn items_have_to_fit_the_total_and_everyone_needs_an_item() {
+        let items = vec![item("Burger", 1550, &[1]), item("Fries", 600, &[1, 2])];
+
+        assert!(owed(cents(2000), &items, &[UserId(1), UserId(2)], iso::CAD).is_err());
+        assert!(owed(
+            cents(2150),
+            &items,
+            &[UserId(1), UserId(2), UserId(3)],
+            iso::CAD
+        )
+        .is_err());
+        assert_eq!(
+            owed(cents(2150), &items, &[UserId(1), UserId(2)], iso::CAD).unwrap(),
+            vec![cents(1850), cents(300)]
+        );
+    }
+}
@@ -0,0 +1,384 @@
+use std::collections::BTreeMap;
+
+use rust_decimal::Decimal;
+use rusty_money::{iso::Currency, Money};
+use serenity::model::prelude::UserId;
+
+use crate::bill::{Bill, ShareStatus};
+use crate::rates;
+
+/// An unsettled share, seen as money the payer owes the bill creator.
+pub struct Debt<'a> {
+    pub bill: &'a Bill,
+    pub debtor: UserId,
+    pub creditor: UserId,
+    /// The share as it appears on the bill, in the bill's currency.
+    pub share_amount: Decimal,
+    /// What's owed in `currency`, which differs from the bill's once the debt is converted.
+    pub amount: Decimal,
+    pub currency: &'static Currency,
+}
+
+/// The net amount owed between a user and one other person in a single currency.
+pub struct Balance {
+    pub counterparty: UserId,
+    pub currency: &'static Currency,
+    /// Positive when the counterparty owes the user, negative when the user owes them.
+    pub amount: Decimal,
+}
+
+/// Lists every unsettled share across `bills`. Shares the creator owes themselves are skipped.
+pub fn outstanding_debts(bills: &[Bill]) -> Vec<Debt<'_>> {
+    bills
+        .iter()
+        .flat_map(|bill| {
+            bill.shares
+                .iter()
+                .filter(move |share| {
+                    share.status != ShareStatus::Settled && share.user_id != bill.creator_id
+                })
+                .map(move |share| Debt {
+                    bill,
+                    debtor: share.user_id,
+                    creditor: bill.creator_id,
+                    share_amount: share.amount,
+                    amount: share.amount,
+                    currency: bill.currency,
+                })
+        })
+        .collect()
+}
+
+/// Converts every debt into `to` using the rate `rate` gives for its bill. Debts without a
+/// known rate stay in their bill's currency, so they're still counted, just separately.
+pub fn convert_debts<'a>(
+    debts: Vec<Debt<'a>>,
+    to: &'static Currency,
+    rate: impl Fn(&Bill) -> Option<Decimal>,
+) -> anyhow::Result<Vec<Debt<'a>>> {
+    debts
+        .into_iter()
+        .map(|mut debt| {
+            if debt.currency != to {
+                if let Some(rate) = rate(debt.bill) {
+                    let amount = Money::from_decimal(debt.amount, debt.currency);
+                    debt.amount = *rates::convert(amount, to, rate)?.amount();
+                    debt.currency = to;
+                }
+            }
+            Ok(debt)
+        })
+        .collect()
+}
+
+/// Nets every debt involving `user` into one balance per counterparty and currency,
+/// dropping pairs that cancel out exactly.
+pub fn net_balances(debts: &[Debt], user: UserId) -> Vec<Balance> {
+    let mut totals: BTreeMap<(UserId, &str), (&'static Currency, Decimal)> = BTreeMap::new();
+
+    for debt in debts {
+        let (counterparty, amount) = if debt.creditor == user {
+            (debt.debtor, debt.amount)
+        } else if debt.debtor == user {
+            (debt.creditor, -debt.amount)
+        } else {
+            continue;
+        };
+
+        let currency = debt.currency;
+        totals
+            .entry((counterparty, currency.iso_alpha_code))
+            .or_insert((currency, Decimal::ZERO))
+            .1 += amount;
+    }
+
+    totals
+        .into_iter()
+        .filter(|(_, (_, amount))| !amount.is_zero())
+        .map(|((counterparty, _), (currency, amount))| Balance {
+            counterparty,
+            currency,
+            amount,
+        })
+        .collect()
+}
+
+/// A single payment in a settle plan.
+pub struct Transfer {
+    pub from: UserId,
+    pub to: UserId,
+    pub currency: &'static Currency,
+    pub amount: Decimal,
+}
+
+/// Nets every debt into one position per person and currency, then pairs the largest debtor
+/// with the largest creditor until everyone is square. This needs at most one transfer fewer
+/// than the number of people involved, and since every amount is an exact decimal the
+/// transfers add up to the net positions to the cent.
+pub fn settle_plan(debts: &[Debt]) -> Vec<Transfer> {
+    let mut positions: BTreeMap<&str, (&'static Currency, BTreeMap<UserId, Decimal>)> =
+        BTreeMap::new();
+
+    for debt in debts {
+        let currency = debt.currency;
+        let (_, people) = positions
+            .entry(currency.iso_alpha_code)
+            .or_insert_with(|| (currency, BTreeMap::new()));
+        *people.entry(debt.creditor).or_default() += debt.amount;
+        *people.entry(debt.debtor).or_default() -= debt.amount;
+    }
+
+    let mut transfers = Vec::new();
+    for (currency, people) in positions.into_values() {
+        let mut creditors: Vec<(UserId, Decimal)> = people
+            .iter()
+            .filter(|(_, amount)| amount.is_sign_positive() && !amount.is_zero())
+            .map(|(user, amount)| (*user, *amount))
+            .collect();
+        let mut debtors: Vec<(UserId, Decimal)> = people
+            .iter()
+            .filter(|(_, amount)| amount.is_sign_negative() && !amount.is_zero())
+            .map(|(user, amount)| (*user, -*amount))
+            .collect();
+
+        // largest first, ties broken by user id so the same debts always give the same plan
+        creditors.sort_by(|a, b| b.1.cmp(&a.1).then(a.0.cmp(&b.0)));
+        debtors.sort_by(|a, b| b.1.cmp(&a.1).then(a.0.cmp(&b.0)));
+
+        let (mut c, mut d) = (0, 0);
+        while c < creditors.len() && d < debtors.len() {
+            let amount = creditors[c].1.min(debtors[d].1);
+            transfers.push(Transfer {
+                from: debtors[d].0,
+                to: creditors[c].0,
+                currency,
+                amount,
+            });
+
+            creditors[c].1 -= amount;
+            debtors[d].1 -= amount;
+            if creditors[c].1.is_zero() {
+                c += 1;
+            }
+            if debtors[d].1.is_zero() {
+                d += 1;
+            }
+        }
+    }
+
+    transfers
+}
+
+#[cfg(test)]
+mod tests {
+    use std::collections::HashMap;
+
+    use chrono::Utc;
+    use proptest::prelude::*;
+    use rusty_money::iso;
+    use serenity::model::prelude::{ChannelId, GuildId};
+
+    use super::*;
+    use crate::bill::BillShare;
+
+    fn cents(amount: i64) -> Decimal {
+        Decimal::new(amount, 2)
+    }
+
+    fn bill(id: i64, creator: u64, currency: &'static Currency, shares: &[(u64, i64)]) -> Bill {
+        Bill {
+            id,
+            guild_id: GuildId(1),
+            channel_id: ChannelId(1),
+            message_id: None,
+            creator_id: UserId(creator),
+            creator_name: creator.to_string(),
+            title: format!("bill {}", id),
+            amount: shares.iter().map(|(_, amount)| cents(*amount)).sum(),
+            currency,
+            method: String::from("cash"),
+            created_at: Utc::now(),
+            due_at: None,
+            rate: None,
+            cancellation: None,
+            shares: shares
+                .iter()
+                .map(|(user, amount)| BillShare {
+                    user_id: UserId(*user),
+                    name: user.to_string(),
+                    amount: cents(*amount),
+                    status: if *user == creator {
+                        ShareStatus::Settled
+                    } else {
+                        ShareStatus::Owed
+                    },
+                    claimed_at: None,
+                    settled_at: None,
+                })
+                .collect(),
+            items: Vec::new(),
+        }
+    }
+
+    /// What each person is owed overall per currency, negative when they owe.
+    fn positions(debts: &[Debt]) -> HashMap<(UserId, &'static str), Decimal> {
+        let mut positions = HashMap::new();
+        for debt in debts {
+            let code = debt.currency.iso_alpha_code;
+            *positions.entry((debt.creditor, code)).or_default() += debt.amount;
+            *positions.entry((debt.debtor, code)).or_default() -= debt.amount;
+        }
+        positions
+    }
+
+    fn transferred(transfers: &[Transfer]) -> HashMap<(UserId, &'static str), Decimal> {
+        let mut positions = HashMap::new();
+        for transfer in transfers {
+            let code = transfer.currency.iso_alpha_code;
+            *positions.entry((transfer.to, code)).or_default() += transfer.amount;
+            *positions.entry((transfer.from, code)).or_default() -= transfer.amount;
+        }
+        positions
+    }
+
+    fn nonzero(
+        positions: HashMap<(UserId, &'static str), Decimal>,
+    ) -> HashMap<(UserId, &'static str), Decimal> {
+        positions
+            .into_iter()
+            .filter(|(_, amount)| !amount.is_zero())
+            .collect()
+    }
+
+    #[test]
+    fn chains_collapse_into_one_transfer() {
+        // 2 owes 1 on 1's bill, and 1 owes 3 the same on 3's bill
+        let bills = vec![
+            bill(1, 1, iso::CAD, &[(2, 1000)]),
+            bill(2, 3, iso::CAD, &[(1, 1000)]),
+        ];
+        let transfers = settle_plan(&outstanding_debts(&bills));
+
+        assert_eq!(transfers.len(), 1);
+        assert_eq!(transfers[0].from, UserId(2));
+        assert_eq!(transfers[0].to, UserId(3));
+        assert_eq!(transfers[0].amount, cents(1000));
+    }
+
+    #[test]
+    fn settled_and_own_shares_are_not_debts() {
+        let mut paid = bill(1, 1, iso::CAD, &[(1, 500), (2, 500), (3, 500)]);
+        paid.shares[1].status = ShareStatus::Settled;
+        let bills = vec![paid];
+        let debts = outstanding_debts(&bills);
+
+        assert_eq!(debts.len(), 1);
+        assert_eq!(debts[0].debtor, UserId(3));
+    }
+
+    #[test]
+    fn debts_that_cancel_out_leave_no_balance() {
+        let bills = vec![
+            bill(1, 1, iso::CAD, &[(2, 1234)]),
+            bill(2, 2, iso::CAD, &[(1, 1234)]),
+        ];
+        let debts = outstanding_debts(&bills);
+
+        assert!(net_balances(&debts, UserId(1)).is_empty());
+        assert!(settle_plan(&debts).is_empty());
+    }
+
+    #[test]
+    fn currencies_are_settled_separately() {
+        let bills = vec![
+            bill(1, 1, iso::CAD, &[(2, 1000)]),
+            bill(2, 2, iso::USD, &[(1, 1000)]),
+        ];
+        let debts = outstanding_debts(&bills);
+
+        let balances = net_balances(&debts, UserId(1));
+        assert_eq!(balances.len(), 2);
+        assert_eq!(settle_plan(&debts).len(), 2);
+    }
+
+    fn bills() -> impl Strategy<Value = Vec<Bill>> {
+        let spec = (
+            1..7u64,
+            prop::collection::vec((1..7u64, 1..100_000i64), 1..6),
+            any::<bool>(),
+        );
+        prop::collection::vec(spec, 1..15).prop_map(|bills| {
+            bills
+                .into_iter()
+                .enumerate()
+                .map(|(i, (creator, shares, usd))| {
+                    let currency = if usd { iso::USD } else { iso::CAD };
+                    let mut shares = shares;
+                    shares.sort_by_key(|(user, _)| *user);
+                    shares.dedup_by_key(|(user, _)| *user);
+                    bill(i as i64 + 1, creator, currency, &shares)
+                })
+                .collect()
+        })
+    }
+
+    proptest! {
+        #[test]
+        fn transfers_reconcile_to_the_cent(bills in bills()) {
+            let debts = outstanding_debts(&bills);
+            let transfers = settle_plan(&debts);
+
+            prop_assert_eq!(nonzero(transferred(&transfers)), nonzero(positions(&debts)));
+            for transfer in &transfers {
+                prop_assert!(transfer.amount > Decimal::ZERO);
+                prop_assert!(transfer.from != transfer.to);
+            }
+        }
+
+        #[test]
+        fn transfers_are_fewer_than_people(bills in bills()) {
+            let debts = outstanding_debts(&bills);
+            let transfers = settle_plan(&debts);
+
+            for currency in [iso::CAD, iso::USD] {
+                let people = positions(&debts)
+                    .into_iter()
+                    .filter(|((_, code), amount)| *code == currency.iso_alpha_code && !amount.is_zero())
+                    .count();
+                let count = transfers.iter().filter(|t| t.currency == currency).count();
+                prop_assert!(count <= people.saturating_sub(1));
+            }
+        }
+
+        #[test]
+        fn balances_add_up_to_each_position(bills in bills()) {
+            let debts = outstanding_debts(&bills);
+            let positions = positions(&debts);
+
+            for user in 1..7u64 {
+                let balances = net_balances(&debts, UserId(user));
+                for currency in [iso::CAD, iso::USD] {
+                    let total: Decimal = balances
+                        .iter()
+                        .filter(|balance| balance.currency == currency)
+                        .map(|balance| balance.amount)
+                        .sum();
+                    let position = positions
+                        .get(&(UserId(user), currency.iso_alpha_code))
+                        .copied()
+                        .unwrap_or_default();
+                    prop_assert_eq!(total, position);
+                }
+
+                // what one person is owed by another is what the other owes them
+                for balance in &balances {
+                    let mirrored = net_balances(&debts, balance.counterparty)
+                        .into_iter()
+                        .find(|other| other.counterparty == UserId(user) && other.currency == balance.currency)
+                        .map(|other| other.amount);
+                    prop_assert_eq!(mirrored, Some(-balance.amount));
+                }
+            }
+        }
+    }
+}
@@ -1,12 +1,29 @@
-use std::env;
+use std::{env, sync::Arc};
 
 use anyhow::Context;
 use handler::Handler;
 use log::info;
+use rates::FileRateProvider;
 use serenity::{prelude::GatewayIntents, Client};
+use store::SqliteStore;
 
+mod bill;
+mod bill_message;
 mod commands;
+mod components;
+mod draft;
+mod due;
 mod handler;
+mod items;
+mod ledger;
+mod members;
+mod payers;
+mod rates;
+mod recurring;
+mod reminders;
+mod schedule;
+mod split;
+mod store;
 
 #[tokio::main]
 async fn main() -> anyhow::Result<()> {
@@ -14,12 +31,21 @@ async fn main() -> anyhow::Result<()> {
 
     let token = env::var("BILLBOT_TOKEN").context("BILLBOT_TOKEN not found")?;
     let guild_ids_str = env::var("BILLBOT_GUILDS").unwrap_or_else(|_| "".to_owned());
+    let db_path = env::var("BILLBOT_DB").unwrap_or_else(|_| "billbot.db".to_owned());
+    let rates_path = env::var("BILLBOT_RATES").unwrap_or_else(|_| "rates.txt".to_owned());
 
     let mut guild_ids: Vec<String> = guild_ids_str.split(',').map(|id| id.to_owned()).collect();
     if guild_ids.first().unwrap() == "" {
         guild_ids.remove(0);
     }
 
+    let store = Arc::new(SqliteStore::open(&db_path).context("failed to open bill store")?);
+    info!("using bill store at {}", db_path);
+
+    let rates = FileRateProvider::open(&rates_path, store.clone())
+        .context("failed to load exchange rates")?;
+    info!("using exchange rates at {}", rates_path);
+
     let mut client = Client::builder(
         token,
         GatewayIntents::GUILD_MEMBERS
@@ -27,7 +53,7 @@ async fn main() -> anyhow::Result<()> {
             | GatewayIntents::GUILD_MESSAGES
             | GatewayIntents::GUILD_PRESENCES,
     )
-    .event_handler(Handler::new(guild_ids))
+    .event_handler(Handler::new(guild_ids, store, Arc::new(rates)))
     .await
     .context("failed to build serenity client")?;
 
@@ -0,0 +1,339 @@
+use std::{collections::HashMap, sync::RwLock};
+
+use anyhow::Context as _;
+use log::{info, warn};
+use serenity::{
+    http::Http,
+    model::{
+        prelude::{ChannelId, GuildId, Member, UserId},
+        Permissions,
+    },
+    prelude::Context,
+};
+
+/// The most members discord returns per request.
+const PAGE_SIZE: u64 = 1000;
+
+/// Every member of the guilds billbot is in. Each guild is fetched once, a page at a time,
+/// and then kept up to date from member events, so resolving payers doesn't mean fetching
+/// the whole guild on every bill.
+#[derive(Default)]
+pub struct MemberDirectory {
+    guilds: RwLock<HashMap<GuildId, Roster>>,
+}
+
+#[derive(Default)]
+struct Roster {
+    /// `None` until the guild has been fetched once.
+    members: Option<HashMap<UserId, Member>>,
+    /// How many fetches of the guild are in flight.
+    fetching: usize,
+    /// Member events seen since the oldest fetch in flight started. A fetch can't tell whether
+    /// its pages were read before or after an event, so every event is replayed onto what it
+    /// fetched, in order.
+    missed: Vec<Change>,
+}
+
+enum Change {
+    Upsert(Box<Member>),
+    Remove(UserId),
+}
+
+impl Roster {
+    fn apply(members: &mut HashMap<UserId, Member>, change: &Change) {
+        match change {
+            Change::Upsert(member) => {
+                members.insert(member.user.id, Member::clone(member));
+            }
+            Change::Remove(user_id) => {
+                members.remove(user_id);
+            }
+        }
+    }
+
+    fn record(&mut self, change: Change) {
+        if let Some(members) = self.members.as_mut() {
+            Self::apply(members, &change);
+        }
+        if self.fetching > 0 {
+            self.missed.push(change);
+        }
+    }
+}
+
+impl MemberDirectory {
+    /// Fetches every member of a guild, replacing whatever was known about it.
+    pub async fn bootstrap(&self, http: &Http, guild_id: GuildId) -> anyhow::Result<()> {
+        self.start_fetch(guild_id);
+        let fetched = fetch(http, guild_id).await;
+        self.finish_fetch(guild_id, fetched)
+    }
+
+    fn start_fetch(&self, guild_id: GuildId) {
+        self.guilds
+            .write()
+            .unwrap()
+            .entry(guild_id)
+            .or_default()
+            .fetching += 1;
+    }
+
+    /// Replaces the guild's members with what was fetched, plus the events missed while
+    /// fetching. A failed fetch keeps whatever was known before.
+    fn finish_fetch(
+        &self,
+        guild_id: GuildId,
+        fetched: anyhow::Result<HashMap<UserId, Member>>,
+    ) -> anyhow::Result<()> {
+        let mut guilds = self.guilds.write().unwrap();
+        let roster = guilds.entry(guild_id).or_default();
+        roster.fetching = roster.fetching.saturating_sub(1);
+
+        let result = fetched.map(|mut members| {
+            for change in &roster.missed {
+                Roster::apply(&mut members, change);
+            }
+            info!("loaded {} members of guild {}", members.len(), guild_id);
+            roster.members = Some(members);
+        });
+
+        if roster.fetching == 0 {
+            roster.missed.clear();
+            if roster.members.is_none() {
+                guilds.remove(&guild_id);
+            }
+        }
+
+        result
+    }
+
+    /// Records a member who joined or changed. Guilds that aren't loaded or being fetched are
+    /// skipped, they'll have the change once they are.
+    pub fn upsert(&self, member: Member) {
+        if let Some(roster) = self.guilds.write().unwrap().get_mut(&member.guild_id) {
+            roster.record(Change::Upsert(Box::new(member)));
+        }
+    }
+
+    pub fn remove(&self, guild_id: GuildId, user_id: UserId) {
+        if let Some(roster) = self.guilds.write().unwrap().get_mut(&guild_id) {
+            roster.record(Change::Remove(user_id));
+        }
+    }
+
+    /// Drops a guild billbot was removed from.
+    pub fn forget(&self, guild_id: GuildId) {
+        self.guilds.write().unwrap().remove(&guild_id);
+    }
+
+    /// Members of the guild who can see the channel, since only they can be billed in it.
+    /// Only the members who can are cloned out of the directory.
+    pub async fn channel_members(
+        &self,
+        ctx: &Context,
+        guild_id: GuildId,
+        channel_id: ChannelId,
+    ) -> anyhow::Result<Vec<Member>> {
+        if !self.is_loaded(guild_id) {
+            self.bootstrap(&ctx.http, guild_id).await?;
+        }
+
+        let channel = channel_id
+            .to_channel(ctx)
+            .await
+            .context("failed to get channel")?
+            .guild()
+            .context("channel is not in a guild")?;
+
+        // permissions come from the cached guild's roles when possible, only falling back to
+        // fetching the guild if it isn't cached
+        let visible = ctx.cache.guild_field(guild_id, |guild| {
+            self.filter(guild_id, |member| {
+                can_view(member, guild.user_permissions_in(&channel, member))
+            })
+        });
+        if let Some(visible) = visible {
+            return Ok(visible);
+        }
+
+        let guild = guild_id
+            .to_partial_guild(&ctx.http)
+            .await
+            .context("failed to get guild")?;
+        Ok(self.filter(guild_id, |member| {
+            can_view(member, guild.user_permissions_in(&channel, member))
+        }))
+    }
+
+    fn is_loaded(&self, guild_id: GuildId) -> bool {
+        self.guilds
+            .read()
+            .unwrap()
+            .get(&guild_id)
+            .is_some_and(|roster| roster.members.is_some())
+    }
+
+    /// Clones the members of a loaded guild that `keep` accepts.
+    fn filter(&self, guild_id: GuildId, keep: impl Fn(&Member) -> bool) -> Vec<Member> {
+        self.guilds
+            .read()
+            .unwrap()
+            .get(&guild_id)
+            .and_then(|roster| roster.members.as_ref())
+            .map(|members| {
+                members
+                    .values()
+                    .filter(|member| keep(member))
+                    .cloned()
+                    .collect()
+            })
+            .unwrap_or_default()
+    }
+}
+
+/// Every member of a guild, a page at a time.
+async fn fetch(http: &Http, guild_id: GuildId) -> anyhow::Result<HashMap<UserId, Member>> {
+    let mut members = HashMap::new();
+    let mut after = None;
+
+    loop {
+        let page = guild_id
+            .members(http, Some(PAGE_SIZE), after)
+            .await
+            .with_context(|| format!("failed to get members for guild {}", guild_id))?;
+
+        let last_page = (page.len() as u64) < PAGE_SIZE;
+        after = page.last().map(|member| member.user.id);
+        members.extend(page.into_iter().map(|member| (member.user.id, member)));
+
+        if last_page {
+            return Ok(members);
+        }
+    }
+}
+
+fn can_view(member: &Member, perms: serenity::Result<Permissions>) -> bool {
+    match perms {
+        Ok(perms) => perms.view_channel(),
+        Err(why) => {
+            warn!("failed to get perms for user {}: {}", member.user.id, why);
+            false
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use anyhow::anyhow;
+    use serde_json::json;
+
+    use super::*;
+
+    const GUILD: GuildId = GuildId(1);
+
+    fn member(id: u64, name: &str) -> Member {
+        serde_json::from_value(json!({
+            "deaf": false,
+            "guild_id": GUILD.to_string(),
+            "joined_at": null,
+            "mute": false,
+            "nick": null,
+            "roles": [],
+            "user": {
+                "id": id.to_string(),
+                "username": name,
+                "discriminator": "0000",
+                "avatar": null,
+            },
+        }))
+        .unwrap()
+    }
+
+    fn fetched(members: &[Member]) -> anyhow::Result<HashMap<UserId, Member>> {
+        Ok(members
+            .iter()
+            .map(|member| (member.user.id, member.clone()))
+            .collect())
+    }
+
+    fn names(directory: &MemberDirectory) -> Vec<String> {
+        let mut names: Vec<String> = directory
+            .filter(GUILD, |_| true)
+            .into_iter()
+            .map(|member| member.user.name)
+            .collect();
+        names.sort();
+        names
+    }
+
+    #[test]
+    fn events_before_a_guild_is_loaded_are_skipped() {
+        let directory = MemberDirectory::default();
+        directory.upsert(member(1, "jacob"));
+
+        assert!(!directory.is_loaded(GUILD));
+        assert!(names(&directory).is_empty());
+    }
+
+    #[test]
+    fn events_during_a_fetch_are_replayed_onto_it() {
+        let directory = MemberDirectory::default();
+        directory.start_fetch(GUILD);
+
+        // the fetch may have read its pages before these happened
+        directory.upsert(member(3, "sam"));
+        directory.remove(GUILD, UserId(2));
+        directory.upsert(member(1, "jacob renamed"));
+
+        directory
+            .finish_fetch(GUILD, fetched(&[member(1, "jacob"), member(2, "joel")]))
+            .unwrap();
+
+        assert_eq!(names(&directory), vec!["jacob renamed", "sam"]);
+
+        // once loaded, events apply as they come
+        directory.remove(GUILD, UserId(3));
+        assert_eq!(names(&directory), vec!["jacob renamed"]);
+    }
+
+    #[test]
+    fn overlapping_fetches_both_get_every_event() {
+        let directory = MemberDirectory::default();
+        directory.start_fetch(GUILD);
+        directory.upsert(member(2, "joel"));
+        directory.start_fetch(GUILD);
+        directory.upsert(member(3, "sam"));
+
+        directory
+            .finish_fetch(GUILD, fetched(&[member(1, "jacob")]))
+            .unwrap();
+        assert_eq!(names(&directory), vec!["jacob", "joel", "sam"]);
+
+        // the second fetch started after joel joined but may still have missed him
+        directory
+            .finish_fetch(GUILD, fetched(&[member(1, "jacob")]))
+            .unwrap();
+        assert_eq!(names(&directory), vec!["jacob", "joel", "sam"]);
+    }
+
+    #[test]
+    fn failed_fetches_keep_what_was_known() {
+        let directory = MemberDirectory::default();
+
+        directory.start_fetch(GUILD);
+        assert!(directory
+            .finish_fetch(GUILD, Err(anyhow!("discord is down")))
+            .is_err());
+        assert!(!directory.is_loaded(GUILD));
+
+        directory.start_fetch(GUILD);
+        directory
+            .finish_fetch(GUILD, fetched(&[member(1, "jacob")]))
+            .unwrap();
+        directory.start_fetch(GUILD);
+        assert!(directory
+            .finish_fetch(GUILD, Err(anyhow!("discord is down")))
+            .is_err());
+        assert_eq!(names(&directory), vec!["jacob"]);
+    }
+}
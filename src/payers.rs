@@ -0,0 +1,234 @@
+use std::collections::HashMap;
+
+use serenity::model::prelude::{Member, Role, RoleId, UserId};
+
+/// How many near misses are suggested when a payer can't be found.
+const MAX_SUGGESTIONS: usize = 3;
+
+/// What a name typed into the payers field refers to.
+pub enum Resolution<'a> {
+    Found(&'a Member),
+    /// Several members go by the name.
+    Ambiguous(Vec<&'a Member>),
+    /// Nobody goes by the name, with the closest names first.
+    NotFound(Vec<&'a Member>),
+}
+
+/// Looks a payer up among `members` by mention, user id, nickname, username or tag,
+/// ignoring case.
+pub fn resolve<'a>(input: &str, members: &'a [Member]) -> Resolution<'a> {
+    // modals don't turn `@name` into a mention, so the @ is just decoration
+    let input = input.trim();
+    let input = input.strip_prefix('@').unwrap_or(input);
+
+    if let Some(id) = parse_user_id(input) {
+        if let Some(member) = members.iter().find(|member| member.user.id == id) {
+            return Resolution::Found(member);
+        }
+    }
+
+    let wanted = input.to_lowercase();
+    let mut matches: Vec<&Member> = members
+        .iter()
+        .filter(|member| names(member).any(|name| name.to_lowercase() == wanted))
+        .collect();
+
+    match matches.len() {
+        0 => Resolution::NotFound(suggestions(&wanted, members)),
+        1 => Resolution::Found(matches.remove(0)),
+        _ => Resolution::Ambiguous(matches),
+    }
+}
+
+/// Whether `input` could refer to a role, in which case the guild's roles need fetching.
+pub fn may_be_role(input: &str) -> bool {
+    input.trim().starts_with('@') || input.trim().starts_with("<@&")
+}
+
+/// Looks a role up by `<@&id>` mention or by `@name`, ignoring case.
+pub fn resolve_role<'a>(input: &str, roles: &'a HashMap<RoleId, Role>) -> Option<&'a Role> {
+    let input = input.trim();
+
+    if let Some(id) = input
+        .strip_prefix("<@&")
+        .and_then(|id| id.strip_suffix('>'))
+        .and_then(|id| id.parse().ok())
+    {
+        return roles.get(&RoleId(id));
+    }
+
+    let name = input.strip_prefix('@')?.to_lowercase();
+    roles.values().find(|role| role.name.to_lowercase() == name)
+}
+
+/// Accepts `<@id>`, `<@!id>` and bare ids.
+fn parse_user_id(input: &str) -> Option<UserId> {
+    let id = input
+        .strip_prefix("<@")
+        .and_then(|id| id.strip_suffix('>'))
+        .map(|id| id.trim_start_matches('!'))
+        .unwrap_or(input);
+
+    id.parse().ok().map(UserId)
+}
+
+/// Every name a member can be typed as. Users' global display names would belong here too, but
+/// serenity 0.11 doesn't deserialize them, so they can't be matched until billbot moves to 0.12.
+fn names(member: &Member) -> impl Iterator<Item = String> + '_ {
+    member
+        .nick
+        .iter()
+        .cloned()
+        .chain([member.user.name.clone(), member.user.tag()])
+}
+
+/// The members whose names are closest to `wanted` by edit distance, leaving out anyone
+/// so far off that suggesting them would be noise.
+fn suggestions<'a>(wanted: &str, members: &'a [Member]) -> Vec<&'a Member> {
+    let max_distance = (wanted.chars().count() / 2).max(2);
+
+    let mut ranked: Vec<(usize, &Member)> = members
+        .iter()
+        .filter_map(|member| {
+            names(member)
+                .map(|name| strsim::levenshtein(wanted, &name.to_lowercase()))
+                .min()
+                .filter(|distance| *distance <= max_distance)
+                .map(|distance| (distance, member))
+        })
+        .collect();
+    ranked.sort_by_key(|(distance, member)| (*distance, member.display_name().to_lowercase()));
+
+    ranked
+        .into_iter()
+        .take(MAX_SUGGESTIONS)
+        .map(|(_, member)| member)
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use serde_json::json;
+
+    use super::*;
+
+    fn member(id: u64, name: &str, discriminator: &str, nick: Option<&str>) -> Member {
+        serde_json::from_value(json!({
+            "deaf": false,
+            "guild_id": "1",
+            "joined_at": null,
+            "mute": false,
+            "nick": nick,
+            "roles": [],
+            "user": {
+                "id": id.to_string(),
+                "username": name,
+                "discriminator": discriminator,
+                "avatar": null,
+            },
+            "premium_since": null,
+            "permissions": null,
+            "avatar": null,
+            "communication_disabled_until": null,
+        }))
+        .unwrap()
+    }
+
+    fn members() -> Vec<Member> {
+        vec![
+            member(1, "jacobm", "0001", Some("Jacob")),
+            member(2, "joel", "0002", None),
+            member(3, "jacob", "0003", Some("Jake")),
+            member(4, "sam", "0004", Some("Jake")),
+        ]
+    }
+
+    fn found(input: &str, members: &[Member]) -> Option<u64> {
+        match resolve(input, members) {
+            Resolution::Found(member) => Some(member.user.id.0),
+            _ => None,
+        }
+    }
+
+    #[test]
+    fn finds_members_by_any_of_their_names() {
+        let members = members();
+
+        assert_eq!(found("JACOBM", &members), Some(1));
+        assert_eq!(found("jacobm#0001", &members), Some(1));
+        assert_eq!(found("sam", &members), Some(4));
+        assert_eq!(found("@joel", &members), Some(2));
+        assert_eq!(found(" joel#0002 ", &members), Some(2));
+    }
+
+    #[test]
+    fn finds_members_by_mention_or_id() {
+        let members = members();
+
+        assert_eq!(found("<@2>", &members), Some(2));
+        assert_eq!(found("<@!2>", &members), Some(2));
+        assert_eq!(found("2", &members), Some(2));
+        assert_eq!(found("<@99>", &members), None);
+    }
+
+    #[test]
+    fn shared_names_are_ambiguous() {
+        let members = members();
+
+        let ambiguous = |input: &str| match resolve(input, &members) {
+            Resolution::Ambiguous(candidates) => {
+                candidates.iter().map(|member| member.user.id.0).collect()
+            }
+            _ => Vec::new(),
+        };
+
+        assert_eq!(ambiguous("jake"), vec![3, 4]);
+        // a nickname and someone else's username count the same
+        assert_eq!(ambiguous("Jacob"), vec![1, 3]);
+    }
+
+    #[test]
+    fn suggests_the_closest_names() {
+        let members = members();
+
+        match resolve("joell", &members) {
+            Resolution::NotFound(suggestions) => {
+                assert_eq!(suggestions[0].user.id.0, 2);
+            }
+            _ => panic!("joell shouldn't be found"),
+        }
+
+        match resolve("somebody else entirely", &members) {
+            Resolution::NotFound(suggestions) => assert!(suggestions.is_empty()),
+            _ => panic!("nobody should be found"),
+        }
+    }
+
+    #[test]
+    fn suggests_at_most_a_few_members() {
+        let members: Vec<Member> = (1..10)
+            .map(|i| member(i, &format!("user{}", i), "0000", None))
+            .collect();
+
+        match resolve("user", &members) {
+            Resolution::NotFound(suggestions) => assert_eq!(suggestions.len(), MAX_SUGGESTIONS),
+            _ => panic!("user shouldn't be found"),
+        }
+    }
+
+    #[test]
+    fn only_at_names_and_role_mentions_may_be_roles() {
+        assert!(may_be_role("@roommates"));
+        assert!(may_be_role(" <@&123>"));
+        assert!(!may_be_role("roommates"));
+    }
+
+    #[test]
+    fn parses_user_ids() {
+        assert_eq!(parse_user_id("<@123>"), Some(UserId(123)));
+        assert_eq!(parse_user_id("<@!123>"), Some(UserId(123)));
+        assert_eq!(parse_user_id("123"), Some(UserId(123)));
+        assert_eq!(parse_user_id("<@&123>"), None);
+        assert_eq!(parse_user_id("jacob"), None);
+    }
+}
@@ -0,0 +1,369 @@
+use std::{collections::BTreeMap, fs, io::ErrorKind, path::Path, str::FromStr, sync::Arc};
+
+use anyhow::{bail, Context};
+use rust_decimal::Decimal;
+use rusty_money::{
+    iso::{self, Currency},
+    ExchangeRate, Money,
+};
+use serenity::model::prelude::GuildId;
+
+use crate::bill::{Bill, BillRate};
+
+/// The smallest rate that can be set. Together with `MAX_RATE` this keeps conversions and
+/// inverse rates well inside what a `Decimal` can hold.
+const MIN_RATE: Decimal = Decimal::from_parts(1, 0, 0, false, 6);
+const MAX_RATE: Decimal = Decimal::from_parts(1_000_000, 0, 0, false, 0);
+
+/// A source of exchange rates between currencies, kept separately for every guild.
+/// Implementations must be safe to share between the event handler's concurrently running tasks.
+pub trait RateProvider: Send + Sync {
+    /// Every rate the guild has set.
+    fn rates(&self, guild_id: GuildId) -> anyhow::Result<RateTable>;
+
+    /// Sets how many units of `to` one unit of `from` buys in the guild, replacing the rate
+    /// in either direction.
+    fn set_rate(
+        &self,
+        guild_id: GuildId,
+        from: &'static Currency,
+        to: &'static Currency,
+        rate: Decimal,
+    ) -> anyhow::Result<()>;
+}
+
+/// Rates kept in a plain text file, one `FROM TO RATE` line per rate, ex. `USD CAD 1.36`, that
+/// every guild starts out with. Blank lines and lines starting with `#` are ignored. Rates a
+/// guild sets itself are kept by `guild_rates` and win over the file's.
+pub struct FileRateProvider {
+    defaults: RateTable,
+    guild_rates: Arc<dyn RateProvider>,
+}
+
+impl FileRateProvider {
+    /// Loads the rates in `path`, starting with no rates if the file doesn't exist.
+    pub fn open(
+        path: impl AsRef<Path>,
+        guild_rates: Arc<dyn RateProvider>,
+    ) -> anyhow::Result<FileRateProvider> {
+        let path = path.as_ref();
+
+        let contents = match fs::read_to_string(path) {
+            Ok(contents) => contents,
+            Err(why) if why.kind() == ErrorKind::NotFound => String::from(""),
+            Err(why) => {
+                return Err(why).with_context(|| format!("failed to read {}", path.display()))
+            }
+        };
+
+        Ok(FileRateProvider {
+            defaults: parse_rates(&contents).with_context(|| path.display().to_string())?,
+            guild_rates,
+        })
+    }
+}
+
+impl RateProvider for FileRateProvider {
+    fn rates(&self, guild_id: GuildId) -> anyhow::Result<RateTable> {
+        let mut rates = self.defaults.clone();
+        for (from, to, rate) in self.guild_rates.rates(guild_id)?.iter() {
+            rates.insert(from, to, rate);
+        }
+
+        Ok(rates)
+    }
+
+    fn set_rate(
+        &self,
+        guild_id: GuildId,
+        from: &'static Currency,
+        to: &'static Currency,
+        rate: Decimal,
+    ) -> anyhow::Result<()> {
+        self.guild_rates.set_rate(guild_id, from, to, rate)
+    }
+}
+
+/// Parses the contents of a rates file.
+fn parse_rates(contents: &str) -> anyhow::Result<RateTable> {
+    let mut rates = RateTable::default();
+    for (i, line) in contents.lines().enumerate() {
+        let line = line.trim();
+        if line.is_empty() || line.starts_with('#') {
+            continue;
+        }
+
+        let (from, to, rate) = parse_rate(line.split_whitespace())
+            .with_context(|| format!("line {}: invalid rate", i + 1))?;
+        rates.insert(from, to, rate);
+    }
+
+    Ok(rates)
+}
+
+/// A set of exchange rates, each stored in the direction it was set in.
+#[derive(Clone, Debug, Default)]
+pub struct RateTable {
+    rates: BTreeMap<(&'static str, &'static str), Decimal>,
+}
+
+impl RateTable {
+    /// Adds a rate, replacing any rate between the two currencies in the other direction.
+    pub fn insert(&mut self, from: &'static Currency, to: &'static Currency, rate: Decimal) {
+        self.rates.remove(&(to.iso_alpha_code, from.iso_alpha_code));
+        self.rates
+            .insert((from.iso_alpha_code, to.iso_alpha_code), rate);
+    }
+
+    /// How many units of `to` one unit of `from` buys, if the rate is known.
+    pub fn rate(&self, from: &'static Currency, to: &'static Currency) -> Option<Decimal> {
+        if from == to {
+            return Some(Decimal::ONE);
+        }
+
+        if let Some(rate) = self.rates.get(&(from.iso_alpha_code, to.iso_alpha_code)) {
+            return Some(*rate);
+        }
+
+        // only one direction has to be set, the other is its inverse
+        self.rates
+            .get(&(to.iso_alpha_code, from.iso_alpha_code))
+            .and_then(|rate| Decimal::ONE.checked_div(*rate))
+    }
+
+    /// Every rate as it was set, sorted by currency code.
+    pub fn iter(
+        &self,
+    ) -> impl Iterator<Item = (&'static Currency, &'static Currency, Decimal)> + '_ {
+        self.rates
+            .iter()
+            .filter_map(|((from, to), rate)| Some((iso::find(from)?, iso::find(to)?, *rate)))
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.rates.is_empty()
+    }
+}
+
+/// Parses a `FROM TO RATE` triple, as found in the rates file and the rates command.
+pub fn parse_rate<'a>(
+    mut parts: impl Iterator<Item = &'a str>,
+) -> anyhow::Result<(&'static Currency, &'static Currency, Decimal)> {
+    let (from, to, rate) = match (parts.next(), parts.next(), parts.next(), parts.next()) {
+        (Some(from), Some(to), Some(rate), None) => (from, to, rate),
+        _ => bail!("expected a rate like `USD CAD 1.36`"),
+    };
+
+    let currency = |code: &str| {
+        iso::find(&code.trim().to_uppercase())
+            .with_context(|| format!("{} isn't a currency code billbot knows", code))
+    };
+    let (from, to) = (currency(from)?, currency(to)?);
+    if from == to {
+        bail!(
+            "{} can't have an exchange rate to itself",
+            from.iso_alpha_code
+        );
+    }
+
+    let rate = Decimal::from_str(rate.trim())
+        .ok()
+        .filter(|rate| rate.is_sign_positive() && !rate.is_zero())
+        .with_context(|| format!("{} isn't a valid exchange rate", rate))?;
+    if rate < MIN_RATE || rate > MAX_RATE {
+        bail!(
+            "Exchange rates have to be between {} and {}",
+            MIN_RATE,
+            MAX_RATE
+        );
+    }
+
+    Ok((from, to, rate))
+}
+
+/// The rate to convert a bill into `to` with. The rate recorded when the bill was created
+/// wins, so changing a rate later doesn't change what people owe on existing bills.
+pub fn bill_rate(bill: &Bill, to: &'static Currency, rates: &RateTable) -> Option<Decimal> {
+    match &bill.rate {
+        Some(recorded) if recorded.currency == to => Some(recorded.rate),
+        _ => rates.rate(bill.currency, to),
+    }
+}
+
+/// The rate to record on a new bill in `currency` for a guild that uses `guild_currency`,
+/// bills in the guild's own currency don't need one.
+pub fn rate_to_record(
+    currency: &'static Currency,
+    guild_currency: &'static Currency,
+    rates: &RateTable,
+) -> Option<BillRate> {
+    if currency == guild_currency {
+        return None;
+    }
+
+    rates.rate(currency, guild_currency).map(|rate| BillRate {
+        currency: guild_currency,
+        rate,
+    })
+}
+
+/// Converts `amount` into `to` and rounds it to `to`'s minor unit.
+pub fn convert(
+    amount: Money<'static, Currency>,
+    to: &'static Currency,
+    rate: Decimal,
+) -> anyhow::Result<Money<'static, Currency>> {
+    if amount.currency() == to {
+        return Ok(amount);
+    }
+
+    // ExchangeRate doesn't check for overflow itself
+    if amount.amount().checked_mul(rate).is_none() {
+        bail!(
+            "{} is too large to convert into {}",
+            amount,
+            to.iso_alpha_code
+        );
+    }
+
+    let converted = ExchangeRate::new(amount.currency(), to, rate)?.convert(amount)?;
+    Ok(Money::from_decimal(
+        converted.amount().round_dp(to.exponent),
+        to,
+    ))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn rate(s: &str) -> anyhow::Result<(&'static Currency, &'static Currency, Decimal)> {
+        parse_rate(s.split_whitespace())
+    }
+
+    fn dec(s: &str) -> Decimal {
+        Decimal::from_str(s).unwrap()
+    }
+
+    #[test]
+    fn parses_rates() {
+        let (from, to, rate) = rate("usd Cad 1.36").unwrap();
+        assert_eq!(from, iso::USD);
+        assert_eq!(to, iso::CAD);
+        assert_eq!(rate, dec("1.36"));
+    }
+
+    #[test]
+    fn rejects_malformed_rates() {
+        assert!(rate("USD CAD").is_err());
+        assert!(rate("USD CAD 1.36 extra").is_err());
+        assert!(rate("USD XYZ 1.36").is_err());
+        assert!(rate("USD USD 1").is_err());
+        assert!(rate("USD CAD abc").is_err());
+        assert!(rate("USD CAD 0").is_err());
+        assert!(rate("USD CAD -1.36").is_err());
+    }
+
+    #[test]
+    fn rejects_out_of_range_rates() {
+        assert!(rate("USD CAD 0.0000009").is_err());
+        assert!(rate("USD CAD 1000001").is_err());
+        assert!(rate("USD CAD 0.000001").is_ok());
+        assert!(rate("USD CAD 1000000").is_ok());
+    }
+
+    #[test]
+    fn parses_rates_files() {
+        let rates = parse_rates("# FROM TO RATE\n\nUSD CAD 1.36\n  eur cad 1.5  \n").unwrap();
+        assert_eq!(rates.rate(iso::USD, iso::CAD), Some(dec("1.36")));
+        assert_eq!(rates.rate(iso::EUR, iso::CAD), Some(dec("1.5")));
+
+        let why = parse_rates("USD CAD 1.36\nUSD CAD").unwrap_err();
+        assert!(format!("{:#}", why).contains("line 2"), "{:#}", why);
+    }
+
+    #[test]
+    fn guild_rates_win_over_the_files() {
+        let store = Arc::new(crate::store::SqliteStore::open(":memory:").unwrap());
+        let rates = FileRateProvider {
+            defaults: parse_rates("USD CAD 1.36\nEUR CAD 1.5").unwrap(),
+            guild_rates: store,
+        };
+        rates
+            .set_rate(GuildId(1), iso::CAD, iso::USD, dec("0.8"))
+            .unwrap();
+
+        let guild = rates.rates(GuildId(1)).unwrap();
+        assert_eq!(guild.rate(iso::USD, iso::CAD), Some(dec("1.25")));
+        assert_eq!(guild.rate(iso::EUR, iso::CAD), Some(dec("1.5")));
+        assert_eq!(guild.iter().count(), 2);
+
+        let other = rates.rates(GuildId(2)).unwrap();
+        assert_eq!(other.rate(iso::USD, iso::CAD), Some(dec("1.36")));
+    }
+
+    #[test]
+    fn looks_up_rates_in_both_directions() {
+        let mut rates = RateTable::default();
+        rates.insert(iso::USD, iso::CAD, dec("1.25"));
+
+        assert_eq!(rates.rate(iso::USD, iso::CAD), Some(dec("1.25")));
+        assert_eq!(rates.rate(iso::CAD, iso::USD), Some(dec("0.8")));
+        assert_eq!(rates.rate(iso::EUR, iso::EUR), Some(Decimal::ONE));
+        assert_eq!(rates.rate(iso::EUR, iso::CAD), None);
+    }
+
+    #[test]
+    fn setting_the_inverse_replaces_a_rate() {
+        let mut rates = RateTable::default();
+        rates.insert(iso::USD, iso::CAD, dec("1.25"));
+        rates.insert(iso::CAD, iso::USD, dec("0.5"));
+
+        assert_eq!(rates.iter().count(), 1);
+        assert_eq!(rates.rate(iso::USD, iso::CAD), Some(dec("2")));
+    }
+
+    #[test]
+    fn records_rates_only_for_foreign_bills() {
+        let mut rates = RateTable::default();
+        rates.insert(iso::USD, iso::CAD, dec("1.36"));
+
+        assert!(rate_to_record(iso::CAD, iso::CAD, &rates).is_none());
+        assert!(rate_to_record(iso::EUR, iso::CAD, &rates).is_none());
+        let recorded = rate_to_record(iso::USD, iso::CAD, &rates).unwrap();
+        assert_eq!(recorded.currency, iso::CAD);
+        assert_eq!(recorded.rate, dec("1.36"));
+    }
+
+    #[test]
+    fn converts_to_the_minor_unit() {
+        let converted = convert(
+            Money::from_str("10.01", iso::USD).unwrap(),
+            iso::CAD,
+            dec("1.36"),
+        );
+        assert_eq!(
+            converted.unwrap(),
+            Money::from_str("13.61", iso::CAD).unwrap()
+        );
+
+        let converted = convert(
+            Money::from_str("12.34", iso::USD).unwrap(),
+            iso::JPY,
+            dec("150.5"),
+        );
+        assert_eq!(
+            converted.unwrap(),
+            Money::from_str("1857", iso::JPY).unwrap()
+        );
+
+        let same = Money::from_str("12.34", iso::USD).unwrap();
+        assert_eq!(convert(same.clone(), iso::USD, dec("2")).unwrap(), same);
+    }
+
+    #[test]
+    fn refuses_conversions_that_overflow() {
+        let huge = Money::from_decimal(Decimal::MAX, iso::USD);
+        assert!(convert(huge, iso::CAD, MAX_RATE).is_err());
+    }
+}
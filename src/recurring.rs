@@ -0,0 +1,252 @@
+use std::{sync::Arc, time::Duration};
+
+use chrono::{DateTime, Utc};
+use log::{error, info, warn};
+use rust_decimal::Decimal;
+use rusty_money::{iso::Currency, Money};
+use serenity::model::prelude::{ChannelId, GuildId, UserId};
+use serenity::prelude::Context;
+use tokio::time::MissedTickBehavior;
+
+use crate::bill::{Bill, BillShare, ShareStatus};
+use crate::bill_message;
+use crate::rates::{self, RateProvider};
+use crate::schedule::Schedule;
+use crate::store::BillStore;
+
+/// How often the scheduler looks for templates that are due.
+const CHECK_INTERVAL: Duration = Duration::from_secs(60);
+
+/// After a long downtime only this many of a template's missed bills are posted, the older
+/// ones are skipped so a channel isn't flooded with them.
+const MAX_CATCH_UP: usize = 12;
+
+/// A bill that's posted again on a schedule, ex. rent on the first of every month.
+#[derive(Clone, Debug)]
+pub struct RecurringBill {
+    pub id: i64,
+    pub guild_id: GuildId,
+    pub channel_id: ChannelId,
+    pub creator_id: UserId,
+    pub creator_name: String,
+    pub title: String,
+    pub amount: Decimal,
+    pub currency: &'static Currency,
+    pub method: String,
+    pub schedule: Schedule,
+    /// When the next bill is due to be posted.
+    pub next_run: DateTime<Utc>,
+    pub created_at: DateTime<Utc>,
+    /// Who pays what on every bill posted from the template, worked out when it was set up.
+    pub shares: Vec<TemplateShare>,
+}
+
+#[derive(Clone, Debug)]
+pub struct TemplateShare {
+    pub user_id: UserId,
+    pub name: String,
+    pub amount: Decimal,
+}
+
+impl RecurringBill {
+    pub fn total(&self) -> Money<'static, Currency> {
+        Money::from_decimal(self.amount, self.currency)
+    }
+
+    /// A new bill from the template, for the run due at `run`.
+    pub fn bill(&self, run: DateTime<Utc>) -> Bill {
+        Bill {
+            id: 0,
+            guild_id: self.guild_id,
+            channel_id: self.channel_id,
+            message_id: None,
+            creator_id: self.creator_id,
+            creator_name: self.creator_name.clone(),
+            title: self.title.clone(),
+            amount: self.amount,
+            currency: self.currency,
+            method: self.method.clone(),
+            created_at: run,
+            due_at: None,
+            rate: None,
+            cancellation: None,
+            shares: self
+                .shares
+                .iter()
+                .map(|share| {
+                    // the creator's own share is counted in the split but there's nothing to pay
+                    let own_share = share.user_id == self.creator_id;
+                    BillShare {
+                        user_id: share.user_id,
+                        name: share.name.clone(),
+                        amount: share.amount,
+                        status: if own_share {
+                            ShareStatus::Settled
+                        } else {
+                            ShareStatus::Owed
+                        },
+                        claimed_at: None,
+                        settled_at: own_share.then_some(run),
+                    }
+                })
+                .collect(),
+            items: Vec::new(),
+        }
+    }
+}
+
+/// Posts bills from recurring templates as they come due, forever. Next runs are kept in the
+/// store, so runs missed while billbot was down are posted once it's back.
+pub async fn run(ctx: Context, store: Arc<dyn BillStore>, rates: Arc<dyn RateProvider>) {
+    let mut interval = tokio::time::interval(CHECK_INTERVAL);
+    interval.set_missed_tick_behavior(MissedTickBehavior::Delay);
+
+    loop {
+        interval.tick().await;
+
+        let due = match store.due_recurring(Utc::now()) {
+            Ok(due) => due,
+            Err(why) => {
+                error!("failed to load due recurring bills: {:?}", why);
+                continue;
+            }
+        };
+
+        for template in due {
+            if let Err(why) = post_due(&ctx, store.as_ref(), rates.as_ref(), &template).await {
+                error!("failed to post recurring bill {}: {:?}", template.id, why);
+            }
+        }
+    }
+}
+
+/// Posts a bill for every run of the template that's come due, up to `MAX_CATCH_UP` of them.
+async fn post_due(
+    ctx: &Context,
+    store: &dyn BillStore,
+    rates: &dyn RateProvider,
+    template: &RecurringBill,
+) -> anyhow::Result<()> {
+    let (runs, skipped, next) = due_runs(&template.schedule, template.next_run, Utc::now());
+    if skipped > 0 {
+        warn!(
+            "skipping {} missed runs of recurring bill {}, posting the last {}",
+            skipped, template.id, MAX_CATCH_UP
+        );
+    }
+
+    let settings = store.guild_settings(template.guild_id)?;
+    let known = rates.rates(template.guild_id)?;
+    for (i, run) in runs.iter().enumerate() {
+        let next_run = runs.get(i + 1).copied().or(next);
+
+        let mut bill = template.bill(*run);
+        bill.rate = rates::rate_to_record(bill.currency, settings.currency, &known);
+
+        // the bill and the template's next run are saved together, so a restart between
+        // them can't post the same run twice
+        match next_run {
+            Some(next_run) => store.insert_recurring_bill(template.id, &mut bill, next_run)?,
+            None => {
+                store.insert_bill(&mut bill)?;
+                store.delete_recurring(template.id)?;
+                warn!(
+                    "recurring bill {} never runs again after {}, removed it",
+                    template.id, run
+                );
+            }
+        }
+        info!(
+            "saved bill {} from recurring bill {} for {}",
+            bill.id, template.id, run
+        );
+
+        if let Err(why) = bill_message::send(ctx, store, &mut bill).await {
+            error!(
+                "failed to post bill {} from recurring bill {}: {:?}",
+                bill.id, template.id, why
+            );
+        }
+    }
+
+    Ok(())
+}
+
+/// The runs of `schedule` from `next_run` up to `now` that still get posted, which are the last
+/// `MAX_CATCH_UP` of them. Also returns how many older runs were skipped and when the schedule
+/// runs after the last one, if it ever does.
+fn due_runs(
+    schedule: &Schedule,
+    next_run: DateTime<Utc>,
+    now: DateTime<Utc>,
+) -> (Vec<DateTime<Utc>>, usize, Option<DateTime<Utc>>) {
+    let mut runs = Vec::new();
+    let mut next = Some(next_run);
+    while let Some(run) = next.filter(|run| *run <= now) {
+        runs.push(run);
+        next = schedule.next_run(run);
+    }
+
+    let skipped = runs.len().saturating_sub(MAX_CATCH_UP);
+    runs.drain(..skipped);
+
+    (runs, skipped, next)
+}
+
+#[cfg(test)]
+mod tests {
+    use chrono::{Duration, NaiveTime, TimeZone};
+
+    use super::*;
+
+    fn every_day() -> Schedule {
+        Schedule::EveryDays {
+            days: 1,
+            at: NaiveTime::from_hms_opt(9, 0, 0).unwrap(),
+        }
+    }
+
+    #[test]
+    fn nothing_is_due_before_the_next_run() {
+        let next_run = Utc.with_ymd_and_hms(2026, 3, 1, 9, 0, 0).unwrap();
+        let (runs, skipped, next) =
+            due_runs(&every_day(), next_run, next_run - Duration::minutes(1));
+
+        assert!(runs.is_empty());
+        assert_eq!(skipped, 0);
+        assert_eq!(next, Some(next_run));
+    }
+
+    #[test]
+    fn every_missed_run_is_posted() {
+        let next_run = Utc.with_ymd_and_hms(2026, 3, 1, 9, 0, 0).unwrap();
+        let (runs, skipped, next) = due_runs(&every_day(), next_run, next_run + Duration::days(2));
+
+        assert_eq!(
+            runs,
+            vec![
+                next_run,
+                next_run + Duration::days(1),
+                next_run + Duration::days(2)
+            ]
+        );
+        assert_eq!(skipped, 0);
+        assert_eq!(next, Some(next_run + Duration::days(3)));
+    }
+
+    #[test]
+    fn only_the_last_runs_are_caught_up_on() {
+        let next_run = Utc.with_ymd_and_hms(2026, 3, 1, 9, 0, 0).unwrap();
+        let now = next_run + Duration::days(99);
+        let (runs, skipped, next) = due_runs(&every_day(), next_run, now);
+
+        assert_eq!(runs.len(), MAX_CATCH_UP);
+        assert_eq!(skipped, 100 - MAX_CATCH_UP);
+        assert_eq!(
+            runs.first(),
+            Some(&(now - Duration::days(MAX_CATCH_UP as i64 - 1)))
+        );
+        assert_eq!(runs.last(), Some(&now));
+        assert_eq!(next, Some(now + Duration::days(1)));
+    }
+}
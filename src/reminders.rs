@@ -0,0 +1,225 @@
+use std::{collections::HashMap, sync::Arc, time::Duration};
+
+use chrono::{DateTime, Duration as DateDuration, Timelike, Utc};
+use log::{error, info, warn};
+use rusty_money::Money;
+use serenity::model::prelude::GuildId;
+use serenity::prelude::{Context, Mentionable};
+use tokio::time::MissedTickBehavior;
+
+use crate::bill::{Bill, BillShare, ShareStatus};
+use crate::bill_message;
+use crate::components::reminder;
+use crate::store::{BillStore, ReminderSettings, ReminderState};
+
+/// How often the reminder engine looks for payers to remind.
+const CHECK_INTERVAL: Duration = Duration::from_secs(10 * 60);
+
+/// Gaps between overdue reminders stop doubling after this many, ex. 2^16 days is plenty.
+const MAX_BACKOFF_STEPS: u32 = 16;
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Reminder {
+    /// The due date is coming up.
+    Upcoming,
+    /// The due date has passed.
+    Overdue,
+}
+
+/// Which reminder, if any, a payer with an owed share should get at `now`.
+pub fn next_reminder(
+    settings: &ReminderSettings,
+    due_at: DateTime<Utc>,
+    state: &ReminderState,
+    now: DateTime<Utc>,
+) -> Option<Reminder> {
+    if now < due_at {
+        if settings.days_before == 0 || state.before_sent {
+            return None;
+        }
+        let remind_at = due_at - DateDuration::days(settings.days_before as i64);
+        return (now >= remind_at).then_some(Reminder::Upcoming);
+    }
+
+    let remind_at = match (state.overdue_sent, state.last_sent_at) {
+        (0, _) | (_, None) => due_at + DateDuration::days(settings.days_after as i64),
+        (sent, Some(last_sent_at)) => {
+            // each reminder waits twice as long as the one before it
+            let base = settings.days_after.max(1) as i64;
+            let gap = base
+                .saturating_mul(1 << sent.min(MAX_BACKOFF_STEPS))
+                .min(settings.max_gap_days.max(1) as i64);
+            last_sent_at + DateDuration::days(gap)
+        }
+    };
+    (now >= remind_at).then_some(Reminder::Overdue)
+}
+
+/// Whether `now` falls in the guild's quiet hours.
+pub fn is_quiet(settings: &ReminderSettings, now: DateTime<Utc>) -> bool {
+    let hour = now.hour();
+    match settings.quiet_hours {
+        Some((start, end)) if start < end => start <= hour && hour < end,
+        Some((start, end)) if start > end => hour >= start || hour < end,
+        _ => false,
+    }
+}
+
+/// Reminds payers of bills with a due date of what they owe, and marks bills overdue in their
+/// messages once the due date passes, forever. What was sent is kept in the store, so restarts
+/// don't send reminders twice.
+pub async fn run(ctx: Context, store: Arc<dyn BillStore>) {
+    let mut interval = tokio::time::interval(CHECK_INTERVAL);
+    interval.set_missed_tick_behavior(MissedTickBehavior::Delay);
+
+    loop {
+        interval.tick().await;
+
+        if let Err(why) = send_due(&ctx, store.as_ref()).await {
+            error!("failed to send reminders: {:?}", why);
+        }
+    }
+}
+
+async fn send_due(ctx: &Context, store: &dyn BillStore) -> anyhow::Result<()> {
+    let now = Utc::now();
+    let mut settings: HashMap<GuildId, ReminderSettings> = HashMap::new();
+
+    for bill in store.due_bills()? {
+        let due_at = match bill.due_at {
+            Some(due_at) => due_at,
+            None => continue,
+        };
+
+        // the bill message only says who's overdue as of when it was last drawn, so it's redrawn
+        // once the due date passes, whether or not anyone gets reminded
+        if bill.overdue_shares(now).next().is_some()
+            && store
+                .overdue_drawn_at(bill.id)?
+                .is_none_or(|drawn_at| drawn_at < due_at)
+        {
+            if let Err(why) = bill_message::update(ctx, &bill).await {
+                warn!(
+                    "failed to update message for overdue bill {}: {:?}",
+                    bill.id, why
+                );
+            }
+            store.set_overdue_drawn_at(bill.id, now)?;
+        }
+
+        let reminders = match settings.get(&bill.guild_id) {
+            Some(reminders) => *reminders,
+            None => {
+                let reminders = store.guild_settings(bill.guild_id)?.reminders;
+                settings.insert(bill.guild_id, reminders);
+                reminders
+            }
+        };
+        if !reminders.enabled || is_quiet(&reminders, now) {
+            continue;
+        }
+
+        for share in bill
+            .payer_shares()
+            .filter(|share| share.status == ShareStatus::Owed)
+        {
+            let state = store.reminder_state(bill.id, share.user_id)?;
+            let reminder = match next_reminder(&reminders, due_at, &state, now) {
+                Some(reminder) => reminder,
+                None => continue,
+            };
+
+            let prefs = store.reminder_prefs(share.user_id)?;
+            if prefs
+                .snoozed_until
+                .is_some_and(|snoozed_until| snoozed_until > now)
+            {
+                continue;
+            }
+
+            if let Err(why) = remind(ctx, &bill, share, reminder, prefs.opted_out).await {
+                warn!(
+                    "failed to remind {} of bill {}: {:?}",
+                    share.user_id, bill.id, why
+                );
+                continue;
+            }
+            store.record_reminder(bill.id, share.user_id, reminder == Reminder::Overdue, now)?;
+            info!(
+                "reminded {} of bill {} ({:?})",
+                share.user_id, bill.id, reminder
+            );
+        }
+    }
+
+    Ok(())
+}
+
+/// Sends a payer a reminder by DM, or in the bill's channel if they opted out of DMs or
+/// can't be sent one.
+async fn remind(
+    ctx: &Context,
+    bill: &Bill,
+    share: &BillShare,
+    reminder: Reminder,
+    opted_out: bool,
+) -> anyhow::Result<()> {
+    let amount = Money::from_decimal(share.amount, bill.currency);
+    let due = bill
+        .due_at
+        .map(|due_at| due_at.timestamp())
+        .unwrap_or_default();
+    let mut content = match reminder {
+        Reminder::Upcoming => format!(
+            "Your {} share of **{}** for {} is due <t:{}:R>",
+            amount, bill.title, bill.creator_name, due
+        ),
+        Reminder::Overdue => format!(
+            "⏰ Your {} share of **{}** for {} was due <t:{}:R> and still isn't paid",
+            amount, bill.title, bill.creator_name, due
+        ),
+    };
+    content.push_str(&format!("\nPayment method: {}", bill.method));
+    // leaves room for the link to the bill or the ping in front
+    let content = bill_message::truncate(&content, bill_message::MAX_CONTENT_LENGTH - 150);
+
+    if !opted_out {
+        let mut dm = content.clone();
+        if let Some(message_id) = bill.message_id {
+            dm.push_str(&format!(
+                "\n[Go to the bill]({})",
+                message_id.link(bill.channel_id, Some(bill.guild_id))
+            ));
+        }
+
+        let dm_result = async {
+            let channel = share.user_id.create_dm_channel(&ctx.http).await?;
+            channel
+                .send_message(&ctx.http, |msg| {
+                    msg.content(&dm)
+                        .components(|cmp| reminder::components(cmp, bill.id, share.user_id))
+                })
+                .await
+        }
+        .await;
+
+        match dm_result {
+            Ok(_) => return Ok(()),
+            Err(why) => warn!(
+                "failed to DM reminder of bill {} to {}, pinging in channel instead: {}",
+                bill.id, share.user_id, why
+            ),
+        }
+    }
+
+    bill.channel_id
+        .send_message(&ctx.http, |msg| {
+            if let Some(message_id) = bill.message_id {
+                msg.reference_message((bill.channel_id, message_id));
+            }
+            msg.content(format!("{} {}", share.user_id.mention(), content))
+        })
+        .await?;
+
+    Ok(())
+}
@@ -0,0 +1,483 @@
+use std::fmt;
+
+use anyhow::bail;
+use chrono::{
+    DateTime, Datelike, Duration, NaiveDate, NaiveTime, TimeZone, Timelike, Utc, Weekday,
+};
+
+/// Cron expressions that can't match are given up on after looking this far ahead.
+const MAX_CRON_SEARCH_DAYS: i64 = 366 * 5;
+
+/// When a recurring bill is posted. Times are in UTC.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub enum Schedule {
+    /// On this day of every month, or on the last day of months too short to have it.
+    Monthly {
+        day: u32,
+        at: NaiveTime,
+    },
+    Weekly {
+        weekday: Weekday,
+        at: NaiveTime,
+    },
+    /// Every this many days, counting from the first run.
+    EveryDays {
+        days: u32,
+        at: NaiveTime,
+    },
+    Cron(Cron),
+}
+
+impl Schedule {
+    /// Parses a schedule as written by people, and as stored: `monthly 1`, `weekly fri`,
+    /// `every 14 days`, each optionally followed by `at 17:30`, or a five field cron
+    /// expression like `0 9 1 * *`. Errors are meant to be shown to the bill creator as is.
+    pub fn parse(input: &str) -> anyhow::Result<Schedule> {
+        let input = input.trim().to_lowercase();
+        let (rule, at) = match input.rsplit_once(" at ") {
+            Some((rule, at)) => (rule.trim(), Some(at.trim())),
+            None => (input.as_str(), None),
+        };
+        let at = match at {
+            Some(at) => match NaiveTime::parse_from_str(at, "%H:%M") {
+                Ok(at) => at,
+                Err(_) => bail!("{} isn't a time, write times like 09:00 or 17:30", at),
+            },
+            None => NaiveTime::from_hms_opt(9, 0, 0).unwrap(),
+        };
+
+        let words: Vec<&str> = rule.split_whitespace().collect();
+        let schedule = match words.as_slice() {
+            ["monthly", day] | ["monthly", "on", "day", day] => match day.parse() {
+                Ok(day) if (1..=31).contains(&day) => Schedule::Monthly { day, at },
+                _ => bail!("{} isn't a day of the month, use 1 to 31", day),
+            },
+            ["weekly", weekday] | ["weekly", "on", weekday] => match weekday.parse() {
+                Ok(weekday) => Schedule::Weekly { weekday, at },
+                Err(_) => bail!("{} isn't a day of the week, use mon to sun", weekday),
+            },
+            ["every", days, "days"] | ["every", days, "day"] => match days.parse() {
+                Ok(days) if days >= 1 => Schedule::EveryDays { days, at },
+                _ => bail!("{} isn't a number of days", days),
+            },
+            fields if fields.len() == 5 => Schedule::Cron(Cron::parse(rule)?),
+            _ => bail!(
+                "Couldn't understand the schedule `{}`, use something like `monthly 1`, `weekly fri`, `every 14 days` or a cron expression like `0 9 1 * *`",
+                input
+            ),
+        };
+
+        if schedule.first_run(Utc::now()).is_none() {
+            bail!("The schedule `{}` never comes up", schedule);
+        }
+        Ok(schedule)
+    }
+
+    /// When a schedule set up at `now` first runs.
+    pub fn first_run(&self, now: DateTime<Utc>) -> Option<DateTime<Utc>> {
+        match self {
+            Schedule::EveryDays { at, .. } => Some(next_time_of_day(now, *at)),
+            _ => self.next_run(now),
+        }
+    }
+
+    /// When the schedule runs next after running at `last`.
+    pub fn next_run(&self, last: DateTime<Utc>) -> Option<DateTime<Utc>> {
+        match self {
+            Schedule::Monthly { day, at } => {
+                let this_month = monthly_run(last.year(), last.month(), *day, *at);
+                if this_month > last {
+                    return Some(this_month);
+                }
+
+                let (year, month) = match last.month() {
+                    12 => (last.year() + 1, 1),
+                    month => (last.year(), month + 1),
+                };
+                Some(monthly_run(year, month, *day, *at))
+            }
+            Schedule::Weekly { weekday, at } => {
+                let days_ahead = (weekday.num_days_from_monday() + 7
+                    - last.weekday().num_days_from_monday())
+                    % 7;
+                let run = Utc.from_utc_datetime(
+                    &(last.date_naive() + Duration::days(days_ahead as i64)).and_time(*at),
+                );
+                if run > last {
+                    Some(run)
+                } else {
+                    Some(run + Duration::days(7))
+                }
+            }
+            Schedule::EveryDays { days, at } => Some(Utc.from_utc_datetime(
+                &(last.date_naive() + Duration::days(*days as i64)).and_time(*at),
+            )),
+            Schedule::Cron(cron) => cron.next_after(last),
+        }
+    }
+}
+
+impl fmt::Display for Schedule {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Schedule::Monthly { day, at } => write!(f, "monthly {} at {}", day, at.format("%H:%M")),
+            Schedule::Weekly { weekday, at } => {
+                write!(
+                    f,
+                    "weekly {} at {}",
+                    weekday.to_string().to_lowercase(),
+                    at.format("%H:%M")
+                )
+            }
+            Schedule::EveryDays { days, at } => {
+                write!(f, "every {} days at {}", days, at.format("%H:%M"))
+            }
+            Schedule::Cron(cron) => write!(f, "{}", cron.expr),
+        }
+    }
+}
+
+/// The next time the clock shows `at` after `now`.
+fn next_time_of_day(now: DateTime<Utc>, at: NaiveTime) -> DateTime<Utc> {
+    let today = Utc.from_utc_datetime(&now.date_naive().and_time(at));
+    if today > now {
+        today
+    } else {
+        today + Duration::days(1)
+    }
+}
+
+fn monthly_run(year: i32, month: u32, day: u32, at: NaiveTime) -> DateTime<Utc> {
+    // every month has a 28th, so one of these days exists
+    let date = (day.min(28)..=day)
+        .rev()
+        .find_map(|d| NaiveDate::from_ymd_opt(year, month, d))
+        .unwrap();
+    Utc.from_utc_datetime(&date.and_time(at))
+}
+
+/// A five field cron expression: minute, hour, day of the month, month and day of the week.
+/// Fields can be `*`, numbers, ranges like `1-5`, steps like `*/15` and lists of those. Like
+/// cron, a bill runs on days matching either of the day fields when both are restricted.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct Cron {
+    expr: String,
+    minutes: u64,
+    hours: u64,
+    days: u64,
+    months: u64,
+    weekdays: u64,
+    any_day: bool,
+    any_weekday: bool,
+}
+
+impl Cron {
+    fn parse(expr: &str) -> anyhow::Result<Cron> {
+        let fields: Vec<&str> = expr.split_whitespace().collect();
+        if fields.len() != 5 {
+            bail!("Cron expressions have five fields: minute, hour, day, month and weekday");
+        }
+
+        // 7 is sunday as well as 0
+        let mut weekdays = parse_field(fields[4], 0, 7, "weekday")?;
+        if weekdays & (1 << 7) != 0 {
+            weekdays |= 1;
+        }
+
+        Ok(Cron {
+            expr: fields.join(" "),
+            minutes: parse_field(fields[0], 0, 59, "minute")?,
+            hours: parse_field(fields[1], 0, 23, "hour")?,
+            days: parse_field(fields[2], 1, 31, "day")?,
+            months: parse_field(fields[3], 1, 12, "month")?,
+            weekdays,
+            any_day: fields[2] == "*",
+            any_weekday: fields[4] == "*",
+        })
+    }
+
+    fn matches_day(&self, date: NaiveDate) -> bool {
+        if self.months & (1 << date.month()) == 0 {
+            return false;
+        }
+
+        let day = self.days & (1 << date.day()) != 0;
+        let weekday = self.weekdays & (1 << date.weekday().num_days_from_sunday()) != 0;
+        match (self.any_day, self.any_weekday) {
+            (true, true) => true,
+            (false, true) => day,
+            (true, false) => weekday,
+            (false, false) => day || weekday,
+        }
+    }
+
+    /// The first minute after `last` the expression matches.
+    fn next_after(&self, last: DateTime<Utc>) -> Option<DateTime<Utc>> {
+        let start = last.with_second(0)?.with_nanosecond(0)? + Duration::minutes(1);
+        let mut date = start.date_naive();
+        let end = date + Duration::days(MAX_CRON_SEARCH_DAYS);
+
+        while date < end {
+            if self.matches_day(date) {
+                for hour in 0..24 {
+                    if self.hours & (1 << hour) == 0 {
+                        continue;
+                    }
+                    for minute in 0..60 {
+                        if self.minutes & (1 << minute) == 0 {
+                            continue;
+                        }
+                        let run = Utc.from_utc_datetime(
+                            &date.and_time(NaiveTime::from_hms_opt(hour, minute, 0)?),
+                        );
+                        if run >= start {
+                            return Some(run);
+                        }
+                    }
+                }
+            }
+            date += Duration::days(1);
+        }
+
+        None
+    }
+}
+
+/// Parses one cron field into a bit set of the values it matches.
+fn parse_field(field: &str, min: u32, max: u32, name: &str) -> anyhow::Result<u64> {
+    let mut set = 0u64;
+
+    for part in field.split(',') {
+        let (range, step) = match part.split_once('/') {
+            Some((range, step)) => match step.parse::<u32>() {
+                Ok(step) if step >= 1 => (range, step),
+                _ => bail!("`{}` isn't a valid step for the {} field", step, name),
+            },
+            None => (part, 1),
+        };
+
+        let (start, end) = match range {
+            "*" => (min, max),
+            range => {
+                let parse = |value: &str| match value.parse::<u32>() {
+                    Ok(value) if (min..=max).contains(&value) => Ok(value),
+                    _ => Err(anyhow::anyhow!(
+                        "`{}` isn't a valid {}, use {} to {}",
+                        value,
+                        name,
+                        min,
+                        max
+                    )),
+                };
+                match range.split_once('-') {
+                    Some((start, end)) => (parse(start)?, parse(end)?),
+                    // a single value with a step runs from that value to the end
+                    None if step > 1 => (parse(range)?, max),
+                    None => {
+                        let value = parse(range)?;
+                        (value, value)
+                    }
+                }
+            }
+        };
+        if start > end {
+            bail!("`{}` is backwards in the {} field", range, name);
+        }
+
+        for value in (start..=end).step_by(step as usize) {
+            set |= 1 << value;
+        }
+    }
+
+    Ok(set)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn at(y: i32, m: u32, d: u32, h: u32, min: u32) -> DateTime<Utc> {
+        Utc.with_ymd_and_hms(y, m, d, h, min, 0).unwrap()
+    }
+
+    fn time(h: u32, m: u32) -> NaiveTime {
+        NaiveTime::from_hms_opt(h, m, 0).unwrap()
+    }
+
+    #[test]
+    fn parses_schedules() {
+        assert_eq!(
+            Schedule::parse("monthly 1").unwrap(),
+            Schedule::Monthly {
+                day: 1,
+                at: time(9, 0)
+            }
+        );
+        assert_eq!(
+            Schedule::parse(" Monthly on day 15 at 17:30 ").unwrap(),
+            Schedule::Monthly {
+                day: 15,
+                at: time(17, 30)
+            }
+        );
+        assert_eq!(
+            Schedule::parse("weekly on Friday").unwrap(),
+            Schedule::Weekly {
+                weekday: Weekday::Fri,
+                at: time(9, 0)
+            }
+        );
+        assert_eq!(
+            Schedule::parse("every 14 days at 08:15").unwrap(),
+            Schedule::EveryDays {
+                days: 14,
+                at: time(8, 15)
+            }
+        );
+        assert!(matches!(
+            Schedule::parse("0 9 1 * *").unwrap(),
+            Schedule::Cron(_)
+        ));
+    }
+
+    #[test]
+    fn schedules_parse_back_from_how_they_are_stored() {
+        for input in [
+            "monthly 31 at 23:59",
+            "weekly sun",
+            "every 1 day",
+            "*/15 9-17 * * 1-5",
+        ] {
+            let schedule = Schedule::parse(input).unwrap();
+            assert_eq!(Schedule::parse(&schedule.to_string()).unwrap(), schedule);
+        }
+    }
+
+    #[test]
+    fn rejects_bad_schedules() {
+        for input in [
+            "monthly 0",
+            "monthly 32",
+            "weekly funday",
+            "every 0 days",
+            "every -1 days",
+            "monthly 1 at 25:00",
+            "monthly 1 at noon",
+            "fortnightly",
+            "60 * * * *",
+            "* 24 * * *",
+            "* * 0 * *",
+            "* * * 13 *",
+            "* * * * 8",
+            "5-1 * * * *",
+            "*/0 * * * *",
+            // february never has a 30th
+            "0 9 30 2 *",
+        ] {
+            assert!(Schedule::parse(input).is_err(), "{}", input);
+        }
+    }
+
+    #[test]
+    fn monthly_runs_fall_back_to_the_last_day_of_short_months() {
+        let schedule = Schedule::parse("monthly 31").unwrap();
+
+        let feb = schedule.next_run(at(2026, 1, 31, 9, 0)).unwrap();
+        assert_eq!(feb, at(2026, 2, 28, 9, 0));
+        assert_eq!(schedule.next_run(feb).unwrap(), at(2026, 3, 31, 9, 0));
+        assert_eq!(
+            schedule.next_run(at(2028, 1, 31, 9, 0)).unwrap(),
+            at(2028, 2, 29, 9, 0)
+        );
+        assert_eq!(
+            schedule.next_run(at(2026, 12, 31, 9, 0)).unwrap(),
+            at(2027, 1, 31, 9, 0)
+        );
+    }
+
+    #[test]
+    fn monthly_first_runs_are_this_month_if_still_ahead() {
+        let schedule = Schedule::parse("monthly 1").unwrap();
+
+        assert_eq!(
+            schedule.first_run(at(2026, 3, 1, 8, 0)).unwrap(),
+            at(2026, 3, 1, 9, 0)
+        );
+        assert_eq!(
+            schedule.first_run(at(2026, 3, 1, 9, 0)).unwrap(),
+            at(2026, 4, 1, 9, 0)
+        );
+    }
+
+    #[test]
+    fn weekly_runs_land_on_the_weekday() {
+        // 2026-03-04 is a wednesday
+        let friday = Schedule::parse("weekly fri").unwrap();
+        assert_eq!(
+            friday.first_run(at(2026, 3, 4, 10, 0)).unwrap(),
+            at(2026, 3, 6, 9, 0)
+        );
+
+        let wednesday = Schedule::parse("weekly wed").unwrap();
+        assert_eq!(
+            wednesday.first_run(at(2026, 3, 4, 8, 0)).unwrap(),
+            at(2026, 3, 4, 9, 0)
+        );
+        assert_eq!(
+            wednesday.next_run(at(2026, 3, 4, 9, 0)).unwrap(),
+            at(2026, 3, 11, 9, 0)
+        );
+    }
+
+    #[test]
+    fn every_n_days_counts_from_the_first_run() {
+        let schedule = Schedule::parse("every 14 days at 17:00").unwrap();
+
+        let first = schedule.first_run(at(2026, 3, 4, 18, 0)).unwrap();
+        assert_eq!(first, at(2026, 3, 5, 17, 0));
+        assert_eq!(schedule.next_run(first).unwrap(), at(2026, 3, 19, 17, 0));
+    }
+
+    #[test]
+    fn cron_runs_on_matching_minutes() {
+        let weekdays = Schedule::parse("*/15 9-17 * * 1-5").unwrap();
+        assert_eq!(
+            weekdays.next_run(at(2026, 3, 4, 10, 7)).unwrap(),
+            at(2026, 3, 4, 10, 15)
+        );
+        // friday evening skips to monday morning
+        assert_eq!(
+            weekdays.next_run(at(2026, 3, 6, 17, 45)).unwrap(),
+            at(2026, 3, 9, 9, 0)
+        );
+
+        let first_of_month = Schedule::parse("0 9 1 * *").unwrap();
+        assert_eq!(
+            first_of_month.next_run(at(2026, 3, 1, 9, 0)).unwrap(),
+            at(2026, 4, 1, 9, 0)
+        );
+    }
+
+    #[test]
+    fn cron_days_match_either_day_field() {
+        // the 10th or any friday
+        let schedule = Schedule::parse("0 9 10 * 5").unwrap();
+        assert_eq!(
+            schedule.next_run(at(2026, 3, 6, 9, 0)).unwrap(),
+            at(2026, 3, 10, 9, 0)
+        );
+        assert_eq!(
+            schedule.next_run(at(2026, 3, 10, 9, 0)).unwrap(),
+            at(2026, 3, 13, 9, 0)
+        );
+    }
+
+    #[test]
+    fn cron_weekday_7_is_sunday() {
+        let schedule = Schedule::parse("30 8 * * 7").unwrap();
+        assert_eq!(
+            schedule.next_run(at(2026, 3, 4, 9, 0)).unwrap(),
+            at(2026, 3, 8, 8, 30)
+        );
+    }
+}
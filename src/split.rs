@@ -0,0 +1,591 @@
+use std::str::FromStr;
+
+use anyhow::{bail, Context};
+use rust_decimal::{Decimal, RoundingStrategy};
+use rusty_money::{iso::Currency, Formatter, LocalFormat, Money, MoneyError, Params, Position};
+
+/// The largest weight a payer can be given. Weights only matter relative to each other, and
+/// capping them keeps splitting from overflowing.
+const MAX_WEIGHT: Decimal = Decimal::from_parts(1_000_000, 0, 0, false, 0);
+
+/// How much of a bill a single payer covers, as typed into the payers field.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Portion {
+    /// A share relative to the other weighted payers, payers listed on their own weigh 1.
+    Weight(Decimal),
+    /// A percentage of whatever isn't covered by fixed amounts.
+    Percent(Decimal),
+    /// An exact amount in the bill's currency.
+    Fixed(Decimal),
+}
+
+#[derive(Clone, Debug)]
+pub struct PayerSpec {
+    pub name: String,
+    pub portion: Portion,
+}
+
+/// Parses a comma separated payers field. Each entry is a name, optionally followed by a weight
+/// (`Jacob:2`), a percentage (`Jacob 60%`) or a fixed amount (`Jacob $50`). An entry of `rest`
+/// or `everyone else` stands for everyone in the channel who isn't otherwise on the bill, as in
+/// `Jacob $50, rest split evenly`. Errors are meant to be shown to the bill creator as is.
+pub fn parse_payers(input: &str, currency: &'static Currency) -> anyhow::Result<Vec<PayerSpec>> {
+    let mut specs = Vec::new();
+
+    for entry in input.split(',').map(str::trim).filter(|e| !e.is_empty()) {
+        let (name, portion) = match entry.rsplit_once(':') {
+            Some((name, portion)) => (name.trim(), Some(portion.trim())),
+            None => match entry.rsplit_once(char::is_whitespace) {
+                Some((name, portion)) if looks_like_portion(portion, currency) => {
+                    (name.trim(), Some(portion))
+                }
+                _ => (entry, None),
+            },
+        };
+
+        let portion = match portion {
+            Some(portion) => parse_portion(name, portion, currency)?,
+            None => Portion::Weight(Decimal::ONE),
+        };
+
+        let name = if is_rest(name) { REST } else { name };
+        if name == REST && specs.iter().any(|spec: &PayerSpec| spec.name == REST) {
+            bail!("`rest` can only be listed once");
+        }
+
+        specs.push(PayerSpec {
+            name: name.to_owned(),
+            portion,
+        });
+    }
+
+    Ok(specs)
+}
+
+/// The name of the entry that stands for everyone in the channel who isn't otherwise listed.
+pub const REST: &str = "rest";
+
+/// Whether an entry's name stands for the rest of the channel rather than a member or role.
+pub fn is_rest(name: &str) -> bool {
+    let name = name.trim().to_lowercase();
+    let name = name
+        .strip_suffix("split evenly")
+        .map(str::trim_end)
+        .unwrap_or(&name);
+    name == REST || name.split_whitespace().eq(["everyone", "else"])
+}
+
+fn looks_like_portion(s: &str, currency: &'static Currency) -> bool {
+    s.ends_with('%') || s.starts_with('$') || s.starts_with(currency.symbol)
+}
+
+fn parse_portion(name: &str, s: &str, currency: &'static Currency) -> anyhow::Result<Portion> {
+    let portion = if let Some(percent) = s.strip_suffix('%') {
+        Decimal::from_str(percent.trim())
+            .ok()
+            .filter(|p| *p > Decimal::ZERO && *p <= Decimal::ONE_HUNDRED)
+            .map(Portion::Percent)
+    } else if let Some(amount) = s
+        .strip_prefix(currency.symbol)
+        .or_else(|| s.strip_prefix('$'))
+    {
+        parse_money(amount.trim(), currency)
+            .ok()
+            .filter(|m| m.is_positive() && is_whole_minor_units(*m.amount(), currency))
+            .map(|m| Portion::Fixed(*m.amount()))
+    } else {
+        Decimal::from_str(s)
+            .ok()
+            .filter(|w| *w > Decimal::ZERO && *w <= MAX_WEIGHT)
+            .map(Portion::Weight)
+    };
+
+    match portion {
+        Some(portion) => Ok(portion),
+        None => bail!(
+            "Couldn't understand `{}` for {}, use a weight like `{}:2` up to {}, a percentage like `{} 60%` or an amount like `{} {}50`",
+            s, name, name, MAX_WEIGHT, name, name, currency.symbol
+        ),
+    }
+}
+
+/// Parses an amount written the way the currency's locale writes it, ex. `1,234.50` for CAD or
+/// `1.234,50` for EUR. rusty_money's own parser panics on some malformed amounts like `1.-5`, so
+/// they're checked with Decimal first.
+pub fn parse_money(
+    s: &str,
+    currency: &'static Currency,
+) -> Result<Money<'static, Currency>, MoneyError> {
+    let format = LocalFormat::from_locale(currency.locale);
+    let plain: String = s
+        .chars()
+        .filter(|c| *c != format.digit_separator)
+        .map(|c| {
+            if c == format.exponent_separator {
+                '.'
+            } else {
+                c
+            }
+        })
+        .collect();
+    if Decimal::from_str(&plain).is_err() {
+        return Err(MoneyError::InvalidAmount);
+    }
+
+    Money::from_str(s, currency)
+}
+
+/// Writes an amount the way `parse_money` reads it back, in the currency's locale and without
+/// its symbol, ex. `1.234,50` for EUR.
+pub fn format_money(amount: Decimal, currency: &'static Currency) -> String {
+    let format = LocalFormat::from_locale(currency.locale);
+    let params = Params {
+        digit_separator: format.digit_separator,
+        exponent_separator: format.exponent_separator,
+        separator_pattern: format.digit_separator_pattern(),
+        positions: vec![Position::Sign, Position::Amount],
+        rounding: Some(currency.exponent),
+        ..Default::default()
+    };
+
+    Formatter::money(&Money::from_decimal(amount, currency), params)
+}
+
+const TOO_LARGE: &str = "The amounts are too large to split, try smaller weights or amounts";
+
+/// Splits `total` between payers according to their portions, returning each payer's amount in
+/// the same order. Fixed amounts come off the top, then the rest is divided by percentage or
+/// weight and rounded down to the currency's minor unit. The cents lost to rounding go one at a
+/// time to the payers who lost the most, earlier payers first on ties, so the amounts always add
+/// back up to `total` exactly. Errors are meant to be shown to the bill creator as is.
+pub fn split(
+    total: Decimal,
+    currency: &'static Currency,
+    portions: &[Portion],
+) -> anyhow::Result<Vec<Decimal>> {
+    let mut fixed = Decimal::ZERO;
+    let mut percent = Decimal::ZERO;
+    let mut weight = Decimal::ZERO;
+
+    for portion in portions {
+        let (sum, value) = match portion {
+            Portion::Fixed(amount) => (&mut fixed, amount),
+            Portion::Percent(p) => (&mut percent, p),
+            Portion::Weight(w) => (&mut weight, w),
+        };
+        *sum = sum.checked_add(*value).context(TOO_LARGE)?;
+    }
+
+    if total <= Decimal::ZERO {
+        bail!("The bill total needs to be more than zero");
+    }
+
+    if !is_whole_minor_units(total, currency) {
+        bail!(
+            "{} can't be split exactly, amounts can have at most {} decimal places",
+            total,
+            currency.exponent
+        );
+    }
+
+    if fixed > total {
+        bail!(
+            "Fixed amounts add up to {}, which is more than the bill total of {}",
+            Money::from_decimal(fixed, currency),
+            Money::from_decimal(total, currency)
+        );
+    }
+
+    if !percent.is_zero() && !weight.is_zero() {
+        bail!("Either give every payer without a fixed amount a percentage, or none of them");
+    }
+
+    if !percent.is_zero() && percent != Decimal::ONE_HUNDRED {
+        bail!(
+            "Percentages add up to {}%, they need to add up to 100%",
+            percent.normalize()
+        );
+    }
+
+    let rest = total - fixed;
+    if percent.is_zero() && weight.is_zero() && !rest.is_zero() {
+        bail!(
+            "Fixed amounts add up to {} but the bill total is {}, add another payer to cover the rest",
+            Money::from_decimal(fixed, currency),
+            Money::from_decimal(total, currency)
+        );
+    }
+
+    let exact: Vec<Decimal> = portions
+        .iter()
+        .map(|portion| match portion {
+            Portion::Fixed(amount) => Some(*amount),
+            Portion::Percent(p) => rest
+                .checked_mul(*p)
+                .and_then(|amount| amount.checked_div(Decimal::ONE_HUNDRED)),
+            Portion::Weight(w) => rest
+                .checked_mul(*w)
+                .and_then(|amount| amount.checked_div(weight)),
+        })
+        .collect::<Option<_>>()
+        .context(TOO_LARGE)?;
+
+    let mut amounts: Vec<Decimal> = exact
+        .iter()
+        .map(|amount| amount.round_dp_with_strategy(currency.exponent, RoundingStrategy::ToZero))
+        .collect();
+
+    let mut by_loss: Vec<usize> = (0..portions.len())
+        .filter(|i| !matches!(portions[*i], Portion::Fixed(_)))
+        .collect();
+    by_loss.sort_by(|a, b| {
+        (exact[*b] - amounts[*b])
+            .cmp(&(exact[*a] - amounts[*a]))
+            .then(a.cmp(b))
+    });
+
+    let unit = minor_unit(currency);
+    let mut leftover = total - amounts.iter().sum::<Decimal>();
+    for i in by_loss.into_iter().cycle() {
+        if leftover < unit {
+            break;
+        }
+        amounts[i] += unit;
+        leftover -= unit;
+    }
+
+    for amount in &mut amounts {
+        amount.rescale(currency.exponent);
+    }
+
+    Ok(amounts)
+}
+
+/// Whether `amount` can be paid exactly, without fractions of the currency's minor unit.
+pub fn is_whole_minor_units(amount: Decimal, currency: &'static Currency) -> bool {
+    amount.normalize().scale() <= currency.exponent
+}
+
+fn minor_unit(currency: &'static Currency) -> Decimal {
+    Decimal::new(1, currency.exponent)
+}
+
+#[cfg(test)]
+mod tests {
+    use proptest::prelude::*;
+    use rusty_money::iso;
+
+    use super::*;
+
+    fn cents(amount: i64) -> Decimal {
+        Decimal::new(amount, 2)
+    }
+
+    #[test]
+    fn even_split_gives_leftover_cents_to_earlier_payers() {
+        let portions = vec![Portion::Weight(Decimal::ONE); 3];
+        let amounts = split(cents(10000), iso::CAD, &portions).unwrap();
+
+        assert_eq!(amounts, vec![cents(3334), cents(3333), cents(3333)]);
+    }
+
+    #[test]
+    fn leftover_cents_go_to_the_largest_rounding_loss() {
+        // exact shares are 16.666.., 33.333.. and 50
+        let portions = vec![
+            Portion::Weight(Decimal::ONE),
+            Portion::Weight(Decimal::TWO),
+            Portion::Weight(Decimal::from(3)),
+        ];
+        let amounts = split(cents(10000), iso::CAD, &portions).unwrap();
+
+        assert_eq!(amounts, vec![cents(1667), cents(3333), cents(5000)]);
+    }
+
+    #[test]
+    fn amounts_are_scaled_to_minor_units() {
+        let portions = vec![Portion::Weight(Decimal::ONE); 2];
+        let amounts = split(Decimal::from(100), iso::CAD, &portions).unwrap();
+
+        assert!(amounts.iter().all(|amount| amount.scale() == 2));
+        assert_eq!(amounts[0].to_string(), "50.00");
+    }
+
+    #[test]
+    fn rejects_fractions_of_a_cent() {
+        let portions = vec![Portion::Weight(Decimal::ONE)];
+
+        assert!(split(Decimal::new(10001, 3), iso::CAD, &portions).is_err());
+    }
+
+    fn parsed(input: &str) -> Vec<(String, Portion)> {
+        parse_payers(input, iso::CAD)
+            .unwrap()
+            .into_iter()
+            .map(|spec| (spec.name, spec.portion))
+            .collect()
+    }
+
+    fn payer(name: &str, portion: Portion) -> (String, Portion) {
+        (name.to_owned(), portion)
+    }
+
+    #[test]
+    fn payers_weigh_one_by_default() {
+        assert_eq!(
+            parsed(" Jacob ,Joel Smith,, "),
+            vec![
+                payer("Jacob", Portion::Weight(Decimal::ONE)),
+                payer("Joel Smith", Portion::Weight(Decimal::ONE)),
+            ]
+        );
+    }
+
+    #[test]
+    fn parses_weights_percentages_and_amounts() {
+        assert_eq!(
+            parsed("Jacob:2, Joel : 0.5, Sam 60%, Alex:40%, Kim $12.50, Lee:$3"),
+            vec![
+                payer("Jacob", Portion::Weight(Decimal::TWO)),
+                payer("Joel", Portion::Weight(Decimal::new(5, 1))),
+                payer("Sam", Portion::Percent(Decimal::from(60))),
+                payer("Alex", Portion::Percent(Decimal::from(40))),
+                payer("Kim", Portion::Fixed(cents(1250))),
+                payer("Lee", Portion::Fixed(cents(300))),
+            ]
+        );
+    }
+
+    #[test]
+    fn only_portion_like_words_are_split_off_names() {
+        // a trailing number without `:` is part of the name, as in a username like `Joel 2`
+        assert_eq!(
+            parsed("Joel 2, Jacob Michels"),
+            vec![
+                payer("Joel 2", Portion::Weight(Decimal::ONE)),
+                payer("Jacob Michels", Portion::Weight(Decimal::ONE)),
+            ]
+        );
+    }
+
+    #[test]
+    fn parses_the_rest_of_the_channel() {
+        for input in [
+            "Jacob $50, rest",
+            "Jacob $50, Rest split evenly",
+            "Jacob $50, everyone else",
+        ] {
+            assert_eq!(
+                parsed(input),
+                vec![
+                    payer("Jacob", Portion::Fixed(cents(5000))),
+                    payer(REST, Portion::Weight(Decimal::ONE)),
+                ],
+                "{}",
+                input
+            );
+        }
+
+        assert!(parse_payers("rest, everyone else", iso::CAD).is_err());
+    }
+
+    #[test]
+    fn rejects_bad_portions() {
+        for input in [
+            "Jacob:",
+            "Jacob:abc",
+            "Jacob:0",
+            "Jacob:-1",
+            "Jacob 0%",
+            "Jacob 101%",
+            "Jacob $0",
+            "Jacob $1.234",
+            "Jacob $abc",
+            "Jacob $1.-5",
+            "Jacob $-",
+            "Jacob:1000001",
+            "Jacob:79228162514264337593543950335, Joel",
+        ] {
+            assert!(parse_payers(input, iso::CAD).is_err(), "{}", input);
+        }
+    }
+
+    #[test]
+    fn parses_money_in_the_currency_locale() {
+        assert_eq!(
+            *parse_money("1,234.50", iso::CAD).unwrap().amount(),
+            cents(123450)
+        );
+        assert_eq!(
+            *parse_money("1.234,50", iso::EUR).unwrap().amount(),
+            cents(123450)
+        );
+        assert_eq!(
+            *parse_money("12,50", iso::EUR).unwrap().amount(),
+            cents(1250)
+        );
+        assert!(parse_money("12.50", iso::EUR).is_err());
+
+        for input in ["1.-5", "1.+5", "-", ".", "", "1..5", "1,2.5"] {
+            assert!(parse_money(input, iso::CAD).is_err(), "{}", input);
+        }
+    }
+
+    #[test]
+    fn overflowing_portions_are_rejected() {
+        let huge = Decimal::MAX;
+        for portions in [
+            vec![Portion::Weight(huge), Portion::Weight(huge)],
+            vec![Portion::Fixed(huge), Portion::Fixed(huge)],
+            vec![Portion::Weight(huge), Portion::Weight(Decimal::ONE)],
+        ] {
+            assert!(split(cents(10000), iso::CAD, &portions).is_err());
+        }
+    }
+
+    #[test]
+    fn percentages_have_to_add_up_to_100() {
+        let portions = vec![
+            Portion::Percent(Decimal::from(60)),
+            Portion::Percent(Decimal::from(30)),
+        ];
+        assert!(split(cents(10000), iso::CAD, &portions).is_err());
+
+        let portions = vec![
+            Portion::Percent(Decimal::from(60)),
+            Portion::Percent(Decimal::from(40)),
+        ];
+        assert_eq!(
+            split(cents(10000), iso::CAD, &portions).unwrap(),
+            vec![cents(6000), cents(4000)]
+        );
+    }
+
+    #[test]
+    fn percentages_and_weights_cannot_be_mixed() {
+        let portions = vec![
+            Portion::Percent(Decimal::from(100)),
+            Portion::Weight(Decimal::ONE),
+        ];
+        assert!(split(cents(10000), iso::CAD, &portions).is_err());
+    }
+
+    #[test]
+    fn fixed_amounts_cannot_exceed_the_total() {
+        let portions = vec![Portion::Fixed(cents(6000)), Portion::Fixed(cents(5000))];
+        assert!(split(cents(10000), iso::CAD, &portions).is_err());
+
+        // and when everyone has a fixed amount, they have to cover the whole total
+        let portions = vec![Portion::Fixed(cents(6000)), Portion::Fixed(cents(3000))];
+        assert!(split(cents(10000), iso::CAD, &portions).is_err());
+    }
+
+    #[test]
+    fn the_rest_is_split_after_fixed_amounts() {
+        let portions = vec![
+            Portion::Fixed(cents(5000)),
+            Portion::Weight(Decimal::ONE),
+            Portion::Weight(Decimal::ONE),
+        ];
+        assert_eq!(
+            split(cents(10000), iso::CAD, &portions).unwrap(),
+            vec![cents(5000), cents(2500), cents(2500)]
+        );
+    }
+
+    fn portions() -> impl Strategy<Value = Vec<Portion>> {
+        prop_oneof![
+            prop::collection::vec(1..10i64, 1..20)
+                .prop_map(|ws| ws.into_iter().map(|w| Portion::Weight(w.into())).collect()),
+            prop::collection::vec(1..100i64, 1..20).prop_map(|ps| {
+                // scale arbitrary positive numbers into percentages that add up to exactly 100
+                let total: i64 = ps.iter().sum();
+                let mut percents: Vec<Decimal> = ps
+                    .iter()
+                    .map(|p| (Decimal::from(*p * 100) / Decimal::from(total)).round_dp(4))
+                    .collect();
+                let drift = Decimal::ONE_HUNDRED - percents.iter().sum::<Decimal>();
+                percents[0] += drift;
+                percents.into_iter().map(Portion::Percent).collect()
+            }),
+        ]
+    }
+
+    proptest! {
+        #[test]
+        fn parsing_never_panics(
+            input in "([a-z ]{1,6}(:[0-9.,+-]{1,32}| [$€][0-9.,+-]{1,12}| [0-9.,+-]{1,6}%)?, ){1,4}",
+        ) {
+            for currency in [iso::CAD, iso::EUR, iso::JPY] {
+                if let Ok(specs) = parse_payers(&input, currency) {
+                    let portions: Vec<Portion> = specs.iter().map(|spec| spec.portion).collect();
+                    let _ = split(cents(10000), currency, &portions);
+                }
+            }
+        }
+
+        #[test]
+        fn parsing_money_never_panics(input in "[0-9.,+-]{0,12}") {
+            for currency in [iso::CAD, iso::EUR, iso::JPY] {
+                let _ = parse_money(&input, currency);
+            }
+        }
+
+        #[test]
+        fn formatted_money_parses_back(minor in 0..2_000_000_000i64) {
+            for currency in [iso::CAD, iso::EUR, iso::JPY] {
+                let amount = Decimal::new(minor, currency.exponent);
+                let parsed = parse_money(&format_money(amount, currency), currency).unwrap();
+                prop_assert_eq!(*parsed.amount(), amount);
+            }
+        }
+
+        #[test]
+        fn shares_add_up_to_total(total in 1..1_000_000_000i64, portions in portions()) {
+            let total = cents(total);
+            let amounts = split(total, iso::CAD, &portions).unwrap();
+
+            prop_assert_eq!(amounts.len(), portions.len());
+            prop_assert_eq!(amounts.iter().sum::<Decimal>(), total);
+            for amount in &amounts {
+                prop_assert!(is_whole_minor_units(*amount, iso::CAD));
+                prop_assert!(*amount >= Decimal::ZERO);
+            }
+        }
+
+        #[test]
+        fn even_shares_differ_by_at_most_a_cent(total in 1..1_000_000_000i64, payers in 1..20usize) {
+            let portions = vec![Portion::Weight(Decimal::ONE); payers];
+            let amounts = split(cents(total), iso::CAD, &portions).unwrap();
+
+            let max = amounts.iter().max().unwrap();
+            let min = amounts.iter().min().unwrap();
+            prop_assert!(*max - *min <= cents(1));
+        }
+
+        #[test]
+        fn fixed_amounts_are_kept_and_the_rest_adds_up(
+            total in 1000..1_000_000_000i64,
+            fixed in 1..1000i64,
+            payers in 1..20usize,
+        ) {
+            let mut portions = vec![Portion::Fixed(cents(fixed))];
+            portions.extend(vec![Portion::Weight(Decimal::ONE); payers]);
+            let amounts = split(cents(total), iso::CAD, &portions).unwrap();
+
+            prop_assert_eq!(amounts[0], cents(fixed));
+            prop_assert_eq!(amounts.iter().sum::<Decimal>(), cents(total));
+        }
+
+        #[test]
+        fn zero_exponent_currencies_split_into_whole_units(total in 1..100_000_000i64, payers in 1..20usize) {
+            let portions = vec![Portion::Weight(Decimal::ONE); payers];
+            let amounts = split(Decimal::from(total), iso::JPY, &portions).unwrap();
+
+            prop_assert_eq!(amounts.iter().sum::<Decimal>(), Decimal::from(total));
+            for amount in &amounts {
+                prop_assert_eq!(amount.scale(), 0);
+            }
+        }
+    }
+}
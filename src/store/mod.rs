@@ -0,0 +1,232 @@
+use chrono::{DateTime, Utc};
+use rust_decimal::Decimal;
+use rusty_money::iso::{self, Currency};
+use serenity::model::prelude::{GuildId, MessageId, UserId};
+
+use crate::bill::{Bill, BillStatus, Cancellation, ShareStatus};
+use crate::recurring::RecurringBill;
+
+mod sqlite;
+
+pub use sqlite::SqliteStore;
+
+/// A snapshot of the shares a settle plan covers, taken when the plan was posted.
+#[derive(Clone, Debug)]
+pub struct SettlePlan {
+    pub id: i64,
+    pub guild_id: GuildId,
+    pub created_at: DateTime<Utc>,
+    pub settled_by: Option<UserId>,
+    pub shares: Vec<PlanShare>,
+}
+
+#[derive(Clone, Debug)]
+pub struct PlanShare {
+    pub bill_id: i64,
+    pub user_id: UserId,
+    pub amount: Decimal,
+    /// Set once the bill's creator marked what they're owed in the plan as received.
+    pub settled_at: Option<DateTime<Utc>>,
+}
+
+/// Narrows down which bills are listed, every field that's set has to match.
+#[derive(Clone, Debug, Default)]
+pub struct BillFilter {
+    pub status: Option<BillStatus>,
+    pub creator: Option<UserId>,
+    /// Someone with a share of the bill, other than its creator.
+    pub payer: Option<UserId>,
+    /// Bills created at or after this time.
+    pub from: Option<DateTime<Utc>>,
+    /// Bills created before this time.
+    pub until: Option<DateTime<Utc>>,
+    /// Text the title contains, ignoring case.
+    pub title: Option<String>,
+}
+
+/// Per-guild configuration, guilds that haven't configured anything get the defaults.
+#[derive(Clone, Debug)]
+pub struct GuildSettings {
+    /// The currency new bills are in when the creator doesn't pick one.
+    pub currency: &'static Currency,
+    pub reminders: ReminderSettings,
+}
+
+impl Default for GuildSettings {
+    fn default() -> Self {
+        GuildSettings {
+            currency: iso::CAD,
+            reminders: ReminderSettings::default(),
+        }
+    }
+}
+
+/// When payers of bills with a due date are reminded of what they owe.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct ReminderSettings {
+    pub enabled: bool,
+    /// How many days before the due date payers are reminded, 0 to not remind them before.
+    pub days_before: u32,
+    /// How many days after the due date payers are first reminded. Every reminder after that
+    /// waits twice as long as the last, up to `max_gap_days`.
+    pub days_after: u32,
+    pub max_gap_days: u32,
+    /// Hours of the day in UTC when reminders aren't sent, from `.0` up to but not including
+    /// `.1`. The range wraps around midnight when it ends before it starts.
+    pub quiet_hours: Option<(u32, u32)>,
+}
+
+impl Default for ReminderSettings {
+    fn default() -> Self {
+        ReminderSettings {
+            enabled: true,
+            days_before: 2,
+            days_after: 1,
+            max_gap_days: 14,
+            quiet_hours: None,
+        }
+    }
+}
+
+/// How a user wants to be reminded, across every guild.
+#[derive(Clone, Copy, Debug, Default)]
+pub struct ReminderPrefs {
+    /// Reminders are posted in the bill's channel instead of sent in a DM.
+    pub opted_out: bool,
+    /// No reminders are sent before this time.
+    pub snoozed_until: Option<DateTime<Utc>>,
+}
+
+/// The reminders sent so far for a single share.
+#[derive(Clone, Copy, Debug, Default)]
+pub struct ReminderState {
+    pub before_sent: bool,
+    /// How many reminders were sent since the due date passed.
+    pub overdue_sent: u32,
+    pub last_sent_at: Option<DateTime<Utc>>,
+}
+
+/// Persistent storage for bills. Implementations must be safe to share between
+/// the event handler's concurrently running tasks.
+pub trait BillStore: Send + Sync {
+    /// Persists a new bill and its shares, setting the bill's id to the one assigned to it.
+    fn insert_bill(&self, bill: &mut Bill) -> anyhow::Result<()>;
+
+    /// Records the id of the message the bill was posted in.
+    fn set_message_id(&self, bill_id: i64, message_id: MessageId) -> anyhow::Result<()>;
+
+    /// Saves an edited bill's details and replaces its shares with the bill's current ones.
+    /// Moving the due date forgets the reminders sent for the old one.
+    fn update_bill(&self, bill: &Bill) -> anyhow::Result<()>;
+
+    /// Voids a bill, which leaves it out of open bills from then on.
+    fn cancel_bill(&self, bill_id: i64, cancellation: &Cancellation) -> anyhow::Result<()>;
+
+    /// Deletes a bill along with its shares and items. Only for bills that never made it to
+    /// their channel, posted bills get cancelled instead.
+    fn delete_bill(&self, bill_id: i64) -> anyhow::Result<()>;
+
+    /// Loads a bill and its shares, returning `None` if no bill has the given id.
+    fn get_bill(&self, bill_id: i64) -> anyhow::Result<Option<Bill>>;
+
+    /// Loads every bill in a guild which isn't cancelled and still has a share that isn't
+    /// settled, oldest first.
+    fn open_bills(&self, guild_id: GuildId) -> anyhow::Result<Vec<Bill>>;
+
+    /// Loads a page of a guild's bills matching the filter, newest first, along with how
+    /// many bills match in total.
+    fn list_bills(
+        &self,
+        guild_id: GuildId,
+        filter: &BillFilter,
+        offset: usize,
+        limit: usize,
+    ) -> anyhow::Result<(Vec<Bill>, usize)>;
+
+    fn set_share_status(
+        &self,
+        bill_id: i64,
+        user_id: UserId,
+        status: ShareStatus,
+    ) -> anyhow::Result<()>;
+
+    /// Persists a settle plan, setting the plan's id to the one assigned to it.
+    fn insert_settle_plan(&self, plan: &mut SettlePlan) -> anyhow::Result<()>;
+
+    fn get_settle_plan(&self, plan_id: i64) -> anyhow::Result<Option<SettlePlan>>;
+
+    /// Settles the plan's shares on bills created by `creditor` in one transaction, returning
+    /// how many were settled. Nothing is settled once the plan is, or if `creditor` already
+    /// settled their shares. The plan is settled by whoever settles its last share.
+    fn settle_plan(&self, plan_id: i64, creditor: UserId) -> anyhow::Result<usize>;
+
+    /// Persists a new recurring bill template, setting its id to the one assigned to it.
+    fn insert_recurring(&self, template: &mut RecurringBill) -> anyhow::Result<()>;
+
+    fn get_recurring(&self, recurring_id: i64) -> anyhow::Result<Option<RecurringBill>>;
+
+    /// Loads every recurring bill template in a guild, in the order they next run.
+    fn recurring_bills(&self, guild_id: GuildId) -> anyhow::Result<Vec<RecurringBill>>;
+
+    /// Loads the recurring bill templates across all guilds whose next run is at or before `now`.
+    fn due_recurring(&self, now: DateTime<Utc>) -> anyhow::Result<Vec<RecurringBill>>;
+
+    /// Persists a bill posted from a recurring template and moves the template's next run to
+    /// `next_run`, in one transaction.
+    fn insert_recurring_bill(
+        &self,
+        recurring_id: i64,
+        bill: &mut Bill,
+        next_run: DateTime<Utc>,
+    ) -> anyhow::Result<()>;
+
+    /// Stops a recurring bill, bills already posted from it are kept.
+    fn delete_recurring(&self, recurring_id: i64) -> anyhow::Result<()>;
+
+    fn guild_settings(&self, guild_id: GuildId) -> anyhow::Result<GuildSettings>;
+
+    fn set_guild_currency(
+        &self,
+        guild_id: GuildId,
+        currency: &'static Currency,
+    ) -> anyhow::Result<()>;
+
+    fn set_reminder_settings(
+        &self,
+        guild_id: GuildId,
+        settings: &ReminderSettings,
+    ) -> anyhow::Result<()>;
+
+    /// Loads every bill across all guilds which has a due date, isn't cancelled and still has
+    /// a share that's owed.
+    fn due_bills(&self) -> anyhow::Result<Vec<Bill>>;
+
+    fn reminder_state(&self, bill_id: i64, user_id: UserId) -> anyhow::Result<ReminderState>;
+
+    /// Records that a payer was reminded of their share, `overdue` when the bill was past due.
+    fn record_reminder(
+        &self,
+        bill_id: i64,
+        user_id: UserId,
+        overdue: bool,
+        sent_at: DateTime<Utc>,
+    ) -> anyhow::Result<()>;
+
+    fn reminder_prefs(&self, user_id: UserId) -> anyhow::Result<ReminderPrefs>;
+
+    fn set_reminders_opted_out(&self, user_id: UserId, opted_out: bool) -> anyhow::Result<()>;
+
+    /// Holds off reminders for the user until `until`, or lifts a snooze when it's `None`.
+    fn snooze_reminders(&self, user_id: UserId, until: Option<DateTime<Utc>>)
+        -> anyhow::Result<()>;
+
+    /// When the bill's creator last nudged its payers, if ever.
+    fn nudged_at(&self, bill_id: i64) -> anyhow::Result<Option<DateTime<Utc>>>;
+
+    fn set_nudged_at(&self, bill_id: i64, nudged_at: DateTime<Utc>) -> anyhow::Result<()>;
+
+    /// When the bill's message was last redrawn because its due date passed, if ever.
+    fn overdue_drawn_at(&self, bill_id: i64) -> anyhow::Result<Option<DateTime<Utc>>>;
+
+    fn set_overdue_drawn_at(&self, bill_id: i64, drawn_at: DateTime<Utc>) -> anyhow::Result<()>;
+}
@@ -0,0 +1,1521 @@
+use std::{error::Error, sync::Mutex};
+
+use anyhow::Context;
+use chrono::{DateTime, Utc};
+use log::info;
+use rusqlite::{params, params_from_iter, types::Type, Connection, OptionalExtension, Row, ToSql};
+use rust_decimal::Decimal;
+use rusty_money::iso::{self, Currency};
+use serenity::model::prelude::{ChannelId, GuildId, MessageId, UserId};
+
+use crate::bill::{Bill, BillItem, BillRate, BillShare, BillStatus, Cancellation, ShareStatus};
+use crate::rates::{RateProvider, RateTable};
+use crate::recurring::{RecurringBill, TemplateShare};
+use crate::schedule::Schedule;
+
+use super::{
+    BillFilter, BillStore, GuildSettings, PlanShare, ReminderPrefs, ReminderSettings,
+    ReminderState, SettlePlan,
+};
+
+// Each entry is applied once, in order, and tracked with sqlite's user_version
+// pragma. Never edit a migration that has shipped, append a new one instead.
+const MIGRATIONS: &[&str] = &[
+    "CREATE TABLE bills (
+        id INTEGER PRIMARY KEY AUTOINCREMENT,
+        guild_id INTEGER NOT NULL,
+        channel_id INTEGER NOT NULL,
+        message_id INTEGER,
+        creator_id INTEGER NOT NULL,
+        title TEXT NOT NULL,
+        amount TEXT NOT NULL,
+        currency TEXT NOT NULL,
+        method TEXT NOT NULL,
+        created_at TEXT NOT NULL
+    );
+
+    CREATE TABLE bill_shares (
+        bill_id INTEGER NOT NULL REFERENCES bills(id) ON DELETE CASCADE,
+        user_id INTEGER NOT NULL,
+        amount TEXT NOT NULL,
+        status TEXT NOT NULL,
+        PRIMARY KEY (bill_id, user_id)
+    );
+
+    CREATE INDEX bills_guild_id ON bills(guild_id);",
+    "ALTER TABLE bills ADD COLUMN creator_name TEXT NOT NULL DEFAULT '';
+    ALTER TABLE bill_shares ADD COLUMN name TEXT NOT NULL DEFAULT '';
+    ALTER TABLE bill_shares ADD COLUMN settled_at TEXT;",
+    "ALTER TABLE bill_shares ADD COLUMN claimed_at TEXT;",
+    "CREATE TABLE settle_plans (
+        id INTEGER PRIMARY KEY AUTOINCREMENT,
+        guild_id INTEGER NOT NULL,
+        created_at TEXT NOT NULL,
+        settled_by INTEGER,
+        settled_at TEXT
+    );
+
+    CREATE TABLE settle_plan_shares (
+        plan_id INTEGER NOT NULL REFERENCES settle_plans(id) ON DELETE CASCADE,
+        bill_id INTEGER NOT NULL REFERENCES bills(id) ON DELETE CASCADE,
+        user_id INTEGER NOT NULL,
+        amount TEXT NOT NULL,
+        PRIMARY KEY (plan_id, bill_id, user_id)
+    );",
+    "CREATE TABLE guild_settings (
+        guild_id INTEGER PRIMARY KEY,
+        currency TEXT NOT NULL
+    );",
+    "ALTER TABLE bills ADD COLUMN rate_currency TEXT;
+    ALTER TABLE bills ADD COLUMN rate TEXT;",
+    "ALTER TABLE bills ADD COLUMN cancelled_by INTEGER;
+    ALTER TABLE bills ADD COLUMN cancel_reason TEXT;
+    ALTER TABLE bills ADD COLUMN cancelled_at TEXT;",
+    "CREATE TABLE recurring_bills (
+        id INTEGER PRIMARY KEY AUTOINCREMENT,
+        guild_id INTEGER NOT NULL,
+        channel_id INTEGER NOT NULL,
+        creator_id INTEGER NOT NULL,
+        creator_name TEXT NOT NULL,
+        title TEXT NOT NULL,
+        amount TEXT NOT NULL,
+        currency TEXT NOT NULL,
+        method TEXT NOT NULL,
+        schedule TEXT NOT NULL,
+        next_run TEXT NOT NULL,
+        created_at TEXT NOT NULL
+    );
+
+    CREATE TABLE recurring_bill_shares (
+        recurring_id INTEGER NOT NULL REFERENCES recurring_bills(id) ON DELETE CASCADE,
+        user_id INTEGER NOT NULL,
+        name TEXT NOT NULL,
+        amount TEXT NOT NULL,
+        PRIMARY KEY (recurring_id, user_id)
+    );
+
+    CREATE INDEX recurring_bills_next_run ON recurring_bills(next_run);",
+    "ALTER TABLE bills ADD COLUMN due_at TEXT;",
+    "ALTER TABLE guild_settings ADD COLUMN reminders_enabled INTEGER;
+    ALTER TABLE guild_settings ADD COLUMN remind_days_before INTEGER;
+    ALTER TABLE guild_settings ADD COLUMN remind_days_after INTEGER;
+    ALTER TABLE guild_settings ADD COLUMN remind_max_gap_days INTEGER;
+    ALTER TABLE guild_settings ADD COLUMN quiet_start INTEGER;
+    ALTER TABLE guild_settings ADD COLUMN quiet_end INTEGER;
+
+    CREATE TABLE share_reminders (
+        bill_id INTEGER NOT NULL REFERENCES bills(id) ON DELETE CASCADE,
+        user_id INTEGER NOT NULL,
+        before_sent INTEGER NOT NULL DEFAULT 0,
+        overdue_sent INTEGER NOT NULL DEFAULT 0,
+        last_sent_at TEXT,
+        PRIMARY KEY (bill_id, user_id)
+    );
+
+    CREATE TABLE reminder_prefs (
+        user_id INTEGER PRIMARY KEY,
+        opted_out INTEGER NOT NULL DEFAULT 0,
+        snoozed_until TEXT
+    );",
+    "ALTER TABLE bills ADD COLUMN nudged_at TEXT;",
+    "CREATE TABLE bill_items (
+        id INTEGER PRIMARY KEY AUTOINCREMENT,
+        bill_id INTEGER NOT NULL REFERENCES bills(id) ON DELETE CASCADE,
+        name TEXT NOT NULL,
+        amount TEXT NOT NULL
+    );
+
+    CREATE TABLE bill_item_payers (
+        item_id INTEGER NOT NULL REFERENCES bill_items(id) ON DELETE CASCADE,
+        user_id INTEGER NOT NULL,
+        PRIMARY KEY (item_id, user_id)
+    );
+
+    CREATE INDEX bill_items_bill_id ON bill_items(bill_id);",
+    "ALTER TABLE settle_plan_shares ADD COLUMN settled_at TEXT;",
+    "CREATE TABLE exchange_rates (
+        guild_id INTEGER NOT NULL,
+        from_currency TEXT NOT NULL,
+        to_currency TEXT NOT NULL,
+        rate TEXT NOT NULL,
+        PRIMARY KEY (guild_id, from_currency, to_currency)
+    );",
+    "ALTER TABLE bills ADD COLUMN overdue_drawn_at TEXT;",
+];
+
+pub struct SqliteStore {
+    conn: Mutex<Connection>,
+}
+
+impl SqliteStore {
+    pub fn open(path: &str) -> anyhow::Result<SqliteStore> {
+        let mut conn =
+            Connection::open(path).with_context(|| format!("failed to open database {}", path))?;
+        conn.pragma_update(None, "foreign_keys", true)?;
+        migrate(&mut conn)?;
+
+        Ok(SqliteStore {
+            conn: Mutex::new(conn),
+        })
+    }
+}
+
+fn migrate(conn: &mut Connection) -> anyhow::Result<()> {
+    let version: usize = conn.pragma_query_value(None, "user_version", |row| row.get(0))?;
+
+    for (i, migration) in MIGRATIONS.iter().enumerate().skip(version) {
+        info!("applying database migration {}", i + 1);
+        let tx = conn.transaction()?;
+        tx.execute_batch(migration)
+            .with_context(|| format!("failed to apply migration {}", i + 1))?;
+        tx.pragma_update(None, "user_version", i + 1)?;
+        tx.commit()?;
+    }
+
+    Ok(())
+}
+
+impl BillStore for SqliteStore {
+    fn insert_bill(&self, bill: &mut Bill) -> anyhow::Result<()> {
+        let mut conn = self.conn.lock().unwrap();
+        let tx = conn.transaction()?;
+        let bill_id = insert_bill(&tx, bill)?;
+        tx.commit()?;
+
+        bill.id = bill_id;
+        Ok(())
+    }
+
+    fn update_bill(&self, bill: &Bill) -> anyhow::Result<()> {
+        let mut conn = self.conn.lock().unwrap();
+        let tx = conn.transaction()?;
+
+        // reminders count towards the due date they were sent for, a new one starts over
+        tx.execute(
+            "DELETE FROM share_reminders WHERE bill_id = ?1 AND EXISTS (
+                SELECT 1 FROM bills WHERE id = ?1 AND due_at IS NOT ?2
+            )",
+            params![bill.id, bill.due_at],
+        )?;
+
+        let updated = tx.execute(
+            "UPDATE bills SET title = ?1, amount = ?2, method = ?3, due_at = ?4 WHERE id = ?5",
+            params![
+                bill.title,
+                bill.amount.to_string(),
+                bill.method,
+                bill.due_at,
+                bill.id
+            ],
+        )?;
+        if updated == 0 {
+            return Err(anyhow::anyhow!("bill {} does not exist", bill.id));
+        }
+
+        tx.execute(
+            "DELETE FROM bill_shares WHERE bill_id = ?1",
+            params![bill.id],
+        )?;
+        insert_shares(&tx, bill.id, &bill.shares)?;
+
+        // payers taken off the bill have nothing left to be reminded of
+        tx.execute(
+            "DELETE FROM share_reminders WHERE bill_id = ?1 AND user_id NOT IN (
+                SELECT user_id FROM bill_shares WHERE bill_id = ?1
+            )",
+            params![bill.id],
+        )?;
+
+        tx.execute(
+            "DELETE FROM bill_items WHERE bill_id = ?1",
+            params![bill.id],
+        )?;
+        insert_items(&tx, bill.id, &bill.items)?;
+
+        tx.commit()?;
+        Ok(())
+    }
+
+    fn cancel_bill(&self, bill_id: i64, cancellation: &Cancellation) -> anyhow::Result<()> {
+        let conn = self.conn.lock().unwrap();
+        let updated = conn.execute(
+            "UPDATE bills SET cancelled_by = ?1, cancel_reason = ?2, cancelled_at = ?3
+            WHERE id = ?4 AND cancelled_at IS NULL",
+            params![
+                cancellation.cancelled_by.0 as i64,
+                cancellation.reason,
+                cancellation.cancelled_at,
+                bill_id
+            ],
+        )?;
+        if updated == 0 {
+            return Err(anyhow::anyhow!(
+                "bill {} does not exist or is already cancelled",
+                bill_id
+            ));
+        }
+
+        Ok(())
+    }
+
+    fn delete_bill(&self, bill_id: i64) -> anyhow::Result<()> {
+        let conn = self.conn.lock().unwrap();
+        conn.execute("DELETE FROM bills WHERE id = ?1", params![bill_id])?;
+
+        Ok(())
+    }
+
+    fn set_message_id(&self, bill_id: i64, message_id: MessageId) -> anyhow::Result<()> {
+        let conn = self.conn.lock().unwrap();
+        conn.execute(
+            "UPDATE bills SET message_id = ?1 WHERE id = ?2",
+            params![message_id.0 as i64, bill_id],
+        )?;
+        Ok(())
+    }
+
+    fn get_bill(&self, bill_id: i64) -> anyhow::Result<Option<Bill>> {
+        let conn = self.conn.lock().unwrap();
+
+        let bill = conn
+            .query_row(
+                &format!("SELECT {} FROM bills WHERE id = ?1", BILL_COLUMNS),
+                params![bill_id],
+                bill_from_row,
+            )
+            .optional()?;
+
+        bill.map(|bill| with_shares(&conn, bill)).transpose()
+    }
+
+    fn open_bills(&self, guild_id: GuildId) -> anyhow::Result<Vec<Bill>> {
+        let conn = self.conn.lock().unwrap();
+
+        let mut stmt = conn.prepare_cached(&format!(
+            "SELECT {} FROM bills WHERE guild_id = ?1 AND cancelled_at IS NULL AND EXISTS (
+                SELECT 1 FROM bill_shares WHERE bill_id = bills.id AND status != ?2
+            ) ORDER BY created_at",
+            BILL_COLUMNS
+        ))?;
+
+        let bills = stmt
+            .query_map(
+                params![guild_id.0 as i64, ShareStatus::Settled.as_str()],
+                bill_from_row,
+            )?
+            .collect::<rusqlite::Result<Vec<_>>>()?;
+
+        bills
+            .into_iter()
+            .map(|bill| with_shares(&conn, bill))
+            .collect()
+    }
+
+    fn list_bills(
+        &self,
+        guild_id: GuildId,
+        filter: &BillFilter,
+        offset: usize,
+        limit: usize,
+    ) -> anyhow::Result<(Vec<Bill>, usize)> {
+        let conn = self.conn.lock().unwrap();
+
+        let mut clauses = vec!["guild_id = ?"];
+        let mut values: Vec<Box<dyn ToSql>> = vec![Box::new(guild_id.0 as i64)];
+
+        match filter.status {
+            Some(BillStatus::Open) => {
+                clauses.push(
+                    "cancelled_at IS NULL AND EXISTS (
+                        SELECT 1 FROM bill_shares WHERE bill_id = bills.id AND status != ?
+                    )",
+                );
+                values.push(Box::new(ShareStatus::Settled.as_str()));
+            }
+            Some(BillStatus::Settled) => {
+                clauses.push(
+                    "cancelled_at IS NULL AND NOT EXISTS (
+                        SELECT 1 FROM bill_shares WHERE bill_id = bills.id AND status != ?
+                    )",
+                );
+                values.push(Box::new(ShareStatus::Settled.as_str()));
+            }
+            Some(BillStatus::Cancelled) => clauses.push("cancelled_at IS NOT NULL"),
+            None => {}
+        }
+        if let Some(creator) = filter.creator {
+            clauses.push("creator_id = ?");
+            values.push(Box::new(creator.0 as i64));
+        }
+        if let Some(payer) = filter.payer {
+            clauses.push(
+                "EXISTS (
+                    SELECT 1 FROM bill_shares
+                    WHERE bill_id = bills.id AND user_id = ? AND user_id != bills.creator_id
+                )",
+            );
+            values.push(Box::new(payer.0 as i64));
+        }
+        if let Some(from) = filter.from {
+            clauses.push("created_at >= ?");
+            values.push(Box::new(from));
+        }
+        if let Some(until) = filter.until {
+            clauses.push("created_at < ?");
+            values.push(Box::new(until));
+        }
+        if let Some(title) = &filter.title {
+            clauses.push("instr(lower(title), lower(?)) > 0");
+            values.push(Box::new(title.clone()));
+        }
+        let clauses = clauses.join(" AND ");
+
+        let total: usize = conn.query_row(
+            &format!("SELECT COUNT(*) FROM bills WHERE {}", clauses),
+            params_from_iter(values.iter()),
+            |row| row.get(0),
+        )?;
+
+        values.push(Box::new(limit as i64));
+        values.push(Box::new(offset as i64));
+        let mut stmt = conn.prepare(&format!(
+            "SELECT {} FROM bills WHERE {} ORDER BY created_at DESC, id DESC LIMIT ? OFFSET ?",
+            BILL_COLUMNS, clauses
+        ))?;
+        let bills = stmt
+            .query_map(params_from_iter(values.iter()), bill_from_row)?
+            .collect::<rusqlite::Result<Vec<_>>>()?;
+
+        let bills = bills
+            .into_iter()
+            .map(|bill| with_shares(&conn, bill))
+            .collect::<anyhow::Result<_>>()?;
+        Ok((bills, total))
+    }
+
+    fn set_share_status(
+        &self,
+        bill_id: i64,
+        user_id: UserId,
+        status: ShareStatus,
+    ) -> anyhow::Result<()> {
+        let conn = self.conn.lock().unwrap();
+        update_share_status(&conn, bill_id, user_id, status)
+    }
+
+    fn insert_settle_plan(&self, plan: &mut SettlePlan) -> anyhow::Result<()> {
+        let mut conn = self.conn.lock().unwrap();
+        let tx = conn.transaction()?;
+
+        tx.execute(
+            "INSERT INTO settle_plans (guild_id, created_at) VALUES (?1, ?2)",
+            params![plan.guild_id.0 as i64, plan.created_at],
+        )?;
+        let plan_id = tx.last_insert_rowid();
+
+        for share in &plan.shares {
+            tx.execute(
+                "INSERT INTO settle_plan_shares (plan_id, bill_id, user_id, amount) VALUES (?1, ?2, ?3, ?4)",
+                params![
+                    plan_id,
+                    share.bill_id,
+                    share.user_id.0 as i64,
+                    share.amount.to_string()
+                ],
+            )?;
+        }
+
+        tx.commit()?;
+        plan.id = plan_id;
+        Ok(())
+    }
+
+    fn get_settle_plan(&self, plan_id: i64) -> anyhow::Result<Option<SettlePlan>> {
+        let conn = self.conn.lock().unwrap();
+
+        let plan = conn
+            .query_row(
+                "SELECT id, guild_id, created_at, settled_by FROM settle_plans WHERE id = ?1",
+                params![plan_id],
+                |row| {
+                    Ok(SettlePlan {
+                        id: row.get(0)?,
+                        guild_id: GuildId(row.get::<_, i64>(1)? as u64),
+                        created_at: row.get(2)?,
+                        settled_by: row.get::<_, Option<i64>>(3)?.map(|id| UserId(id as u64)),
+                        shares: Vec::new(),
+                    })
+                },
+            )
+            .optional()?;
+
+        let mut plan = match plan {
+            Some(plan) => plan,
+            None => return Ok(None),
+        };
+
+        let mut stmt = conn.prepare_cached(
+            "SELECT bill_id, user_id, amount, settled_at FROM settle_plan_shares WHERE plan_id = ?1 ORDER BY rowid",
+        )?;
+        plan.shares = stmt
+            .query_map(params![plan.id], |row| {
+                Ok(PlanShare {
+                    bill_id: row.get(0)?,
+                    user_id: UserId(row.get::<_, i64>(1)? as u64),
+                    amount: decimal_column(row, 2)?,
+                    settled_at: row.get(3)?,
+                })
+            })?
+            .collect::<rusqlite::Result<_>>()?;
+
+        Ok(Some(plan))
+    }
+
+    fn settle_plan(&self, plan_id: i64, creditor: UserId) -> anyhow::Result<usize> {
+        let mut conn = self.conn.lock().unwrap();
+        let tx = conn.transaction()?;
+
+        // checked in the transaction so two presses at once can't both settle the same shares
+        let shares = {
+            let mut stmt = tx.prepare_cached(
+                "SELECT shares.bill_id, shares.user_id FROM settle_plan_shares shares
+                JOIN settle_plans plans ON plans.id = shares.plan_id
+                JOIN bills ON bills.id = shares.bill_id
+                WHERE shares.plan_id = ?1 AND bills.creator_id = ?2
+                    AND shares.settled_at IS NULL AND plans.settled_by IS NULL",
+            )?;
+            let rows = stmt
+                .query_map(params![plan_id, creditor.0 as i64], |row| {
+                    Ok((row.get::<_, i64>(0)?, UserId(row.get::<_, i64>(1)? as u64)))
+                })?
+                .collect::<rusqlite::Result<Vec<_>>>()?;
+            rows
+        };
+        if shares.is_empty() {
+            return Ok(0);
+        }
+
+        let now = Utc::now();
+        for (bill_id, user_id) in &shares {
+            update_share_status(&tx, *bill_id, *user_id, ShareStatus::Settled)?;
+            tx.execute(
+                "UPDATE settle_plan_shares SET settled_at = ?1
+                WHERE plan_id = ?2 AND bill_id = ?3 AND user_id = ?4",
+                params![now, plan_id, bill_id, user_id.0 as i64],
+            )?;
+        }
+
+        tx.execute(
+            "UPDATE settle_plans SET settled_by = ?1, settled_at = ?2
+            WHERE id = ?3 AND settled_by IS NULL AND NOT EXISTS (
+                SELECT 1 FROM settle_plan_shares WHERE plan_id = ?3 AND settled_at IS NULL
+            )",
+            params![creditor.0 as i64, now, plan_id],
+        )?;
+
+        tx.commit()?;
+        Ok(shares.len())
+    }
+
+    fn insert_recurring(&self, template: &mut RecurringBill) -> anyhow::Result<()> {
+        let mut conn = self.conn.lock().unwrap();
+        let tx = conn.transaction()?;
+
+        tx.execute(
+            "INSERT INTO recurring_bills (guild_id, channel_id, creator_id, creator_name, title, amount, currency, method, schedule, next_run, created_at)
+            VALUES (?1, ?2, ?3, ?4, ?5, ?6, ?7, ?8, ?9, ?10, ?11)",
+            params![
+                template.guild_id.0 as i64,
+                template.channel_id.0 as i64,
+                template.creator_id.0 as i64,
+                template.creator_name,
+                template.title,
+                template.amount.to_string(),
+                template.currency.iso_alpha_code,
+                template.method,
+                template.schedule.to_string(),
+                template.next_run,
+                template.created_at,
+            ],
+        )?;
+        let recurring_id = tx.last_insert_rowid();
+
+        for share in &template.shares {
+            tx.execute(
+                "INSERT INTO recurring_bill_shares (recurring_id, user_id, name, amount) VALUES (?1, ?2, ?3, ?4)",
+                params![
+                    recurring_id,
+                    share.user_id.0 as i64,
+                    share.name,
+                    share.amount.to_string()
+                ],
+            )?;
+        }
+
+        tx.commit()?;
+        template.id = recurring_id;
+        Ok(())
+    }
+
+    fn get_recurring(&self, recurring_id: i64) -> anyhow::Result<Option<RecurringBill>> {
+        let conn = self.conn.lock().unwrap();
+
+        let template = conn
+            .query_row(
+                &format!(
+                    "SELECT {} FROM recurring_bills WHERE id = ?1",
+                    RECURRING_COLUMNS
+                ),
+                params![recurring_id],
+                recurring_from_row,
+            )
+            .optional()?;
+
+        template
+            .map(|template| with_template_shares(&conn, template))
+            .transpose()
+    }
+
+    fn recurring_bills(&self, guild_id: GuildId) -> anyhow::Result<Vec<RecurringBill>> {
+        let conn = self.conn.lock().unwrap();
+
+        let mut stmt = conn.prepare_cached(&format!(
+            "SELECT {} FROM recurring_bills WHERE guild_id = ?1 ORDER BY next_run, id",
+            RECURRING_COLUMNS
+        ))?;
+        let templates = stmt
+            .query_map(params![guild_id.0 as i64], recurring_from_row)?
+            .collect::<rusqlite::Result<Vec<_>>>()?;
+
+        templates
+            .into_iter()
+            .map(|template| with_template_shares(&conn, template))
+            .collect()
+    }
+
+    fn due_recurring(&self, now: DateTime<Utc>) -> anyhow::Result<Vec<RecurringBill>> {
+        let conn = self.conn.lock().unwrap();
+
+        let mut stmt = conn.prepare_cached(&format!(
+            "SELECT {} FROM recurring_bills WHERE next_run <= ?1 ORDER BY next_run, id",
+            RECURRING_COLUMNS
+        ))?;
+        let templates = stmt
+            .query_map(params![now], recurring_from_row)?
+            .collect::<rusqlite::Result<Vec<_>>>()?;
+
+        templates
+            .into_iter()
+            .map(|template| with_template_shares(&conn, template))
+            .collect()
+    }
+
+    fn insert_recurring_bill(
+        &self,
+        recurring_id: i64,
+        bill: &mut Bill,
+        next_run: DateTime<Utc>,
+    ) -> anyhow::Result<()> {
+        let mut conn = self.conn.lock().unwrap();
+        let tx = conn.transaction()?;
+
+        let bill_id = insert_bill(&tx, bill)?;
+        let updated = tx.execute(
+            "UPDATE recurring_bills SET next_run = ?1 WHERE id = ?2",
+            params![next_run, recurring_id],
+        )?;
+        if updated == 0 {
+            return Err(anyhow::anyhow!(
+                "recurring bill {} does not exist",
+                recurring_id
+            ));
+        }
+
+        tx.commit()?;
+        bill.id = bill_id;
+        Ok(())
+    }
+
+    fn delete_recurring(&self, recurring_id: i64) -> anyhow::Result<()> {
+        let conn = self.conn.lock().unwrap();
+        conn.execute(
+            "DELETE FROM recurring_bills WHERE id = ?1",
+            params![recurring_id],
+        )?;
+        Ok(())
+    }
+
+    fn guild_settings(&self, guild_id: GuildId) -> anyhow::Result<GuildSettings> {
+        let conn = self.conn.lock().unwrap();
+
+        // reminder columns are null until a guild configures reminders, which means the defaults
+        let settings = conn
+            .query_row(
+                "SELECT currency, reminders_enabled, remind_days_before, remind_days_after, remind_max_gap_days, quiet_start, quiet_end
+                FROM guild_settings WHERE guild_id = ?1",
+                params![guild_id.0 as i64],
+                |row| {
+                    let defaults = ReminderSettings::default();
+                    Ok(GuildSettings {
+                        currency: currency_column(row, 0)?,
+                        reminders: ReminderSettings {
+                            enabled: row.get::<_, Option<bool>>(1)?.unwrap_or(defaults.enabled),
+                            days_before: row
+                                .get::<_, Option<u32>>(2)?
+                                .unwrap_or(defaults.days_before),
+                            days_after: row
+                                .get::<_, Option<u32>>(3)?
+                                .unwrap_or(defaults.days_after),
+                            max_gap_days: row
+                                .get::<_, Option<u32>>(4)?
+                                .unwrap_or(defaults.max_gap_days),
+                            quiet_hours: match (row.get(5)?, row.get(6)?) {
+                                (Some(start), Some(end)) => Some((start, end)),
+                                _ => None,
+                            },
+                        },
+                    })
+                },
+            )
+            .optional()?;
+
+        Ok(settings.unwrap_or_default())
+    }
+
+    fn set_guild_currency(
+        &self,
+        guild_id: GuildId,
+        currency: &'static Currency,
+    ) -> anyhow::Result<()> {
+        let conn = self.conn.lock().unwrap();
+        conn.execute(
+            "INSERT INTO guild_settings (guild_id, currency) VALUES (?1, ?2)
+            ON CONFLICT (guild_id) DO UPDATE SET currency = excluded.currency",
+            params![guild_id.0 as i64, currency.iso_alpha_code],
+        )?;
+        Ok(())
+    }
+
+    fn set_reminder_settings(
+        &self,
+        guild_id: GuildId,
+        settings: &ReminderSettings,
+    ) -> anyhow::Result<()> {
+        let conn = self.conn.lock().unwrap();
+        conn.execute(
+            "INSERT INTO guild_settings (guild_id, currency, reminders_enabled, remind_days_before, remind_days_after, remind_max_gap_days, quiet_start, quiet_end)
+            VALUES (?1, ?2, ?3, ?4, ?5, ?6, ?7, ?8)
+            ON CONFLICT (guild_id) DO UPDATE SET
+                reminders_enabled = excluded.reminders_enabled,
+                remind_days_before = excluded.remind_days_before,
+                remind_days_after = excluded.remind_days_after,
+                remind_max_gap_days = excluded.remind_max_gap_days,
+                quiet_start = excluded.quiet_start,
+                quiet_end = excluded.quiet_end",
+            params![
+                guild_id.0 as i64,
+                GuildSettings::default().currency.iso_alpha_code,
+                settings.enabled,
+                settings.days_before,
+                settings.days_after,
+                settings.max_gap_days,
+                settings.quiet_hours.map(|(start, _)| start),
+                settings.quiet_hours.map(|(_, end)| end),
+            ],
+        )?;
+        Ok(())
+    }
+
+    fn due_bills(&self) -> anyhow::Result<Vec<Bill>> {
+        let conn = self.conn.lock().unwrap();
+
+        let mut stmt = conn.prepare_cached(&format!(
+            "SELECT {} FROM bills WHERE due_at IS NOT NULL AND cancelled_at IS NULL AND EXISTS (
+                SELECT 1 FROM bill_shares WHERE bill_id = bills.id AND status = ?1
+            ) ORDER BY due_at, id",
+            BILL_COLUMNS
+        ))?;
+
+        let bills = stmt
+            .query_map(params![ShareStatus::Owed.as_str()], bill_from_row)?
+            .collect::<rusqlite::Result<Vec<_>>>()?;
+
+        bills
+            .into_iter()
+            .map(|bill| with_shares(&conn, bill))
+            .collect()
+    }
+
+    fn reminder_state(&self, bill_id: i64, user_id: UserId) -> anyhow::Result<ReminderState> {
+        let conn = self.conn.lock().unwrap();
+
+        let state = conn
+            .query_row(
+                "SELECT before_sent, overdue_sent, last_sent_at FROM share_reminders
+                WHERE bill_id = ?1 AND user_id = ?2",
+                params![bill_id, user_id.0 as i64],
+                |row| {
+                    Ok(ReminderState {
+                        before_sent: row.get(0)?,
+                        overdue_sent: row.get(1)?,
+                        last_sent_at: row.get(2)?,
+                    })
+                },
+            )
+            .optional()?;
+
+        Ok(state.unwrap_or_default())
+    }
+
+    fn record_reminder(
+        &self,
+        bill_id: i64,
+        user_id: UserId,
+        overdue: bool,
+        sent_at: DateTime<Utc>,
+    ) -> anyhow::Result<()> {
+        let conn = self.conn.lock().unwrap();
+        let (before, overdue) = if overdue { (0, 1) } else { (1, 0) };
+        conn.execute(
+            "INSERT INTO share_reminders (bill_id, user_id, before_sent, overdue_sent, last_sent_at)
+            VALUES (?1, ?2, ?3, ?4, ?5)
+            ON CONFLICT (bill_id, user_id) DO UPDATE SET
+                before_sent = max(before_sent, excluded.before_sent),
+                overdue_sent = overdue_sent + excluded.overdue_sent,
+                last_sent_at = excluded.last_sent_at",
+            params![bill_id, user_id.0 as i64, before, overdue, sent_at],
+        )?;
+        Ok(())
+    }
+
+    fn reminder_prefs(&self, user_id: UserId) -> anyhow::Result<ReminderPrefs> {
+        let conn = self.conn.lock().unwrap();
+
+        let prefs = conn
+            .query_row(
+                "SELECT opted_out, snoozed_until FROM reminder_prefs WHERE user_id = ?1",
+                params![user_id.0 as i64],
+                |row| {
+                    Ok(ReminderPrefs {
+                        opted_out: row.get(0)?,
+                        snoozed_until: row.get(1)?,
+                    })
+                },
+            )
+            .optional()?;
+
+        Ok(prefs.unwrap_or_default())
+    }
+
+    fn set_reminders_opted_out(&self, user_id: UserId, opted_out: bool) -> anyhow::Result<()> {
+        let conn = self.conn.lock().unwrap();
+        conn.execute(
+            "INSERT INTO reminder_prefs (user_id, opted_out) VALUES (?1, ?2)
+            ON CONFLICT (user_id) DO UPDATE SET opted_out = excluded.opted_out",
+            params![user_id.0 as i64, opted_out],
+        )?;
+        Ok(())
+    }
+
+    fn snooze_reminders(
+        &self,
+        user_id: UserId,
+        until: Option<DateTime<Utc>>,
+    ) -> anyhow::Result<()> {
+        let conn = self.conn.lock().unwrap();
+        conn.execute(
+            "INSERT INTO reminder_prefs (user_id, snoozed_until) VALUES (?1, ?2)
+            ON CONFLICT (user_id) DO UPDATE SET snoozed_until = excluded.snoozed_until",
+            params![user_id.0 as i64, until],
+        )?;
+        Ok(())
+    }
+
+    fn nudged_at(&self, bill_id: i64) -> anyhow::Result<Option<DateTime<Utc>>> {
+        let conn = self.conn.lock().unwrap();
+
+        let nudged_at = conn
+            .query_row(
+                "SELECT nudged_at FROM bills WHERE id = ?1",
+                params![bill_id],
+                |row| row.get(0),
+            )
+            .optional()?;
+
+        Ok(nudged_at.flatten())
+    }
+
+    fn set_nudged_at(&self, bill_id: i64, nudged_at: DateTime<Utc>) -> anyhow::Result<()> {
+        let conn = self.conn.lock().unwrap();
+        conn.execute(
+            "UPDATE bills SET nudged_at = ?1 WHERE id = ?2",
+            params![nudged_at, bill_id],
+        )?;
+        Ok(())
+    }
+
+    fn overdue_drawn_at(&self, bill_id: i64) -> anyhow::Result<Option<DateTime<Utc>>> {
+        let conn = self.conn.lock().unwrap();
+
+        let drawn_at = conn
+            .query_row(
+                "SELECT overdue_drawn_at FROM bills WHERE id = ?1",
+                params![bill_id],
+                |row| row.get(0),
+            )
+            .optional()?;
+
+        Ok(drawn_at.flatten())
+    }
+
+    fn set_overdue_drawn_at(&self, bill_id: i64, drawn_at: DateTime<Utc>) -> anyhow::Result<()> {
+        let conn = self.conn.lock().unwrap();
+        conn.execute(
+            "UPDATE bills SET overdue_drawn_at = ?1 WHERE id = ?2",
+            params![drawn_at, bill_id],
+        )?;
+        Ok(())
+    }
+}
+
+impl RateProvider for SqliteStore {
+    fn rates(&self, guild_id: GuildId) -> anyhow::Result<RateTable> {
+        let conn = self.conn.lock().unwrap();
+        let mut stmt = conn.prepare(
+            "SELECT from_currency, to_currency, rate FROM exchange_rates WHERE guild_id = ?1",
+        )?;
+        let rows = stmt.query_map(params![guild_id.0 as i64], |row| {
+            Ok((
+                currency_column(row, 0)?,
+                currency_column(row, 1)?,
+                decimal_column(row, 2)?,
+            ))
+        })?;
+
+        let mut rates = RateTable::default();
+        for row in rows {
+            let (from, to, rate) = row?;
+            rates.insert(from, to, rate);
+        }
+        Ok(rates)
+    }
+
+    fn set_rate(
+        &self,
+        guild_id: GuildId,
+        from: &'static Currency,
+        to: &'static Currency,
+        rate: Decimal,
+    ) -> anyhow::Result<()> {
+        let mut conn = self.conn.lock().unwrap();
+        let tx = conn.transaction()?;
+
+        // a rate is only kept in the direction it was last set in
+        tx.execute(
+            "DELETE FROM exchange_rates WHERE guild_id = ?1 AND from_currency = ?2 AND to_currency = ?3",
+            params![guild_id.0 as i64, to.iso_alpha_code, from.iso_alpha_code],
+        )?;
+        tx.execute(
+            "INSERT INTO exchange_rates (guild_id, from_currency, to_currency, rate) VALUES (?1, ?2, ?3, ?4)
+            ON CONFLICT (guild_id, from_currency, to_currency) DO UPDATE SET rate = excluded.rate",
+            params![
+                guild_id.0 as i64,
+                from.iso_alpha_code,
+                to.iso_alpha_code,
+                rate.to_string()
+            ],
+        )?;
+
+        tx.commit()?;
+        Ok(())
+    }
+}
+
+fn insert_bill(conn: &Connection, bill: &Bill) -> anyhow::Result<i64> {
+    conn.execute(
+        "INSERT INTO bills (guild_id, channel_id, message_id, creator_id, creator_name, title, amount, currency, method, created_at, rate_currency, rate, due_at)
+        VALUES (?1, ?2, ?3, ?4, ?5, ?6, ?7, ?8, ?9, ?10, ?11, ?12, ?13)",
+        params![
+            bill.guild_id.0 as i64,
+            bill.channel_id.0 as i64,
+            bill.message_id.map(|id| id.0 as i64),
+            bill.creator_id.0 as i64,
+            bill.creator_name,
+            bill.title,
+            bill.amount.to_string(),
+            bill.currency.iso_alpha_code,
+            bill.method,
+            bill.created_at,
+            bill.rate.map(|rate| rate.currency.iso_alpha_code),
+            bill.rate.map(|rate| rate.rate.to_string()),
+            bill.due_at,
+        ],
+    )?;
+    let bill_id = conn.last_insert_rowid();
+    insert_shares(conn, bill_id, &bill.shares)?;
+    insert_items(conn, bill_id, &bill.items)?;
+
+    Ok(bill_id)
+}
+
+fn insert_shares(conn: &Connection, bill_id: i64, shares: &[BillShare]) -> anyhow::Result<()> {
+    for share in shares {
+        conn.execute(
+            "INSERT INTO bill_shares (bill_id, user_id, name, amount, status, claimed_at, settled_at)
+            VALUES (?1, ?2, ?3, ?4, ?5, ?6, ?7)",
+            params![
+                bill_id,
+                share.user_id.0 as i64,
+                share.name,
+                share.amount.to_string(),
+                share.status.as_str(),
+                share.claimed_at,
+                share.settled_at,
+            ],
+        )?;
+    }
+
+    Ok(())
+}
+
+fn update_share_status(
+    conn: &Connection,
+    bill_id: i64,
+    user_id: UserId,
+    status: ShareStatus,
+) -> anyhow::Result<()> {
+    // a creator can settle a share that was never claimed, so the claim time is only
+    // cleared when the share goes back to being owed
+    let now = Utc::now();
+    let updated = match status {
+        ShareStatus::Owed => conn.execute(
+            "UPDATE bill_shares SET status = ?1, claimed_at = NULL, settled_at = NULL
+            WHERE bill_id = ?2 AND user_id = ?3",
+            params![status.as_str(), bill_id, user_id.0 as i64],
+        )?,
+        ShareStatus::Claimed => conn.execute(
+            "UPDATE bill_shares SET status = ?1, claimed_at = ?2, settled_at = NULL
+            WHERE bill_id = ?3 AND user_id = ?4",
+            params![status.as_str(), now, bill_id, user_id.0 as i64],
+        )?,
+        ShareStatus::Settled => conn.execute(
+            "UPDATE bill_shares SET status = ?1, settled_at = ?2 WHERE bill_id = ?3 AND user_id = ?4",
+            params![status.as_str(), now, bill_id, user_id.0 as i64],
+        )?,
+    };
+    if updated == 0 {
+        return Err(anyhow::anyhow!(
+            "bill {} has no share for user {}",
+            bill_id,
+            user_id
+        ));
+    }
+
+    Ok(())
+}
+
+fn insert_items(conn: &Connection, bill_id: i64, items: &[BillItem]) -> anyhow::Result<()> {
+    for item in items {
+        conn.execute(
+            "INSERT INTO bill_items (bill_id, name, amount) VALUES (?1, ?2, ?3)",
+            params![bill_id, item.name, item.amount.to_string()],
+        )?;
+        let item_id = conn.last_insert_rowid();
+
+        for user_id in &item.payers {
+            conn.execute(
+                "INSERT INTO bill_item_payers (item_id, user_id) VALUES (?1, ?2)",
+                params![item_id, user_id.0 as i64],
+            )?;
+        }
+    }
+
+    Ok(())
+}
+
+const BILL_COLUMNS: &str = "id, guild_id, channel_id, message_id, creator_id, creator_name, title, amount, currency, method, created_at, rate_currency, rate, cancelled_by, cancel_reason, cancelled_at, due_at";
+
+fn bill_from_row(row: &Row) -> rusqlite::Result<Bill> {
+    Ok(Bill {
+        id: row.get(0)?,
+        guild_id: GuildId(row.get::<_, i64>(1)? as u64),
+        channel_id: ChannelId(row.get::<_, i64>(2)? as u64),
+        message_id: row.get::<_, Option<i64>>(3)?.map(|id| MessageId(id as u64)),
+        creator_id: UserId(row.get::<_, i64>(4)? as u64),
+        creator_name: row.get(5)?,
+        title: row.get(6)?,
+        amount: decimal_column(row, 7)?,
+        currency: currency_column(row, 8)?,
+        method: row.get(9)?,
+        created_at: row.get(10)?,
+        due_at: row.get(16)?,
+        rate: match row.get::<_, Option<String>>(11)? {
+            Some(_) => Some(BillRate {
+                currency: currency_column(row, 11)?,
+                rate: decimal_column(row, 12)?,
+            }),
+            None => None,
+        },
+        cancellation: match row.get::<_, Option<i64>>(13)? {
+            Some(cancelled_by) => Some(Cancellation {
+                cancelled_by: UserId(cancelled_by as u64),
+                reason: row.get::<_, Option<String>>(14)?.unwrap_or_default(),
+                cancelled_at: row.get(15)?,
+            }),
+            None => None,
+        },
+        shares: Vec::new(),
+        items: Vec::new(),
+    })
+}
+
+fn share_from_row(row: &Row) -> rusqlite::Result<BillShare> {
+    let status: String = row.get(3)?;
+
+    Ok(BillShare {
+        user_id: UserId(row.get::<_, i64>(0)? as u64),
+        name: row.get(1)?,
+        amount: decimal_column(row, 2)?,
+        status: ShareStatus::parse(&status).map_err(|why| conversion_error(3, why.into()))?,
+        claimed_at: row.get(4)?,
+        settled_at: row.get(5)?,
+    })
+}
+
+/// Loads the bill's shares, along with its items if it's split by item.
+fn with_shares(conn: &Connection, mut bill: Bill) -> anyhow::Result<Bill> {
+    let mut stmt = conn.prepare_cached(
+        "SELECT user_id, name, amount, status, claimed_at, settled_at FROM bill_shares WHERE bill_id = ?1 ORDER BY rowid",
+    )?;
+
+    bill.shares = stmt
+        .query_map(params![bill.id], share_from_row)?
+        .collect::<rusqlite::Result<_>>()?;
+
+    let mut stmt = conn
+        .prepare_cached("SELECT id, name, amount FROM bill_items WHERE bill_id = ?1 ORDER BY id")?;
+    let items: Vec<(i64, String, Decimal)> = stmt
+        .query_map(params![bill.id], |row| {
+            Ok((row.get(0)?, row.get(1)?, decimal_column(row, 2)?))
+        })?
+        .collect::<rusqlite::Result<_>>()?;
+
+    let mut stmt = conn
+        .prepare_cached("SELECT user_id FROM bill_item_payers WHERE item_id = ?1 ORDER BY rowid")?;
+    for (item_id, name, amount) in items {
+        let payers = stmt
+            .query_map(params![item_id], |row| {
+                Ok(UserId(row.get::<_, i64>(0)? as u64))
+            })?
+            .collect::<rusqlite::Result<_>>()?;
+        bill.items.push(BillItem {
+            name,
+            amount,
+            payers,
+        });
+    }
+
+    Ok(bill)
+}
+
+const RECURRING_COLUMNS: &str = "id, guild_id, channel_id, creator_id, creator_name, title, amount, currency, method, schedule, next_run, created_at";
+
+fn recurring_from_row(row: &Row) -> rusqlite::Result<RecurringBill> {
+    let schedule: String = row.get(9)?;
+
+    Ok(RecurringBill {
+        id: row.get(0)?,
+        guild_id: GuildId(row.get::<_, i64>(1)? as u64),
+        channel_id: ChannelId(row.get::<_, i64>(2)? as u64),
+        creator_id: UserId(row.get::<_, i64>(3)? as u64),
+        creator_name: row.get(4)?,
+        title: row.get(5)?,
+        amount: decimal_column(row, 6)?,
+        currency: currency_column(row, 7)?,
+        method: row.get(8)?,
+        schedule: Schedule::parse(&schedule).map_err(|why| conversion_error(9, why.into()))?,
+        next_run: row.get(10)?,
+        created_at: row.get(11)?,
+        shares: Vec::new(),
+    })
+}
+
+fn with_template_shares(
+    conn: &Connection,
+    mut template: RecurringBill,
+) -> anyhow::Result<RecurringBill> {
+    let mut stmt = conn.prepare_cached(
+        "SELECT user_id, name, amount FROM recurring_bill_shares WHERE recurring_id = ?1 ORDER BY rowid",
+    )?;
+
+    template.shares = stmt
+        .query_map(params![template.id], |row| {
+            Ok(TemplateShare {
+                user_id: UserId(row.get::<_, i64>(0)? as u64),
+                name: row.get(1)?,
+                amount: decimal_column(row, 2)?,
+            })
+        })?
+        .collect::<rusqlite::Result<_>>()?;
+
+    Ok(template)
+}
+
+fn decimal_column(row: &Row, idx: usize) -> rusqlite::Result<Decimal> {
+    let amount: String = row.get(idx)?;
+    Decimal::from_str_exact(&amount).map_err(|why| conversion_error(idx, Box::new(why)))
+}
+
+fn currency_column(row: &Row, idx: usize) -> rusqlite::Result<&'static Currency> {
+    let code: String = row.get(idx)?;
+    iso::find(&code)
+        .ok_or_else(|| conversion_error(idx, format!("unknown currency: {}", code).into()))
+}
+
+fn conversion_error(idx: usize, why: Box<dyn Error + Send + Sync>) -> rusqlite::Error {
+    rusqlite::Error::FromSqlConversionFailure(idx, Type::Text, why)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn store() -> SqliteStore {
+        SqliteStore::open(":memory:").unwrap()
+    }
+
+    fn at(day: u32) -> DateTime<Utc> {
+        chrono::TimeZone::with_ymd_and_hms(&Utc, 2026, 3, day, 12, 0, 0).unwrap()
+    }
+
+    /// Bills aren't comparable, but everything about them shows up in their debug output.
+    fn assert_same(got: &Bill, want: &Bill) {
+        assert_eq!(format!("{:?}", got), format!("{:?}", want));
+    }
+
+    fn ids(bills: &[Bill]) -> Vec<i64> {
+        bills.iter().map(|bill| bill.id).collect()
+    }
+
+    fn bill(guild: u64, creator: u64, shares: &[(u64, &str)]) -> Bill {
+        let shares: Vec<BillShare> = shares
+            .iter()
+            .map(|(user, amount)| BillShare {
+                user_id: UserId(*user),
+                name: format!("user {}", user),
+                amount: amount.parse().unwrap(),
+                status: if *user == creator {
+                    ShareStatus::Settled
+                } else {
+                    ShareStatus::Owed
+                },
+                claimed_at: None,
+                settled_at: None,
+            })
+            .collect();
+
+        Bill {
+            id: 0,
+            guild_id: GuildId(guild),
+            channel_id: ChannelId(10),
+            message_id: None,
+            creator_id: UserId(creator),
+            creator_name: format!("user {}", creator),
+            title: String::from("Groceries"),
+            amount: shares.iter().map(|share| share.amount).sum(),
+            currency: iso::CAD,
+            method: String::from("etransfer"),
+            created_at: Utc::now(),
+            due_at: None,
+            rate: None,
+            cancellation: None,
+            shares,
+            items: Vec::new(),
+        }
+    }
+
+    #[test]
+    fn editing_a_bill_forgets_reminders_of_removed_payers() {
+        let store = store();
+        let mut bill = bill(1, 1, &[(1, "10"), (2, "10"), (3, "10")]);
+        store.insert_bill(&mut bill).unwrap();
+        store
+            .record_reminder(bill.id, UserId(2), false, Utc::now())
+            .unwrap();
+        store
+            .record_reminder(bill.id, UserId(3), false, Utc::now())
+            .unwrap();
+
+        bill.shares.retain(|share| share.user_id != UserId(3));
+        store.update_bill(&bill).unwrap();
+
+        assert!(
+            store
+                .reminder_state(bill.id, UserId(2))
+                .unwrap()
+                .before_sent
+        );
+        assert!(
+            !store
+                .reminder_state(bill.id, UserId(3))
+                .unwrap()
+                .before_sent
+        );
+        let count: i64 = store
+            .conn
+            .lock()
+            .unwrap()
+            .query_row(
+                "SELECT COUNT(*) FROM share_reminders WHERE bill_id = ?1",
+                params![bill.id],
+                |row| row.get(0),
+            )
+            .unwrap();
+        assert_eq!(count, 1);
+    }
+
+    #[test]
+    fn migrations_run_from_scratch_once() {
+        let store = store();
+        let mut conn = store.conn.lock().unwrap();
+
+        let version: usize = conn
+            .pragma_query_value(None, "user_version", |row| row.get(0))
+            .unwrap();
+        assert_eq!(version, MIGRATIONS.len());
+
+        // reopening an up to date database applies nothing, or the ALTERs would fail
+        migrate(&mut conn).unwrap();
+    }
+
+    #[test]
+    fn bills_round_trip() {
+        let store = store();
+        let mut bill = bill(1, 1, &[(1, "10.50"), (2, "10.25"), (3, "10.25")]);
+        bill.due_at = Some(at(20));
+        bill.rate = Some(BillRate {
+            currency: iso::USD,
+            rate: "0.73".parse().unwrap(),
+        });
+        bill.items = vec![
+            BillItem {
+                name: String::from("Pizza"),
+                amount: "20.50".parse().unwrap(),
+                payers: vec![UserId(3), UserId(1)],
+            },
+            BillItem {
+                name: String::from("Wings"),
+                amount: "10.50".parse().unwrap(),
+                payers: vec![UserId(2)],
+            },
+        ];
+
+        store.insert_bill(&mut bill).unwrap();
+        assert_ne!(bill.id, 0);
+        assert_same(&store.get_bill(bill.id).unwrap().unwrap(), &bill);
+
+        store.set_message_id(bill.id, MessageId(99)).unwrap();
+        bill.message_id = Some(MessageId(99));
+        assert_same(&store.get_bill(bill.id).unwrap().unwrap(), &bill);
+
+        assert!(store.get_bill(bill.id + 1).unwrap().is_none());
+    }
+
+    #[test]
+    fn updates_replace_the_form_shares_and_items() {
+        let store = store();
+        let mut bill = bill(1, 1, &[(1, "10"), (2, "10")]);
+        bill.items = vec![BillItem {
+            name: String::from("Pizza"),
+            amount: "20".parse().unwrap(),
+            payers: vec![UserId(1), UserId(2)],
+        }];
+        store.insert_bill(&mut bill).unwrap();
+        store
+            .set_share_status(bill.id, UserId(2), ShareStatus::Claimed)
+            .unwrap();
+
+        bill.title = String::from("Groceries and pizza");
+        bill.amount = "45".parse().unwrap();
+        bill.method = String::from("cash");
+        bill.due_at = Some(at(10));
+        bill.shares = self::bill(1, 1, &[(1, "15"), (3, "30")]).shares;
+        bill.items.clear();
+        store.update_bill(&bill).unwrap();
+
+        assert_same(&store.get_bill(bill.id).unwrap().unwrap(), &bill);
+
+        bill.id += 1;
+        assert!(store.update_bill(&bill).is_err());
+    }
+
+    #[test]
+    fn bills_are_cancelled_once() {
+        let store = store();
+        let mut bill = bill(1, 1, &[(1, "10"), (2, "10")]);
+        store.insert_bill(&mut bill).unwrap();
+
+        let cancellation = Cancellation {
+            cancelled_by: UserId(1),
+            reason: String::from("entered twice"),
+            cancelled_at: at(2),
+        };
+        store.cancel_bill(bill.id, &cancellation).unwrap();
+        assert!(store.cancel_bill(bill.id, &cancellation).is_err());
+
+        bill.cancellation = Some(cancellation);
+        assert_same(&store.get_bill(bill.id).unwrap().unwrap(), &bill);
+        assert!(store.open_bills(bill.guild_id).unwrap().is_empty());
+    }
+
+    #[test]
+    fn deleting_a_bill_removes_its_shares_and_items() {
+        let store = store();
+        let mut bill = bill(1, 1, &[(1, "10"), (2, "10")]);
+        bill.items = vec![BillItem {
+            name: String::from("Pizza"),
+            amount: "20".parse().unwrap(),
+            payers: vec![UserId(1), UserId(2)],
+        }];
+        store.insert_bill(&mut bill).unwrap();
+        let mut kept = self::bill(1, 1, &[(1, "5"), (2, "5")]);
+        store.insert_bill(&mut kept).unwrap();
+
+        store.delete_bill(bill.id).unwrap();
+
+        assert!(store.get_bill(bill.id).unwrap().is_none());
+        assert_eq!(ids(&store.open_bills(GuildId(1)).unwrap()), vec![kept.id]);
+        let conn = store.conn.lock().unwrap();
+        for table in ["bill_shares", "bill_items"] {
+            let count: i64 = conn
+                .query_row(
+                    &format!("SELECT COUNT(*) FROM {} WHERE bill_id = ?1", table),
+                    params![bill.id],
+                    |row| row.get(0),
+                )
+                .unwrap();
+            assert_eq!(count, 0, "{} left behind", table);
+        }
+    }
+
+    #[test]
+    fn listing_combines_every_filter() {
+        let store = store();
+        let insert = |guild: u64, creator: u64, title: &str, day: u32, shares| {
+            let mut bill = bill(guild, creator, shares);
+            bill.title = String::from(title);
+            bill.created_at = at(day);
+            store.insert_bill(&mut bill).unwrap();
+            bill.id
+        };
+
+        let rent = insert(1, 1, "Rent", 1, &[(1, "500"), (2, "500")]);
+        let groceries = insert(1, 1, "Groceries", 2, &[(1, "20"), (3, "20")]);
+        let pizza = insert(1, 2, "Pizza night", 3, &[(2, "10"), (1, "10")]);
+        let cancelled = insert(1, 2, "Pizza again", 4, &[(2, "10"), (3, "10")]);
+        insert(2, 1, "Rent", 5, &[(1, "500"), (2, "500")]);
+
+        store
+            .set_share_status(groceries, UserId(3), ShareStatus::Settled)
+            .unwrap();
+        store
+            .cancel_bill(
+                cancelled,
+                &Cancellation {
+                    cancelled_by: UserId(2),
+                    reason: String::from("nobody came"),
+                    cancelled_at: at(4),
+                },
+            )
+            .unwrap();
+
+        let list = |filter: BillFilter| {
+            let (bills, total) = store.list_bills(GuildId(1), &filter, 0, 10).unwrap();
+            assert_eq!(bills.len(), total);
+            ids(&bills)
+        };
+
+        // newest first, and only the guild's own bills
+        assert_eq!(
+            list(BillFilter::default()),
+            vec![cancelled, pizza, groceries, rent]
+        );
+        assert_eq!(
+            list(BillFilter {
+                status: Some(BillStatus::Open),
+                ..Default::default()
+            }),
+            vec![pizza, rent]
+        );
+        assert_eq!(
+            list(BillFilter {
+                status: Some(BillStatus::Settled),
+                ..Default::default()
+            }),
+            vec![groceries]
+        );
+        assert_eq!(
+            list(BillFilter {
+                status: Some(BillStatus::Cancelled),
+                ..Default::default()
+            }),
+            vec![cancelled]
+        );
+        assert_eq!(
+            list(BillFilter {
+                creator: Some(UserId(2)),
+                ..Default::default()
+            }),
+            vec![cancelled, pizza]
+        );
+        // creators aren't payers of their own bills
+        assert_eq!(
+            list(BillFilter {
+                payer: Some(UserId(2)),
+                ..Default::default()
+            }),
+            vec![rent]
+        );
+        assert_eq!(
+            list(BillFilter {
+                from: Some(at(2)),
+                until: Some(at(4)),
+                ..Default::default()
+            }),
+            vec![pizza, groceries]
+        );
+        assert_eq!(
+            list(BillFilter {
+                title: Some(String::from("PIZZA")),
+                status: Some(BillStatus::Open),
+                payer: Some(UserId(1)),
+                ..Default::default()
+            }),
+            vec![pizza]
+        );
+        assert!(list(BillFilter {
+            title: Some(String::from("Rent")),
+            creator: Some(UserId(2)),
+            ..Default::default()
+        })
+        .is_empty());
+    }
+
+    #[test]
+    fn listing_pages_through_the_total() {
+        let store = store();
+        for day in 1..=5 {
+            let mut bill = bill(1, 1, &[(1, "10"), (2, "10")]);
+            bill.created_at = at(day);
+            store.insert_bill(&mut bill).unwrap();
+        }
+
+        let filter = BillFilter::default();
+        let (first, total) = store.list_bills(GuildId(1), &filter, 0, 2).unwrap();
+        let (last, _) = store.list_bills(GuildId(1), &filter, 4, 2).unwrap();
+
+        assert_eq!(total, 5);
+        assert_eq!(ids(&first), vec![5, 4]);
+        assert_eq!(ids(&last), vec![1]);
+    }
+}